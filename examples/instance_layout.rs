@@ -0,0 +1,171 @@
+//! Measures the instance upload of a full screen grid with the full and compact instance layouts,
+//! and as a GPU driven grid, in a static, a scrolling and an animated scene.
+//!
+//! The uploaded bytes are read from the `TextModeInstanceStats` resource, averaged over a few frames
+//! per scene, and printed as a table before exiting.
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+
+use bevy_text_mode::{
+    TextModeCell, TextModeGrid, TextModeGridBundle, TextModeInstanceLayout, TextModeInstanceStats, TextModePlugin,
+};
+
+const COLUMNS: usize = 240;
+const ROWS: usize = 135;
+/// Frames drawn before measuring a scene, the statistics being one frame late with pipelined rendering
+const WARMUP_FRAMES: u32 = 10;
+const MEASURED_FRAMES: u32 = 60;
+
+#[derive(Debug, Clone, Copy)]
+enum Scene {
+    /// Cells are written once
+    Static,
+    /// A new row is scrolled in every frame
+    Scroll,
+    /// Every cell is rewritten every frame
+    Animated,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Mode {
+    Full,
+    Compact,
+    GpuDriven,
+}
+
+#[derive(Resource, Default)]
+struct Benchmark {
+    run: usize,
+    frame: u32,
+    uploaded_bytes: usize,
+    uploaded_instances: usize,
+    writes: usize,
+}
+
+const RUNS: [(Scene, Mode); 9] = [
+    (Scene::Static, Mode::Full),
+    (Scene::Static, Mode::Compact),
+    (Scene::Static, Mode::GpuDriven),
+    (Scene::Scroll, Mode::Full),
+    (Scene::Scroll, Mode::Compact),
+    (Scene::Scroll, Mode::GpuDriven),
+    (Scene::Animated, Mode::Full),
+    (Scene::Animated, Mode::Compact),
+    (Scene::Animated, Mode::GpuDriven),
+];
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins.set(ImagePlugin::default_nearest()))
+        .add_plugins(TextModePlugin)
+        .insert_resource(ClearColor(Color::BLACK))
+        .init_resource::<Benchmark>()
+        .add_systems(Startup, init)
+        .add_systems(Update, (measure, animate).chain())
+        .run();
+}
+
+fn init(
+    mut commands: Commands,
+    server: Res<AssetServer>,
+    mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+) {
+    let layout = TextureAtlasLayout::from_grid(UVec2::new(8, 8), 7, 1, None, None);
+    let mut grid = TextModeGrid::new(COLUMNS, ROWS, texture_atlas_layouts.add(layout));
+    grid.anchor = bevy::sprite::Anchor::Center;
+
+    commands.spawn(Camera2dBundle::default());
+    commands.spawn(TextModeGridBundle {
+        grid,
+        texture: server.load("texmod.png"),
+        ..default()
+    });
+
+    println!(
+        "{:<10} {:<10} {:>14} {:>14} {:>8}",
+        "scene", "layout", "bytes/frame", "cells/frame", "writes"
+    );
+}
+
+/// Accumulates the statistics of the current run, and sets up the next run when done
+fn measure(
+    mut benchmark: ResMut<Benchmark>,
+    mut layout: ResMut<TextModeInstanceLayout>,
+    mut grids: Query<&mut TextModeGrid>,
+    stats: Res<TextModeInstanceStats>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let (scene, mode) = RUNS[benchmark.run];
+    if benchmark.frame == 0 {
+        *layout = match mode {
+            Mode::Compact => TextModeInstanceLayout::Compact,
+            Mode::Full | Mode::GpuDriven => TextModeInstanceLayout::Full,
+        };
+        for mut grid in &mut grids {
+            grid.gpu_driven = matches!(mode, Mode::GpuDriven);
+            for (i, cell) in grid.cells_mut().iter_mut().enumerate() {
+                *cell = cell_at(i % COLUMNS, i / COLUMNS, 0.0);
+            }
+        }
+    } else if benchmark.frame > WARMUP_FRAMES {
+        benchmark.uploaded_bytes += stats.uploaded_bytes();
+        benchmark.uploaded_instances += stats.uploaded_instances();
+        benchmark.writes += stats.writes();
+    }
+
+    benchmark.frame += 1;
+    if benchmark.frame <= WARMUP_FRAMES + MEASURED_FRAMES {
+        return;
+    }
+
+    let frames = MEASURED_FRAMES as usize;
+    println!(
+        "{:<10} {:<10} {:>14} {:>14} {:>8}",
+        format!("{scene:?}"),
+        format!("{mode:?}"),
+        benchmark.uploaded_bytes / frames,
+        benchmark.uploaded_instances / frames,
+        benchmark.writes / frames,
+    );
+    *benchmark = Benchmark {
+        run: benchmark.run + 1,
+        ..default()
+    };
+    if benchmark.run == RUNS.len() {
+        exit.send(AppExit::Success);
+    }
+}
+
+fn animate(mut grids: Query<&mut TextModeGrid>, benchmark: Res<Benchmark>, time: Res<Time>) {
+    let Some(&(scene, _)) = RUNS.get(benchmark.run) else {
+        return;
+    };
+    let t = time.elapsed_seconds();
+    for mut grid in &mut grids {
+        match scene {
+            Scene::Static => {}
+            Scene::Scroll => {
+                grid.scroll_up(1, TextModeCell::default());
+                for x in 0..COLUMNS {
+                    grid.set(x, ROWS - 1, cell_at(x, ROWS - 1, t));
+                }
+            }
+            Scene::Animated => {
+                for (i, cell) in grid.cells_mut().iter_mut().enumerate() {
+                    *cell = cell_at(i % COLUMNS, i / COLUMNS, t);
+                }
+            }
+        }
+    }
+}
+
+fn cell_at(x: usize, y: usize, t: f32) -> TextModeCell {
+    let hue = (x as f32 * 2.0 + y as f32 * 3.0 + t * 90.0) % 360.0;
+    TextModeCell {
+        index: (x + y + (t * 4.0) as usize) % 7,
+        bg: Color::BLACK.to_linear(),
+        fg: Color::hsl(hue, 0.8, 0.6).to_linear(),
+        ..default()
+    }
+}
@@ -0,0 +1,91 @@
+//! Draws a grid lying on the ground and billboard sprites with a 3D camera orbiting around them.
+
+use bevy::prelude::*;
+
+use bevy_text_mode::{
+    TextMode3d, TextModeBillboard, TextModeCell, TextModeGrid, TextModeGridBundle, TextModePlugin, TextModeSprite,
+    TextModeSpriteBundle,
+};
+
+const SIZE: usize = 16;
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins.set(ImagePlugin::default_nearest()))
+        .add_plugins(TextModePlugin)
+        .insert_resource(ClearColor(Color::BLACK))
+        .add_systems(Startup, init)
+        .add_systems(Update, orbit)
+        .run();
+}
+
+fn init(
+    mut commands: Commands,
+    server: Res<AssetServer>,
+    mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+) {
+    let tileset: Handle<Image> = server.load("texmod.png");
+    let layout = texture_atlas_layouts.add(TextureAtlasLayout::from_grid(UVec2::new(8, 8), 7, 1, None, None));
+
+    commands.spawn(Camera3dBundle::default());
+
+    // A checkerboard floor, written to the depth buffer so that it hides the sprites below it
+    let mut grid = TextModeGrid::new(SIZE, SIZE, layout.clone());
+    grid.anchor = bevy::sprite::Anchor::Center;
+    for (i, cell) in grid.cells_mut().iter_mut().enumerate() {
+        let dark = (i % SIZE + i / SIZE) & 1 == 0;
+        *cell = TextModeCell {
+            index: 3,
+            bg: if dark { Color::srgb(0.1, 0.1, 0.2) } else { Color::srgb(0.2, 0.2, 0.4) }.to_linear(),
+            fg: Color::srgb(0.3, 0.3, 0.6).to_linear(),
+            ..default()
+        };
+    }
+    commands.spawn((
+        TextModeGridBundle {
+            grid,
+            texture: tileset.clone(),
+            transform: Transform::from_rotation(Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2)),
+            ..default()
+        },
+        TextMode3d {
+            depth_write: true,
+            ..default()
+        },
+    ));
+
+    // Signs standing on the floor, their background discarded
+    for (i, billboard) in [TextModeBillboard::Spherical, TextModeBillboard::Cylindrical, TextModeBillboard::None]
+        .into_iter()
+        .enumerate()
+    {
+        commands.spawn((
+            TextModeSpriteBundle {
+                sprite: TextModeSprite {
+                    fg: Color::hsl(120.0 * i as f32, 0.8, 0.6).to_linear(),
+                    skip_bg: true,
+                    anchor: bevy::sprite::Anchor::BottomCenter,
+                    ..default()
+                },
+                atlas: TextureAtlas {
+                    layout: layout.clone(),
+                    index: i,
+                },
+                texture: tileset.clone(),
+                transform: Transform::from_xyz(24.0 * (i as f32 - 1.0), 0.0, 0.0).with_scale(Vec3::splat(2.0)),
+                ..default()
+            },
+            TextMode3d {
+                billboard,
+                depth_write: true,
+            },
+        ));
+    }
+}
+
+fn orbit(mut cameras: Query<&mut Transform, With<Camera3d>>, time: Res<Time>) {
+    let angle = time.elapsed_seconds() * 0.3;
+    for mut transform in &mut cameras {
+        *transform = Transform::from_xyz(120.0 * angle.sin(), 60.0, 120.0 * angle.cos()).looking_at(Vec3::ZERO, Vec3::Y);
+    }
+}
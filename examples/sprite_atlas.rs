@@ -1,7 +1,7 @@
 use bevy::prelude::*;
 use bevy::sprite::Anchor;
 
-use bevy_text_mode::{TextModePlugin, TextModeSprite, TextModeSpriteBundle};
+use bevy_text_mode::{TextModePalette, TextModePaletteIndices, TextModePlugin, TextModeSprite, TextModeSpriteBundle};
 
 const WIDTH: f32 = 8. * 8. * 8.;
 const HEIGHT: f32 = 7. * 8. * 8.;
@@ -25,54 +25,37 @@ fn main() {
         .run();
 }
 
-enum Dark {
-    BLACK,
-    BLUE,
-    GREEN,
-    ORANGE,
-    PINK,
-}
-
-impl Into<LinearRgba> for Dark {
-    fn into(self) -> LinearRgba {
-        match self {
-            Dark::BLACK => LinearRgba::from(Srgba::BLACK),
-            Dark::BLUE => LinearRgba::from(Srgba::hex("305182").unwrap()),
-            Dark::GREEN => LinearRgba::from(Srgba::hex("386900").unwrap()),
-            Dark::ORANGE => LinearRgba::from(Srgba::hex("a23000").unwrap()),
-            Dark::PINK => LinearRgba::from(Srgba::hex("9a2079").unwrap()),
-        }
-    }
-}
-
-enum Light {
-    WHITE,
-    BLUE,
-    GREEN,
-    ORANGE,
-    PINK,
-}
-
-impl Into<LinearRgba> for Light {
-    fn into(self) -> LinearRgba {
-        match self {
-            Light::WHITE => LinearRgba::from(Srgba::WHITE),
-            Light::BLUE => LinearRgba::from(Srgba::hex("a2fff3").unwrap()),
-            Light::GREEN => LinearRgba::from(Srgba::hex("cbf382").unwrap()),
-            Light::ORANGE => LinearRgba::from(Srgba::hex("ffcbba").unwrap()),
-            Light::PINK => LinearRgba::from(Srgba::hex("e3b2ff").unwrap()),
-        }
+fn palette() -> TextModePalette {
+    let mut palette = TextModePalette::default();
+    for (name, hex) in [
+        ("black", "000000"),
+        ("dark_blue", "305182"),
+        ("dark_green", "386900"),
+        ("dark_orange", "a23000"),
+        ("dark_pink", "9a2079"),
+        ("white", "ffffff"),
+        ("light_blue", "a2fff3"),
+        ("light_green", "cbf382"),
+        ("light_orange", "ffcbba"),
+        ("light_pink", "e3b2ff"),
+    ] {
+        palette.push_named(name, LinearRgba::from(Srgba::hex(hex).unwrap()));
     }
+    palette
 }
 
 fn init(
     mut commands: Commands,
     server: Res<AssetServer>,
     mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+    mut palettes: ResMut<Assets<TextModePalette>>,
 ) {
     let tileset: Handle<Image> = server.load("texmod.png");
     let layout = TextureAtlasLayout::from_grid(UVec2::new(8, 8), 7, 1, None, None);
     let handle = texture_atlas_layouts.add(layout);
+    let palette = palette();
+    let color = |name: &str| palette.index_of(name).unwrap();
+    let palette_handle = palettes.add(palette.clone());
 
     commands.spawn(Camera2dBundle {
         transform: Transform {
@@ -84,31 +67,37 @@ fn init(
     });
 
     for (x, y, i, bg, fg) in [
-        (1, 1, 0, Light::WHITE.into(), Dark::BLUE.into()),
-        (2, 1, 1, Light::GREEN.into(), Dark::GREEN.into()),
-        (3, 1, 2, Light::WHITE.into(), Dark::ORANGE.into()),
-        (4, 1, 0, Light::PINK.into(), Dark::PINK.into()),
+        (1, 1, 0, color("white"), color("dark_blue")),
+        (2, 1, 1, color("light_green"), color("dark_green")),
+        (3, 1, 2, color("white"), color("dark_orange")),
+        (4, 1, 0, color("light_pink"), color("dark_pink")),
 
-        (3, 2, 3, Light::BLUE.into(), Dark::BLUE.into()),
-        (4, 2, 4, Light::WHITE.into(), Dark::GREEN.into()),
-        (5, 2, 5, Light::ORANGE.into(), Dark::ORANGE.into()),
-        (6, 2, 1, Light::WHITE.into(), Dark::PINK.into()),
+        (3, 2, 3, color("light_blue"), color("dark_blue")),
+        (4, 2, 4, color("white"), color("dark_green")),
+        (5, 2, 5, color("light_orange"), color("dark_orange")),
+        (6, 2, 1, color("white"), color("dark_pink")),
     ] {
-        commands.spawn(TextModeSpriteBundle {
-            sprite: TextModeSprite {
+        commands.spawn((
+            TextModeSpriteBundle {
+                sprite: TextModeSprite {
+                    anchor: Anchor::TopLeft,
+                    ..default()
+                },
+                atlas: TextureAtlas {
+                    layout: handle.clone(),
+                    index: i,
+                },
+                texture: tileset.clone(),
+                transform: Transform::from_xyz(8. * x as f32, -8. * y as f32, 0.),
+                ..default()
+            },
+            TextModePaletteIndices {
+                palette: palette_handle.clone(),
                 bg,
                 fg,
-                anchor: Anchor::TopLeft,
                 ..default()
             },
-            atlas: TextureAtlas {
-                layout: handle.clone(),
-                index: i,
-            },
-            texture: tileset.clone(),
-            transform: Transform::from_xyz(8. * x as f32, -8. * y as f32, 0.),
-            ..default()
-        });
+        ));
     }
 
     for (x, y, i, flip_x, flip_y, rotation) in [
@@ -124,8 +113,8 @@ fn init(
     ] {
         commands.spawn(TextModeSpriteBundle {
             sprite: TextModeSprite {
-                bg: Light::WHITE.into(),
-                fg: Dark::BLACK.into(),
+                bg: palette.get_named("white").unwrap(),
+                fg: palette.get_named("black").unwrap(),
                 flip_x,
                 flip_y,
                 rotation,
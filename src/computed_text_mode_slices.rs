@@ -5,12 +5,49 @@ use bevy_sprite::ImageScaleMode;
 use crate::plugin::TextModeExtractedSprite;
 use crate::TextModeSprite;
 
+/// Flip and rotation of a tile of a [`TextModeTilePattern`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+pub struct TextModeTile {
+    pub flip_x: bool,
+    pub flip_y: bool,
+    /// Rotation in quarter turns
+    pub rotation: u8,
+}
+
+/// Flips and rotates the tiles of a sprite with an [`ImageScaleMode::Tiled`] scale mode.
+///
+/// The pattern is repeated over the tiles, row by row from the top left tile, and is combined
+/// with the flip and rotation of the sprite.
+/// Rotations of a quarter turn should be used with square tiles fitting whole in the sprite size.
+#[derive(Component, Debug, Clone, Default, Reflect)]
+pub struct TextModeTilePattern {
+    pub tiles: Vec<TextModeTile>,
+    /// Number of tiles per pattern row
+    pub columns: usize,
+}
+
+impl TextModeTilePattern {
+    /// Tile of the pattern at column `x` and row `y` of the sprite tiles
+    fn tile(&self, x: usize, y: usize) -> TextModeTile {
+        let columns = self.columns.max(1);
+        let rows = self.tiles.len().div_ceil(columns).max(1);
+        self.tiles
+            .get((y % rows) * columns + x % columns)
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
 /// Component storing texture slices for sprite entities with a [`ImageScaleMode`]
 ///
 /// This component is automatically inserted and updated
 /// See [bevy_sprite::ComputedTextureSlices]
 #[derive(Debug, Clone, Component)]
-pub struct ComputedTextModeTextureSlices(Vec<TextureSlice>);
+pub struct ComputedTextModeTextureSlices {
+    slices: Vec<TextureSlice>,
+    /// Tile of each slice, empty without a [`TextModeTilePattern`]
+    tiles: Vec<TextModeTile>,
+}
 
 impl ComputedTextModeTextureSlices {
     /// Computes [`TextModeExtractedSprite`] iterator from the sprite slices
@@ -18,14 +55,12 @@ impl ComputedTextModeTextureSlices {
     /// # Arguments
     ///
     /// * `transform` - the sprite entity global transform
-    /// * `original_entity` - the sprite entity
     /// * `sprite` - The sprite component
     /// * `handle` - The sprite texture handle
     #[must_use]
     pub(crate) fn extract_text_mode_sprites<'a>(
         &'a self,
         transform: &'a GlobalTransform,
-        original_entity: Entity,
         sprite: &'a TextModeSprite,
         handle: &'a Handle<Image>,
     ) -> impl ExactSizeIterator<Item = TextModeExtractedSprite> + 'a {
@@ -39,22 +74,34 @@ impl ComputedTextModeTextureSlices {
             flip.y *= -1.0;
             flip_y = true;
         }
-        self.0.iter().map(move |slice| {
+        let (palette, palette_len) = sprite.palette.padded();
+        self.slices.iter().enumerate().map(move |(i, slice)| {
+            let tile = self.tiles.get(i).copied().unwrap_or_default();
             let offset = (slice.offset * flip).extend(0.0);
             let transform = transform.mul_transform(Transform::from_translation(offset));
             TextModeExtractedSprite {
-                original_entity: Some(original_entity),
                 bg: sprite.bg,
                 fg: sprite.fg,
                 alpha: sprite.alpha,
                 transform,
                 rect: Some(slice.texture_rect),
+                glyph: None,
                 custom_size: Some(slice.draw_size),
-                flip_x,
-                flip_y,
-                rotation: sprite.rotation,
+                flip_x: flip_x ^ tile.flip_x,
+                flip_y: flip_y ^ tile.flip_y,
+                rotation: sprite.rotation.wrapping_add(tile.rotation) % 4,
                 image_handle_id: handle.id(),
                 anchor: Self::redepend_anchor_from_sprite_to_slice(sprite, slice),
+                palette,
+                palette_len,
+                mask: sprite.mask,
+                invert_mask: sprite.invert_mask,
+                skip_bg: sprite.skip_bg,
+                skip_fg: sprite.skip_fg,
+                coverage: sprite.coverage,
+                blink: sprite.blink,
+                cursor: None,
+                mode_3d: None,
             }
         })
     }
@@ -80,27 +127,26 @@ impl ComputedTextModeTextureSlices {
 ///
 /// * `sprite` - The text mode sprite component, will be used to find the draw area size
 /// * `scale_mode` - The image scaling component
+/// * `pattern` - Optional tile pattern, flipping and rotating the tiles of a tiled sprite
 /// * `image_handle` - The texture to slice or tile
 /// * `images` - The image assets, use to retrieve the image dimensions
 /// * `atlas` - Optional texture atlas, if set the slicing will happen on the matching sub section
-/// of the texture
+///   of the texture
 /// * `atlas_layouts` - The atlas layout assets, used to retrieve the texture atlas section rect
 #[must_use]
 fn compute_text_mode_sprite_slices(
     sprite: &TextModeSprite,
     scale_mode: &ImageScaleMode,
+    pattern: Option<&TextModeTilePattern>,
     image_handle: &Handle<Image>,
     images: &Assets<Image>,
     atlas: Option<&TextureAtlas>,
     atlas_layouts: &Assets<TextureAtlasLayout>,
 ) -> Option<ComputedTextModeTextureSlices> {
-    let (image_size, texture_rect) = match atlas {
+    let texture_rect = match atlas {
         Some(a) => {
             let layout = atlas_layouts.get(&a.layout)?;
-            (
-                layout.size.as_vec2(),
-                layout.textures.get(a.index)?.as_rect(),
-            )
+            layout.textures.get(a.index)?.as_rect()
         }
         None => {
             let image = images.get(image_handle)?;
@@ -108,13 +154,13 @@ fn compute_text_mode_sprite_slices(
                 image.texture_descriptor.size.width as f32,
                 image.texture_descriptor.size.height as f32,
             );
-            let rect = sprite.rect.unwrap_or(Rect {
+            sprite.rect.unwrap_or(Rect {
                 min: Vec2::ZERO,
                 max: size,
-            });
-            (size, rect)
+            })
         }
     };
+    let mut tiles = Vec::new();
     let slices = match scale_mode {
         ImageScaleMode::Sliced(slicer) => slicer.compute_slices(texture_rect, sprite.custom_size),
         ImageScaleMode::Tiled {
@@ -122,19 +168,32 @@ fn compute_text_mode_sprite_slices(
             tile_y,
             stretch_value,
         } => {
+            // The tile unit is the atlas cell, or the sprite rect, not the whole texture
+            let draw_size = sprite.custom_size.unwrap_or(texture_rect.size());
             let slice = TextureSlice {
                 texture_rect,
-                draw_size: sprite.custom_size.unwrap_or(image_size),
+                draw_size,
                 offset: Vec2::ZERO,
             };
-            slice.tiled(*stretch_value, (*tile_x, *tile_y))
+            let slices = slice.tiled(*stretch_value, (*tile_x, *tile_y));
+            if let Some(pattern) = pattern.filter(|p| !p.tiles.is_empty()) {
+                // Slices are generated row by row from the top left tile
+                let tile_width = texture_rect.width() * stretch_value.max(0.001);
+                let columns = match tile_x {
+                    true => (draw_size.x / tile_width.max(1.0)).ceil().max(1.0) as usize,
+                    false => 1,
+                };
+                tiles = (0..slices.len()).map(|i| pattern.tile(i % columns, i / columns)).collect();
+            }
+            slices
         }
     };
-    Some(ComputedTextModeTextureSlices(slices))
+    Some(ComputedTextModeTextureSlices { slices, tiles })
 }
 
 /// System reacting to added or modified [`Image`] handles, and recompute sprite slices
 /// on matching sprite entities with a [`ImageScaleMode`] component
+#[allow(clippy::type_complexity)]
 pub(crate) fn compute_text_mode_slices_on_asset_event(
     mut commands: Commands,
     mut events: EventReader<AssetEvent<Image>>,
@@ -143,6 +202,7 @@ pub(crate) fn compute_text_mode_slices_on_asset_event(
     sprites: Query<(
         Entity,
         &ImageScaleMode,
+        Option<&TextModeTilePattern>,
         &TextModeSprite,
         &Handle<Image>,
         Option<&TextureAtlas>,
@@ -160,13 +220,14 @@ pub(crate) fn compute_text_mode_slices_on_asset_event(
         return;
     }
     // We recompute the sprite slices for sprite entities with a matching asset handle id
-    for (entity, scale_mode, sprite, image_handle, atlas) in &sprites {
+    for (entity, scale_mode, pattern, sprite, image_handle, atlas) in &sprites {
         if !added_handles.contains(&image_handle.id()) {
             continue;
         }
         if let Some(slices) = compute_text_mode_sprite_slices(
             sprite,
             scale_mode,
+            pattern,
             image_handle,
             &images,
             atlas,
@@ -179,6 +240,7 @@ pub(crate) fn compute_text_mode_slices_on_asset_event(
 
 /// System reacting to changes on relevant sprite bundle components to compute the sprite slices
 /// on matching sprite entities with a [`ImageScaleMode`] component
+#[allow(clippy::type_complexity)]
 pub(crate) fn compute_text_mode_slices_on_sprite_change(
     mut commands: Commands,
     images: Res<Assets<Image>>,
@@ -187,22 +249,25 @@ pub(crate) fn compute_text_mode_slices_on_sprite_change(
         (
             Entity,
             &ImageScaleMode,
+            Option<&TextModeTilePattern>,
             &TextModeSprite,
             &Handle<Image>,
             Option<&TextureAtlas>,
         ),
         Or<(
             Changed<ImageScaleMode>,
+            Changed<TextModeTilePattern>,
             Changed<Handle<Image>>,
             Changed<TextModeSprite>,
             Changed<TextureAtlas>,
         )>,
     >,
 ) {
-    for (entity, scale_mode, sprite, image_handle, atlas) in &changed_sprites {
+    for (entity, scale_mode, pattern, sprite, image_handle, atlas) in &changed_sprites {
         if let Some(slices) = compute_text_mode_sprite_slices(
             sprite,
             scale_mode,
+            pattern,
             image_handle,
             &images,
             atlas,
@@ -1,8 +1,46 @@
-pub use plugin::TextModePlugin;
+pub use plugin::{TextModeInstanceLayout, TextModePlugin};
+pub use text_mode_charset::{AsciiCharset, Charset, CP437, Cp437Charset, TextModeCharset, TextModeSpritePrinter};
+pub use text_mode_palette::{TextModePalette, TextModePaletteIndices};
+pub use text_mode_palette_loader::{GplPaletteLoader, HexPaletteLoader, JascPaletteLoader, LospecPaletteLoader, PaintNetPaletteLoader, TextModePaletteLoaderError};
+pub use text_mode_ansi::{ansi_color, AnsiAction, AnsiAttributes, AnsiColor, AnsiParser};
+pub use text_mode_canvas::{TextModeCanvas, TextModeLayeredCanvas};
+pub use text_mode_canvas_loader::{AnsiArtLoader, BinLoader, RexPaintLoader, TextModeCanvasLoaderError, XBinLoader};
+pub use text_mode_font::TextModeFont;
+pub use text_mode_font_loader::{BdfFontLoader, PsfFontLoader, TextModeFontLoaderError};
+pub use text_mode_sauce::Sauce;
+pub use text_mode_terminal::{TextModeTerminal, TextModeTerminalBundle};
+pub use text_mode_blink::{TextModeBlink, TextModeBlinkTimer};
+pub use text_mode_grid::{TextModeCell, TextModeCursor, TextModeCursorShape, TextModeGrid, TextModeGridBundle};
+pub use text_mode_box::{TextModeBox, TextModeBoxStyle};
+pub use text_mode_nine_slice::{TextModeNineSlice, TextModeNineSliceBundle};
+pub use text_mode_markup::{TextModeMarkup, TextModeMarkupError, TextModeStyle};
+pub use text_mode_text_layout::{TextModeAlign, TextModeLaidOutText, TextModeTextLayout, TextModeWrap};
+pub use computed_text_mode_slices::{TextModeTile, TextModeTilePattern};
+pub use text_mode_instance_buffer::TextModeInstanceStats;
+pub use text_mode_3d::{TextMode3d, TextModeBillboard};
+pub use text_mode_texture_atlas::TextModeMask;
 pub use text_mode_texture_atlas::TextModeSprite;
+pub use text_mode_texture_atlas::{TextModeSpritePalette, MAX_SPRITE_PALETTE_LEN};
 pub use text_mode_texture_atlas::TextModeSpriteBundle;
 
 mod plugin;
 mod text_mode_texture_atlas;
 mod computed_text_mode_slices;
-
+mod text_mode_grid;
+mod text_mode_charset;
+mod text_mode_palette;
+mod text_mode_palette_loader;
+mod text_mode_ansi;
+mod text_mode_canvas;
+mod text_mode_canvas_loader;
+mod text_mode_sauce;
+mod text_mode_font;
+mod text_mode_font_loader;
+mod text_mode_terminal;
+mod text_mode_blink;
+mod text_mode_text_layout;
+mod text_mode_markup;
+mod text_mode_box;
+mod text_mode_nine_slice;
+mod text_mode_instance_buffer;
+mod text_mode_3d;
@@ -3,6 +3,7 @@ use std::ops::Range;
 
 use bevy::asset::load_internal_asset;
 use bevy::core_pipeline::core_2d::Transparent2d;
+use bevy::core_pipeline::core_3d::{Transparent3d, CORE_3D_DEPTH_FORMAT};
 use bevy::core_pipeline::tonemapping::{DebandDither, get_lut_bind_group_layout_entries, get_lut_bindings, Tonemapping, TonemappingLuts};
 use bevy::ecs::entity::EntityHashMap;
 use bevy::ecs::query::ROQueryItem;
@@ -11,23 +12,34 @@ use bevy::ecs::system::lifetimeless::{Read, SRes};
 use bevy::math::{Affine3A, FloatOrd};
 use bevy::prelude::*;
 use bevy::render::{Extract, Render, RenderApp, RenderSet};
+use bevy::render::extract_resource::{ExtractResource, ExtractResourcePlugin};
 use bevy::render::mesh::PrimitiveTopology;
 use bevy::render::render_asset::RenderAssets;
 use bevy::render::render_phase::*;
-use bevy::render::render_resource::{BindGroup, BindGroupEntries, BindGroupLayout, BindGroupLayoutDescriptor, BindGroupLayoutEntries, BindGroupLayoutEntry, BindingType, BlendState, BufferBindingType, BufferUsages, BufferVec, ColorTargetState, ColorWrites, FragmentState, FrontFace, ImageCopyTexture, ImageDataLayout, IndexFormat, MultisampleState, Origin3d, PipelineCache, PolygonMode, PrimitiveState, RawBufferVec, RenderPipelineDescriptor, SamplerBindingType, ShaderDefVal, ShaderStages, ShaderType, SpecializedRenderPipeline, SpecializedRenderPipelines, TextureAspect, TextureFormat, TextureSampleType, TextureViewDescriptor, TextureViewDimension, VertexAttribute, VertexBufferLayout, VertexFormat, VertexState, VertexStepMode};
-use bevy::render::render_resource::binding_types::{sampler, texture_2d, uniform_buffer};
-use bevy::render::render_resource::VertexFormat::Float32;
+use bevy::render::render_resource::{BindGroup, BindGroupEntries, BindGroupLayout, BindGroupLayoutEntries, BlendState, BufferUsages, CachedRenderPipelineId, ColorTargetState, ColorWrites, CompareFunction, DepthBiasState, DepthStencilState, DynamicUniformBuffer, FragmentState, Extent3d, FrontFace, IndexFormat, MultisampleState, PipelineCache, PolygonMode, PrimitiveState, RawBufferVec, RenderPipelineDescriptor, SamplerBindingType, ShaderDefVal, ShaderStages, SpecializedRenderPipeline, SpecializedRenderPipelines, StencilState, Texture, TextureDataOrder, TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType, TextureUsages, TextureViewDescriptor, UniformBuffer, VertexAttribute, VertexBufferLayout, VertexFormat, VertexState, VertexStepMode};
+use bevy::render::render_resource::binding_types::{sampler, storage_buffer_read_only_sized, texture_2d, uniform_buffer};
 use bevy::render::renderer::{RenderDevice, RenderQueue};
-use bevy::render::texture::{BevyDefault, DefaultImageSampler, FallbackImage, GpuImage, ImageSampler, TextureFormatPixelInfo};
+use bevy::render::texture::{BevyDefault, FallbackImage, GpuImage};
 use bevy::render::view::{check_visibility, ExtractedView, ViewTarget, ViewUniform, ViewUniformOffset, ViewUniforms, VisibilitySystems, VisibleEntities};
 use bevy::sprite::{queue_material2d_meshes, SpriteAssetEvents, SpriteSystem};
-use bevy::utils::HashMap;
-use bevy_sprite::{calculate_bounds_2d, SpriteSource, SpriteViewBindGroup, WithMesh2d, WithSprite};
+use bevy::utils::{HashMap, HashSet};
+use bevy_sprite::calculate_bounds_2d;
 use bytemuck::{Pod, Zeroable};
 use fixedbitset::FixedBitSet;
 
 use crate::computed_text_mode_slices::{compute_text_mode_slices_on_asset_event, compute_text_mode_slices_on_sprite_change, ComputedTextModeTextureSlices};
-use crate::TextModeSprite;
+use crate::text_mode_canvas_loader::{AnsiArtLoader, BinLoader, RexPaintLoader, XBinLoader};
+use crate::text_mode_font_loader::{BdfFontLoader, PsfFontLoader};
+use crate::text_mode_blink::advance_text_mode_blink_timer;
+use crate::text_mode_terminal::flush_text_mode_terminals;
+use crate::text_mode_nine_slice::draw_text_mode_nine_slices;
+use crate::text_mode_instance_buffer::{TextModeInstanceBuffer, TextModeInstanceStats};
+use crate::text_mode_palette_loader::{GplPaletteLoader, HexPaletteLoader, JascPaletteLoader, LospecPaletteLoader, PaintNetPaletteLoader};
+use crate::{TextMode3d, TextModeBillboard, TextModeBlink, TextModeBlinkTimer, TextModeCanvas, TextModeCell, TextModeCharset, TextModeCursor, TextModeCursorShape, TextModeFont, TextModeLayeredCanvas, TextModeGrid, TextModeMask, TextModePalette, TextModePaletteIndices, TextModeSprite, MAX_SPRITE_PALETTE_LEN};
+use uniforms::{TextModeBatchUniform, TextModeGridUniform};
+
+/// Query filter matching entities drawn by the text mode pipeline
+pub type WithTextModeSprite = Or<(With<TextModeSprite>, With<TextModeGrid>)>;
 
 const SPRITE_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(1354325909327402345);
 
@@ -42,19 +54,40 @@ impl Plugin for TextModePlugin {
             Shader::from_wgsl
         );
 
+        let instance_stats = TextModeInstanceStats::default();
+        app
+            .init_resource::<TextModeInstanceLayout>()
+            .insert_resource(instance_stats.clone())
+            .add_plugins(ExtractResourcePlugin::<TextModeInstanceLayout>::default());
+
         if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
             render_app
+                .insert_resource(instance_stats)
                 .init_resource::<TextModeImageBindGroups>()
+                .init_resource::<TextModeSpriteBatches>()
                 .init_resource::<SpecializedRenderPipelines<TextModeSpritePipeline>>()
                 .init_resource::<TextModeSpriteMeta>()
                 .init_resource::<ExtractedTextModeSprites>()
+                .init_resource::<ExtractedTextModeGrids>()
                 .init_resource::<TextModeSpriteAssetEvents>()
+                .init_resource::<TextModeBlinkUniformBuffer>()
+                .init_resource::<TextModeGpuAtlasLayouts>()
                 .add_render_command::<Transparent2d, DrawTextModeSprite>()
+                .add_render_command::<Transparent3d, DrawTextModeSprite>()
                 .add_systems(
                     ExtractSchedule,
                     (
-                        extract_text_mode_sprites.in_set(SpriteSystem::ExtractSprites),
+                        extract_text_mode_sprites
+                            .in_set(SpriteSystem::ExtractSprites)
+                            .after(extract_text_mode_atlas_layout_events)
+                            .after(extract_text_mode_sprite_events),
+                        extract_text_mode_grids
+                            .in_set(SpriteSystem::ExtractSprites)
+                            .after(extract_text_mode_atlas_layout_events)
+                            .after(extract_text_mode_sprite_events),
+                        extract_text_mode_atlas_layout_events,
                         extract_text_mode_sprite_events,
+                        extract_text_mode_blink_timer,
                     ),
                 )
                 .add_systems(
@@ -63,6 +96,9 @@ impl Plugin for TextModePlugin {
                         queue_text_mode_sprites
                             .in_set(RenderSet::Queue)
                             .ambiguous_with(queue_material2d_meshes::<ColorMaterial>),
+                        queue_text_mode_sprites_3d.in_set(RenderSet::Queue),
+                        prepare_text_mode_blink_uniform.in_set(RenderSet::PrepareResources),
+                        prepare_text_mode_atlas_layouts.in_set(RenderSet::PrepareResources),
                         prepare_text_mode_sprite_image_bind_groups.in_set(RenderSet::PrepareBindGroups),
                         prepare_text_mode_sprite_view_bind_groups.in_set(RenderSet::PrepareBindGroups),
                     ),
@@ -70,11 +106,31 @@ impl Plugin for TextModePlugin {
         };
 
         app
+            .init_resource::<TextModeBlinkTimer>()
+            .init_asset::<TextModeCharset>()
+            .init_asset::<TextModePalette>()
+            .init_asset::<TextModeCanvas>()
+            .init_asset::<TextModeLayeredCanvas>()
+            .init_asset::<TextModeFont>()
+            .init_asset_loader::<GplPaletteLoader>()
+            .init_asset_loader::<PaintNetPaletteLoader>()
+            .init_asset_loader::<JascPaletteLoader>()
+            .init_asset_loader::<HexPaletteLoader>()
+            .init_asset_loader::<LospecPaletteLoader>()
+            .init_asset_loader::<AnsiArtLoader>()
+            .init_asset_loader::<RexPaintLoader>()
+            .init_asset_loader::<XBinLoader>()
+            .init_asset_loader::<BinLoader>()
+            .init_asset_loader::<PsfFontLoader>()
+            .init_asset_loader::<BdfFontLoader>()
             .add_systems(
                 PostUpdate,
                 (
+                    flush_text_mode_terminals,
+                    draw_text_mode_nine_slices,
+                    advance_text_mode_blink_timer,
                     calculate_bounds_2d.in_set(VisibilitySystems::CalculateBounds),
-                    check_visibility::<With<TextModeSprite>>.in_set(VisibilitySystems::CheckVisibility),
+                    check_visibility::<WithTextModeSprite>.in_set(VisibilitySystems::CheckVisibility),
                     (
                         compute_text_mode_slices_on_asset_event,
                         compute_text_mode_slices_on_sprite_change,
@@ -95,17 +151,18 @@ impl Plugin for TextModePlugin {
 pub struct TextModeSpritePipeline {
     view_layout: BindGroupLayout,
     material_layout: BindGroupLayout,
-    pub dummy_white_gpu_image: GpuImage,
+    /// Layout of the [`TextModeBatchUniform`] bind group of instanced batches
+    batch_layout: BindGroupLayout,
+    /// Layout of the [`TextModeGrid::gpu_driven`] grid bind group, `None` without storage buffers
+    grid_layout: Option<BindGroupLayout>,
+    /// Layout of the glyph table bind group of an atlas layout, see [`TextModeGpuAtlasLayouts`]
+    glyph_table_layout: BindGroupLayout,
 }
 
 impl FromWorld for TextModeSpritePipeline {
     fn from_world(world: &mut World) -> Self {
-        let mut system_state: SystemState<(
-            Res<RenderDevice>,
-            Res<DefaultImageSampler>,
-            Res<RenderQueue>,
-        )> = SystemState::new(world);
-        let (render_device, default_sampler, render_queue) = system_state.get_mut(world);
+        let mut system_state: SystemState<Res<RenderDevice>> = SystemState::new(world);
+        let render_device = system_state.get_mut(world);
 
         let tonemapping_lut_entries = get_lut_bind_group_layout_entries();
         let view_layout = render_device.create_bind_group_layout(
@@ -122,6 +179,7 @@ impl FromWorld for TextModeSpritePipeline {
                         2,
                         tonemapping_lut_entries[1].visibility(ShaderStages::FRAGMENT),
                     ),
+                    (3, uniform_buffer::<Vec2>(false)),
                 ),
             ),
         );
@@ -136,42 +194,39 @@ impl FromWorld for TextModeSpritePipeline {
                 ),
             ),
         );
-        let dummy_white_gpu_image = {
-            let image = Image::default();
-            let texture = render_device.create_texture(&image.texture_descriptor);
-            let sampler = match image.sampler {
-                ImageSampler::Default => (**default_sampler).clone(),
-                ImageSampler::Descriptor(ref descriptor) => {
-                    render_device.create_sampler(&descriptor.as_wgpu())
-                }
-            };
-
-            let format_size = image.texture_descriptor.format.pixel_size();
-            render_queue.write_texture(
-                texture.as_image_copy(),
-                &image.data,
-                ImageDataLayout {
-                    offset: 0,
-                    bytes_per_row: Some(image.width() * format_size as u32),
-                    rows_per_image: None,
-                },
-                image.texture_descriptor.size,
-            );
-            let texture_view = texture.create_view(&TextureViewDescriptor::default());
-            GpuImage {
-                texture,
-                texture_view,
-                texture_format: image.texture_descriptor.format,
-                sampler,
-                size: image.size(),
-                mip_level_count: image.texture_descriptor.mip_level_count,
-            }
-        };
-
+        let batch_layout = render_device.create_bind_group_layout(
+            "text_mode_batch_layout",
+            &BindGroupLayoutEntries::single(
+                ShaderStages::VERTEX_FRAGMENT,
+                uniform_buffer::<TextModeBatchUniform>(true),
+            ),
+        );
+        let grid_layout = gpu_driven_grids_supported(&render_device).then(|| {
+            render_device.create_bind_group_layout(
+                "text_mode_grid_layout",
+                &BindGroupLayoutEntries::sequential(
+                    ShaderStages::VERTEX,
+                    (
+                        uniform_buffer::<TextModeGridUniform>(true),
+                        // Cells
+                        storage_buffer_read_only_sized(false, None),
+                    ),
+                ),
+            )
+        });
+        let glyph_table_layout = render_device.create_bind_group_layout(
+            "text_mode_glyph_table_layout",
+            &BindGroupLayoutEntries::single(
+                ShaderStages::VERTEX,
+                texture_2d(TextureSampleType::Float { filterable: false }),
+            ),
+        );
         TextModeSpritePipeline {
             view_layout,
             material_layout,
-            dummy_white_gpu_image,
+            batch_layout,
+            grid_layout,
+            glyph_table_layout,
         }
     }
 }
@@ -185,6 +240,12 @@ bitflags::bitflags! {
         const HDR                               = 1 << 1;
         const TONEMAP_IN_SHADER                 = 1 << 2;
         const DEBAND_DITHER                     = 1 << 3;
+        const INDEXED_PALETTE                   = 1 << 4;
+        const COMPACT_INSTANCES                 = 1 << 5;
+        const GPU_DRIVEN_GRID                   = 1 << 6;
+        const DEPTH_TEST                        = 1 << 7;
+        const DEPTH_WRITE                       = 1 << 8;
+        const CUSTOM_RECTS                      = 1 << 9;
         const MSAA_RESERVED_BITS                = Self::MSAA_MASK_BITS << Self::MSAA_SHIFT_BITS;
         const TONEMAP_METHOD_RESERVED_BITS      = Self::TONEMAP_METHOD_MASK_BITS << Self::TONEMAP_METHOD_SHIFT_BITS;
         const TONEMAP_METHOD_NONE               = 0 << Self::TONEMAP_METHOD_SHIFT_BITS;
@@ -271,72 +332,48 @@ impl SpecializedRenderPipeline for TextModeSpritePipeline {
             }
         }
 
+        if key.contains(TextModeSpritePipelineKey::INDEXED_PALETTE) {
+            shader_defs.push("INDEXED_PALETTE".into());
+        }
+
+        // Custom rect instances always use the full layout
+        if key.contains(TextModeSpritePipelineKey::COMPACT_INSTANCES) && !key.contains(TextModeSpritePipelineKey::CUSTOM_RECTS) {
+            shader_defs.push("COMPACT_INSTANCES".into());
+        }
+
         let format = match key.contains(TextModeSpritePipelineKey::HDR) {
             true => ViewTarget::TEXTURE_FORMAT_HDR,
             false => TextureFormat::bevy_default(),
         };
 
-        let instance_rate_vertex_buffer_layout = VertexBufferLayout {
-            array_stride: 112,
-            step_mode: VertexStepMode::Instance,
-            attributes: vec![
-                // @location(0) i_model_transpose_col0: vec4<f32>,
-                VertexAttribute {
-                    format: VertexFormat::Float32x4,
-                    offset: 0,
-                    shader_location: 0,
-                },
-                // @location(1) i_model_transpose_col1: vec4<f32>,
-                VertexAttribute {
-                    format: VertexFormat::Float32x4,
-                    offset: 16,
-                    shader_location: 1,
-                },
-                // @location(2) i_model_transpose_col2: vec4<f32>,
-                VertexAttribute {
-                    format: VertexFormat::Float32x4,
-                    offset: 32,
-                    shader_location: 2,
-                },
-                // @location(3) i_bg: vec4<f32>,
-                VertexAttribute {
-                    format: VertexFormat::Float32x4,
-                    offset: 48,
-                    shader_location: 3,
-                },
-                // @location(4) i_fg: vec4<f32>,
-                VertexAttribute {
-                    format: VertexFormat::Float32x4,
-                    offset: 64,
-                    shader_location: 4,
-                },
-                // @location(5) i_alpha: f32,
-                VertexAttribute {
-                    format: VertexFormat::Float32,
-                    offset: 80,
-                    shader_location: 5,
-                },
-                // @location(6) i_uv_offset_scale: vec4<f32>,
-                VertexAttribute {
-                    format: VertexFormat::Float32x4,
-                    offset: 84,
-                    shader_location: 6,
-                },
-                // @location(7) i_pad: vec3<f32>,
-                VertexAttribute {
-                    format: VertexFormat::Float32x3,
-                    offset: 100,
-                    shader_location: 7,
-                },
-            ],
+        let mut layout = vec![self.view_layout.clone(), self.material_layout.clone()];
+        let instance_rate_vertex_buffer_layout = if key.contains(TextModeSpritePipelineKey::GPU_DRIVEN_GRID) {
+            // Cells are read from the storage buffer by instance index
+            shader_defs.push("GPU_DRIVEN_GRID".into());
+            layout.extend(self.grid_layout.clone());
+            None
+        } else if key.contains(TextModeSpritePipelineKey::CUSTOM_RECTS) {
+            shader_defs.push("CUSTOM_RECTS".into());
+            layout.push(self.batch_layout.clone());
+            Some(TextModeRectSpriteInstance::vertex_buffer_layout())
+        } else if key.contains(TextModeSpritePipelineKey::COMPACT_INSTANCES) {
+            layout.push(self.batch_layout.clone());
+            Some(TextModeCompactSpriteInstance::vertex_buffer_layout())
+        } else {
+            layout.push(self.batch_layout.clone());
+            Some(TextModeSpriteInstance::vertex_buffer_layout())
         };
+        // Glyph instances and grid cells look up their UVs in the glyph table of their atlas layout
+        if !key.contains(TextModeSpritePipelineKey::CUSTOM_RECTS) {
+            layout.push(self.glyph_table_layout.clone());
+        }
 
         RenderPipelineDescriptor {
             vertex: VertexState {
                 shader: SPRITE_SHADER_HANDLE,
                 entry_point: "vertex".into(),
                 shader_defs: shader_defs.clone(),
-                buffers: vec![instance_rate_vertex_buffer_layout],
+                buffers: instance_rate_vertex_buffer_layout.into_iter().collect(),
             },
             fragment: Some(FragmentState {
                 shader: SPRITE_SHADER_HANDLE,
@@ -348,7 +385,7 @@ impl SpecializedRenderPipeline for TextModeSpritePipeline {
                     write_mask: ColorWrites::ALL,
                 })],
             }),
-            layout: vec![self.view_layout.clone(), self.material_layout.clone()],
+            layout,
             primitive: PrimitiveState {
                 front_face: FrontFace::Ccw,
                 cull_mode: None,
@@ -358,7 +395,14 @@ impl SpecializedRenderPipeline for TextModeSpritePipeline {
                 topology: PrimitiveTopology::TriangleList,
                 strip_index_format: None,
             },
-            depth_stencil: None,
+            // 3D views reverse Z, greater depths being closer to the camera
+            depth_stencil: key.contains(TextModeSpritePipelineKey::DEPTH_TEST).then(|| DepthStencilState {
+                format: CORE_3D_DEPTH_FORMAT,
+                depth_write_enabled: key.contains(TextModeSpritePipelineKey::DEPTH_WRITE),
+                depth_compare: CompareFunction::GreaterEqual,
+                stencil: StencilState::default(),
+                bias: DepthBiasState::default(),
+            }),
             multisample: MultisampleState {
                 count: key.msaa_samples(),
                 mask: !0,
@@ -378,67 +422,219 @@ pub struct TextModeExtractedSprite {
     pub alpha: f32,
     pub custom_size: Option<Vec2>,
     pub rect: Option<Rect>,
+    /// Atlas layout and index of the glyph in `rect`, drawn from the glyph table of the layout.
+    /// `None` for custom rects and whole images, drawn with their own UVs
+    pub glyph: Option<(AssetId<TextureAtlasLayout>, u32)>,
     pub image_handle_id: AssetId<Image>,
     pub flip_x: bool,
     pub flip_y: bool,
     pub rotation: u8,
     pub anchor: Vec2,
-    pub original_entity: Option<Entity>,
+    pub palette: [LinearRgba; 4],
+    /// Number of indexed palette colors, `0` for the `bg` / `fg` mode
+    pub palette_len: u32,
+    pub mask: TextModeMask,
+    pub invert_mask: bool,
+    pub skip_bg: bool,
+    pub skip_fg: bool,
+    pub coverage: bool,
+    pub blink: TextModeBlink,
+    pub cursor: Option<TextModeCursor>,
+    /// 3D settings of the sprite entity, `None` for sprites drawn in 2D
+    pub mode_3d: Option<TextModeExtracted3d>,
+}
+
+/// [`TextMode3d`] settings of an extracted sprite or grid
+#[derive(Clone, Copy)]
+pub struct TextModeExtracted3d {
+    pub settings: TextMode3d,
+    /// Translation of the entity, billboards turning around it
+    pub pivot: Vec3,
+}
+
+impl TextModeExtracted3d {
+    fn new(settings: TextMode3d, transform: &GlobalTransform) -> Self {
+        Self {
+            settings,
+            pivot: transform.translation(),
+        }
+    }
+
+    /// Transform of the drawn instances, without the rotation of billboards as the vertex shader
+    /// turns them towards each view
+    fn drawn_transform(mode_3d: Option<&Self>, transform: &GlobalTransform) -> GlobalTransform {
+        match mode_3d {
+            Some(mode_3d) if mode_3d.settings.billboard != TextModeBillboard::None => {
+                let (scale, _, translation) = transform.to_scale_rotation_translation();
+                GlobalTransform::from(Transform::from_translation(translation).with_scale(scale))
+            }
+            _ => *transform,
+        }
+    }
+
+    /// Pivot and billboard mode of the batch uniforms
+    fn billboard(mode_3d: Option<&Self>) -> (Vec3, u32) {
+        mode_3d.map_or((Vec3::ZERO, 0), |mode_3d| (mode_3d.pivot, mode_3d.settings.billboard.shader_mode()))
+    }
 }
 
+/// Extracted sprites, kept between frames while their entity and assets are unchanged
 #[derive(Resource, Default)]
 pub struct ExtractedTextModeSprites {
-    pub sprites: EntityHashMap<TextModeExtractedSprite>,
+    pub sprites: EntityHashMap<TextModeExtractedSpriteQuads>,
+}
+
+/// Quads of an extracted sprite entity
+pub struct TextModeExtractedSpriteQuads {
+    /// Transform of the sprite entity, sorting its phase item
+    pub transform: GlobalTransform,
+    /// The sprite quad, or the quads of the slices of a sliced sprite sharing its image, palette and mask
+    pub quads: Vec<TextModeExtractedSprite>,
+    /// Whether the quads were rebuilt this frame, their instances being written again
+    pub changed: bool,
+}
+
+impl TextModeExtractedSpriteQuads {
+    /// First quad, holding the image, palette, mask and 3D settings of the sprite
+    fn first(&self) -> &TextModeExtractedSprite {
+        &self.quads[0]
+    }
+}
+
+/// Cells of a [`TextModeGrid`], ready to be copied to the instance buffer
+pub struct TextModeExtractedGrid {
+    pub transform: GlobalTransform,
+    pub image_handle_id: AssetId<Image>,
+    /// Atlas layout whose glyph table is bound with the instances, see [`TextModeGpuAtlasLayouts`]
+    pub layout_id: AssetId<TextureAtlasLayout>,
+    /// Mask shared by the cells, unused by GPU driven grids
+    pub(crate) uniform: TextModeBatchUniform,
+    pub(crate) instances: Vec<TextModeSpriteInstance>,
+    /// Instances of the [`TextModeInstanceLayout::Compact`] layout of 2D grids, used instead of `instances`
+    pub(crate) compact_instances: Vec<TextModeCompactSpriteInstance>,
+    /// Cells of a [`TextModeGrid::gpu_driven`] grid, drawn instead of the instances
+    pub(crate) packed: Option<TextModeExtractedPackedGrid>,
+    /// 3D settings of the grid entity, `None` for grids drawn in 2D
+    pub mode_3d: Option<TextModeExtracted3d>,
+    /// Whether the grid was rebuilt this frame, its instances or cells being written again
+    pub changed: bool,
+}
+
+/// Extracted grids, kept between frames while their entity and assets are unchanged
+#[derive(Resource, Default)]
+pub struct ExtractedTextModeGrids {
+    pub grids: EntityHashMap<TextModeExtractedGrid>,
 }
 
+/// Assets with an event since the previous extraction, the sprites and grids using them being rebuilt
 #[derive(Resource, Default)]
 pub struct TextModeSpriteAssetEvents {
-    pub images: Vec<AssetEvent<Image>>,
+    pub images: HashSet<AssetId<Image>>,
+    pub layouts: HashSet<AssetId<TextureAtlasLayout>>,
+    pub palettes: HashSet<AssetId<TextModePalette>>,
 }
 
 pub fn extract_text_mode_sprite_events(
     mut events: ResMut<TextModeSpriteAssetEvents>,
     mut image_events: Extract<EventReader<AssetEvent<Image>>>,
+    mut layout_events: Extract<EventReader<AssetEvent<TextureAtlasLayout>>>,
+    mut palette_events: Extract<EventReader<AssetEvent<TextModePalette>>>,
 ) {
-    let TextModeSpriteAssetEvents { ref mut images } = *events;
+    let TextModeSpriteAssetEvents { ref mut images, ref mut layouts, ref mut palettes } = *events;
     images.clear();
+    layouts.clear();
+    palettes.clear();
 
-    for event in image_events.read() {
-        images.push(*event);
+    images.extend(image_events.read().map(asset_event_id));
+    layouts.extend(layout_events.read().map(asset_event_id));
+    palettes.extend(palette_events.read().map(asset_event_id));
+}
+
+fn asset_event_id<A: Asset>(event: &AssetEvent<A>) -> AssetId<A> {
+    match *event {
+        AssetEvent::Added { id }
+        | AssetEvent::Modified { id }
+        | AssetEvent::Removed { id }
+        | AssetEvent::Unused { id }
+        | AssetEvent::LoadedWithDependencies { id } => id,
     }
 }
 
-/// See [bevy::sprite::extract_sprites]
+/// See [bevy::sprite::extract_sprites]. Sprites are only rebuilt when their entity or assets changed
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
 pub fn extract_text_mode_sprites(
-    mut commands: Commands,
+    mut previous_sprites: Local<EntityHashMap<TextModeExtractedSpriteQuads>>,
     mut extracted_sprites: ResMut<ExtractedTextModeSprites>,
+    mut gpu_layouts: ResMut<TextModeGpuAtlasLayouts>,
+    events: Res<TextModeSpriteAssetEvents>,
     texture_atlases: Extract<Res<Assets<TextureAtlasLayout>>>,
+    palettes: Extract<Res<Assets<TextModePalette>>>,
     sprite_query: Extract<
         Query<(
             Entity,
             &ViewVisibility,
-            &TextModeSprite,
-            &GlobalTransform,
-            &Handle<Image>,
-            Option<&TextureAtlas>,
-            Option<&ComputedTextModeTextureSlices>,
+            Ref<TextModeSprite>,
+            Ref<GlobalTransform>,
+            Ref<Handle<Image>>,
+            Option<Ref<TextureAtlas>>,
+            Option<Ref<ComputedTextModeTextureSlices>>,
+            Option<Ref<TextModePaletteIndices>>,
+            Option<Ref<TextMode3d>>,
         )>,
     >,
 ) {
-    extracted_sprites.sprites.clear();
-    for (entity, view_visibility, sprite, transform, handle, sheet, slices) in sprite_query.iter() {
+    // Sprites that aren't visible anymore are dropped with the previous frame
+    std::mem::swap(&mut *previous_sprites, &mut extracted_sprites.sprites);
+    for (entity, view_visibility, sprite, transform, handle, sheet, slices, palette_indices, mode_3d) in sprite_query.iter() {
         if !view_visibility.get() {
             continue;
         }
 
-        if let Some(slices) = slices {
-            extracted_sprites.sprites.extend(
-                slices
-                    .extract_text_mode_sprites(transform, entity, sprite, handle)
-                    .map(|e| (commands.spawn_empty().id(), e))
-            );
+        let changed = sprite.is_changed()
+            || transform.is_changed()
+            || handle.is_changed()
+            || events.images.contains(&handle.id())
+            || sheet.as_ref().is_some_and(|s| s.is_changed() || events.layouts.contains(&s.layout.id()))
+            || slices.as_ref().is_some_and(Ref::is_changed)
+            || palette_indices.as_ref().is_some_and(|p| p.is_changed() || events.palettes.contains(&p.palette.id()))
+            || mode_3d.as_ref().is_some_and(Ref::is_changed);
+        if let (false, Some(mut previous)) = (changed, previous_sprites.remove(&entity)) {
+            previous.changed = false;
+            extracted_sprites.sprites.insert(entity, previous);
+            continue;
+        }
+
+        // Palette colors override the sprite colors
+        let palette = palette_indices.as_deref().and_then(|p| Some((p, palettes.get(&p.palette)?)));
+        let mode_3d = mode_3d.map(|settings| TextModeExtracted3d::new(*settings, &transform));
+        let transform = &TextModeExtracted3d::drawn_transform(mode_3d.as_ref(), &transform);
+        let apply_palette = |mut extracted: TextModeExtractedSprite| {
+            if let Some((indices, palette)) = palette {
+                indices.apply(palette, &mut extracted);
+            }
+            extracted.mode_3d = mode_3d;
+            extracted
+        };
+
+        let quads: Vec<_> = if let Some(slices) = &slices {
+            slices
+                .extract_text_mode_sprites(transform, &sprite, &handle)
+                .map(apply_palette)
+                .collect()
         } else {
+            let sheet = sheet.as_deref();
             let atlas_rect = sheet.and_then(|s| s.texture_rect(&texture_atlases));
+            // Whole atlas glyphs are looked up in the glyph table of the layout
+            let glyph = match (sheet, atlas_rect, sprite.rect) {
+                (Some(sheet), Some(_), None) => {
+                    let layout_id = sheet.layout.id();
+                    if let Some(layout) = texture_atlases.get(layout_id) {
+                        gpu_layouts.request(layout_id, layout);
+                    }
+                    u32::try_from(sheet.index).ok().map(|index| (layout_id, index))
+                }
+                _ => None,
+            };
             let rect = match (atlas_rect, sprite.rect) {
                 (None, None) => None,
                 (None, Some(sprite_rect)) => Some(sprite_rect),
@@ -451,177 +647,1118 @@ pub fn extract_text_mode_sprites(
                 }
             };
 
+            let (palette, palette_len) = sprite.palette.padded();
+            vec![apply_palette(TextModeExtractedSprite {
+                bg: sprite.bg,
+                fg: sprite.fg,
+                alpha: sprite.alpha,
+                transform: *transform,
+                // Select the area in the texture atlas
+                rect,
+                glyph,
+                // Pass the custom size
+                custom_size: sprite.custom_size,
+                flip_x: sprite.flip_x,
+                flip_y: sprite.flip_y,
+                rotation: sprite.rotation,
+                image_handle_id: handle.id(),
+                anchor: sprite.anchor.as_vec(),
+                palette,
+                palette_len,
+                mask: sprite.mask,
+                invert_mask: sprite.invert_mask,
+                skip_bg: sprite.skip_bg,
+                skip_fg: sprite.skip_fg,
+                coverage: sprite.coverage,
+                blink: sprite.blink,
+                cursor: None,
+                mode_3d: None,
+            })]
+        };
+
+        if !quads.is_empty() {
             extracted_sprites.sprites.insert(
                 entity,
-                TextModeExtractedSprite {
-                    bg: sprite.bg,
-                    fg: sprite.fg,
-                    alpha: sprite.alpha,
+                TextModeExtractedSpriteQuads {
                     transform: *transform,
-                    // Select the area in the texture atlas
-                    rect,
-                    // Pass the custom size
-                    custom_size: sprite.custom_size,
-                    flip_x: sprite.flip_x,
-                    flip_y: sprite.flip_y,
-                    rotation: sprite.rotation,
-                    image_handle_id: handle.id(),
-                    anchor: sprite.anchor.as_vec(),
-                    original_entity: None,
+                    quads,
+                    changed: true,
                 },
             );
         }
     }
+    previous_sprites.clear();
 }
 
-#[repr(C)]
-#[derive(Copy, Clone, Pod, Zeroable)]
-struct TextModeSpriteInstance {
-    pub i_model_transpose: [Vec4; 3],
-    pub i_bg: [f32; 4],
-    pub i_fg: [f32; 4],
-    pub i_alpha: f32,
-    pub i_uv: [f32; 4],
-    pub i_pad: [f32; 3],
-}
+/// Extracts the visible grids, only rebuilding the instances or cells of the grids whose entity or assets changed
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+pub fn extract_text_mode_grids(
+    mut previous_grids: Local<EntityHashMap<TextModeExtractedGrid>>,
+    mut extracted_grids: ResMut<ExtractedTextModeGrids>,
+    mut gpu_layouts: ResMut<TextModeGpuAtlasLayouts>,
+    render_device: Res<RenderDevice>,
+    events: Res<TextModeSpriteAssetEvents>,
+    texture_atlases: Extract<Res<Assets<TextureAtlasLayout>>>,
+    instance_layout: Extract<Res<TextModeInstanceLayout>>,
+    grid_query: Extract<
+        Query<(
+            Entity,
+            &ViewVisibility,
+            Ref<TextModeGrid>,
+            Ref<GlobalTransform>,
+            Ref<Handle<Image>>,
+            Option<Ref<TextMode3d>>,
+        )>,
+    >,
+) {
+    // Grids that aren't visible anymore are dropped with the previous frame
+    std::mem::swap(&mut *previous_grids, &mut extracted_grids.grids);
+    let gpu_driven_supported = gpu_driven_grids_supported(&render_device);
+    for (entity, view_visibility, grid, transform, handle, mode_3d) in grid_query.iter() {
+        if !view_visibility.get() {
+            continue;
+        }
 
-impl TextModeSpriteInstance {
-    #[inline]
-    fn from(transform: &Affine3A, bg: &LinearRgba, fg: &LinearRgba, alpha: f32, uv_offset_scale: &Vec4) -> Self {
-        let transpose_model_3x3 = transform.matrix3.transpose();
-        Self {
-            i_model_transpose: [
-                transpose_model_3x3.x_axis.extend(transform.translation.x),
-                transpose_model_3x3.y_axis.extend(transform.translation.y),
-                transpose_model_3x3.z_axis.extend(transform.translation.z),
-            ],
-            i_bg: bg.to_f32_array(),
-            i_fg: fg.to_f32_array(),
-            i_alpha: alpha,
-            i_uv: uv_offset_scale.to_array(),
-            i_pad: [0., 0., 0.],
+        let changed = grid.is_changed()
+            || transform.is_changed()
+            || handle.is_changed()
+            || events.layouts.contains(&grid.layout.id())
+            || mode_3d.as_ref().is_some_and(Ref::is_changed)
+            || instance_layout.is_changed();
+        if let (false, Some(mut previous)) = (changed, previous_grids.remove(&entity)) {
+            previous.changed = false;
+            extracted_grids.grids.insert(entity, previous);
+            continue;
         }
-    }
-}
 
-/// See [bevy::sprite::SpriteMeta]
-#[derive(Resource)]
-pub struct TextModeSpriteMeta {
-    sprite_index_buffer: RawBufferVec<u32>,
-    sprite_instance_buffer: RawBufferVec<TextModeSpriteInstance>,
-}
+        let mode_3d = mode_3d.map(|settings| TextModeExtracted3d::new(*settings, &transform));
+        let transform = &TextModeExtracted3d::drawn_transform(mode_3d.as_ref(), &transform);
+        let (pivot, billboard) = TextModeExtracted3d::billboard(mode_3d.as_ref());
+        let Some(layout) = texture_atlases.get(&grid.layout) else {
+            continue;
+        };
 
-impl Default for TextModeSpriteMeta {
-    fn default() -> Self {
-        Self {
-            sprite_index_buffer: RawBufferVec::<u32>::new(BufferUsages::INDEX),
-            sprite_instance_buffer: RawBufferVec::<TextModeSpriteInstance>::new(BufferUsages::VERTEX),
+        let image_size = layout.size.as_vec2();
+        let layout_id = grid.layout.id();
+        gpu_layouts.request(layout_id, layout);
+        if grid.gpu_driven && gpu_driven_supported {
+            // Storage buffers can't be empty
+            if grid.width() == 0 || layout.textures.is_empty() {
+                continue;
+            }
+            let mut packed = extract_packed_grid(&grid, transform, layout);
+            packed.uniform.billboard = billboard;
+            extracted_grids.grids.insert(
+                entity,
+                TextModeExtractedGrid {
+                    transform: *transform,
+                    image_handle_id: handle.id(),
+                    layout_id,
+                    uniform: TextModeBatchUniform::default(),
+                    instances: Vec::new(),
+                    compact_instances: Vec::new(),
+                    packed: Some(packed),
+                    mode_3d,
+                    changed: true,
+                },
+            );
+            continue;
         }
+
+        let sprites = grid.extract_text_mode_sprites(transform, layout, &handle);
+        let (instances, compact_instances) = if instance_layout.is_compact(mode_3d.as_ref()) {
+            (
+                Vec::new(),
+                sprites.map(|sprite| TextModeCompactSpriteInstance::from_extracted(&sprite, image_size)).collect(),
+            )
+        } else {
+            (
+                sprites.map(|sprite| TextModeSpriteInstance::from_extracted(&sprite, image_size)).collect(),
+                Vec::new(),
+            )
+        };
+        extracted_grids.grids.insert(
+            entity,
+            TextModeExtractedGrid {
+                transform: *transform,
+                image_handle_id: handle.id(),
+                layout_id,
+                uniform: TextModeBatchUniform {
+                    mask: TextModeSpriteInstanceFlags::from_mask(grid.mask, grid.invert_mask, grid.coverage).1,
+                    pivot,
+                    billboard,
+                    ..default()
+                },
+                instances,
+                compact_instances,
+                packed: None,
+                mode_3d,
+                changed: true,
+            },
+        );
     }
+    previous_grids.clear();
 }
 
-#[derive(Component)]
-pub struct TextModeSpriteViewBindGroup {
-    pub value: BindGroup,
+fn extract_packed_grid(
+    grid: &TextModeGrid,
+    transform: &GlobalTransform,
+    layout: &TextureAtlasLayout,
+) -> TextModeExtractedPackedGrid {
+    let (cell_size, origin, cells) = grid.extract_drawn_cells(layout);
+    let (mask_flags, mask) = TextModeSpriteInstanceFlags::from_mask(grid.mask, grid.invert_mask, grid.coverage);
+    TextModeExtractedPackedGrid {
+        uniform: TextModeGridUniform {
+            world_from_local: transform.compute_matrix(),
+            origin,
+            cell_size,
+            mask,
+            width: grid.width() as u32,
+            alpha: grid.alpha,
+            ..default()
+        },
+        cells: cells
+            .map(|cell| match cell {
+                Some((cell, cursor)) => TextModePackedCell::new(cell, cursor, mask_flags),
+                None => TextModePackedCell::EMPTY,
+            })
+            .collect(),
+    }
 }
 
-#[derive(Component, PartialEq, Eq, Clone)]
-pub struct TextModeSpriteBatch {
-    image_handle_id: AssetId<Image>,
-    range: Range<u32>,
+/// Texels of the glyph table of an atlas layout, the UV offset and scale of each glyph padded with
+/// zeros to whole rows of [`GLYPH_TABLE_WIDTH`] glyphs
+fn glyph_table(layout: &TextureAtlasLayout) -> Vec<[f32; 4]> {
+    let image_size = layout.size.as_vec2();
+    let rows = layout.textures.len().div_ceil(GLYPH_TABLE_WIDTH).max(1);
+    let mut texels: Vec<_> = layout
+        .textures
+        .iter()
+        .map(|rect| uv_offset_scale_of(rect.as_rect(), image_size).to_array())
+        .collect();
+    texels.resize(rows * GLYPH_TABLE_WIDTH, [0.0; 4]);
+    texels
+}
+
+/// Glyphs per row of the glyph tables, must match `GLYPH_TABLE_WIDTH` in `text_mode_sprite.wgsl`
+const GLYPH_TABLE_WIDTH: usize = 256;
+
+/// Glyph table of an atlas layout on the GPU
+struct TextModeGlyphTable {
+    _texture: Texture,
+    bind_group: BindGroup,
 }
 
+/// Glyph tables of the atlas layouts, uploaded on first use and kept on the GPU until the layout is
+/// modified or unused. Atlas sprites and grid cells only upload the index of their glyph in the table
 #[derive(Resource, Default)]
-pub struct TextModeImageBindGroups {
-    values: HashMap<AssetId<Image>, BindGroup>,
+pub struct TextModeGpuAtlasLayouts {
+    tables: HashMap<AssetId<TextureAtlasLayout>, TextModeGlyphTable>,
+    /// Glyph tables of the layouts to upload
+    pending: HashMap<AssetId<TextureAtlasLayout>, Vec<[f32; 4]>>,
 }
 
-/// See [bevy::sprite::queue_sprites]
-#[allow(clippy::too_many_arguments)]
-pub fn queue_text_mode_sprites(
-    mut view_entities: Local<FixedBitSet>,
-    draw_functions: Res<DrawFunctions<Transparent2d>>,
+impl TextModeGpuAtlasLayouts {
+    /// Uploads the glyph table of `layout` if it isn't on the GPU yet
+    fn request(&mut self, id: AssetId<TextureAtlasLayout>, layout: &TextureAtlasLayout) {
+        if !self.tables.contains_key(&id) {
+            self.pending.entry(id).or_insert_with(|| glyph_table(layout));
+        }
+    }
+
+    /// Bind group of the glyph table of a layout
+    fn bind_group(&self, id: AssetId<TextureAtlasLayout>) -> Option<&BindGroup> {
+        self.tables.get(&id).map(|table| &table.bind_group)
+    }
+}
+
+/// Drops the glyph tables of the modified and unused atlas layouts, modified layouts being uploaded
+/// again by the next sprite or grid using them
+pub fn extract_text_mode_atlas_layout_events(
+    mut gpu_layouts: ResMut<TextModeGpuAtlasLayouts>,
+    mut layout_events: Extract<EventReader<AssetEvent<TextureAtlasLayout>>>,
+) {
+    for event in layout_events.read() {
+        match event {
+            AssetEvent::Added { .. } | AssetEvent::LoadedWithDependencies { .. } => {}
+            AssetEvent::Modified { id } | AssetEvent::Unused { id } | AssetEvent::Removed { id } => {
+                gpu_layouts.tables.remove(id);
+            }
+        }
+    }
+}
+
+pub fn prepare_text_mode_atlas_layouts(
+    mut gpu_layouts: ResMut<TextModeGpuAtlasLayouts>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
     sprite_pipeline: Res<TextModeSpritePipeline>,
-    mut pipelines: ResMut<SpecializedRenderPipelines<TextModeSpritePipeline>>,
-    pipeline_cache: Res<PipelineCache>,
-    msaa: Res<Msaa>,
-    extracted_sprites: Res<ExtractedTextModeSprites>,
-    mut transparent_render_phases: ResMut<ViewSortedRenderPhases<Transparent2d>>,
-    mut views: Query<(
-        Entity,
-        &VisibleEntities,
-        &ExtractedView,
-        Option<&Tonemapping>,
-        Option<&DebandDither>,
-    )>,
 ) {
-    let msaa_key = TextModeSpritePipelineKey::from_msaa_samples(msaa.samples());
+    let TextModeGpuAtlasLayouts { tables, pending } = &mut *gpu_layouts;
+    for (id, texels) in pending.drain() {
+        let texture = render_device.create_texture_with_data(
+            &render_queue,
+            &TextureDescriptor {
+                label: Some("text_mode_glyph_table"),
+                size: Extent3d {
+                    width: GLYPH_TABLE_WIDTH as u32,
+                    height: (texels.len() / GLYPH_TABLE_WIDTH) as u32,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: TextureFormat::Rgba32Float,
+                usage: TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+            TextureDataOrder::LayerMajor,
+            bytemuck::cast_slice(&texels),
+        );
+        let bind_group = render_device.create_bind_group(
+            "text_mode_glyph_table_bind_group",
+            &sprite_pipeline.glyph_table_layout,
+            &BindGroupEntries::single(&texture.create_view(&TextureViewDescriptor::default())),
+        );
+        tables.insert(id, TextModeGlyphTable { _texture: texture, bind_group });
+    }
+}
 
-    let draw_sprite_function = draw_functions.read().id::<DrawTextModeSprite>();
+bitflags::bitflags! {
+    /// Per instance flags, must match the constants of `text_mode_sprite.wgsl`
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    #[repr(transparent)]
+    struct TextModeSpriteInstanceFlags: u32 {
+        const PALETTE_LEN_RESERVED_BITS = 0b111;
+        const MASK_RESERVED_BITS        = 0b11 << 3;
+        const MASK_RED                  = 0 << 3;
+        const MASK_LUMINANCE            = 1 << 3;
+        const MASK_ALPHA                = 2 << 3;
+        const MASK_KEY                  = 3 << 3;
+        const INVERT_MASK               = 1 << 5;
+        const SKIP_BG                   = 1 << 6;
+        const SKIP_FG                   = 1 << 7;
+        const COVERAGE                  = 1 << 8;
+        const BLINK_RESERVED_BITS       = 0b11 << 9;
+        const BLINK_FG                  = 1 << 9;
+        const BLINK_SWAP                = 2 << 9;
+        const BLINK_HIDE                = 3 << 9;
+        const CURSOR_RESERVED_BITS      = 0b11 << 11;
+        const CURSOR_BLOCK              = 1 << 11;
+        const CURSOR_UNDERLINE          = 2 << 11;
+        const CURSOR_BAR                = 3 << 11;
+        const CURSOR_BLINK              = 1 << 13;
+        // Applied to the glyph UVs by the vertex shader, custom rect instances bake them into their UVs
+        const FLIP_X                    = 1 << 14;
+        const FLIP_Y                    = 1 << 15;
+        // Only set on the cells of GPU driven grids, sprite instances bake it into their transform
+        const ROTATION_RESERVED_BITS    = 0b11 << 16;
+    }
+}
 
-    for (view_entity, visible_entities, view, tonemapping, dither) in &mut views {
-        let Some(transparent_phase) = transparent_render_phases.get_mut(&view_entity) else {
-            continue;
+impl TextModeSpriteInstanceFlags {
+    /// Flags and mask parameters (threshold or key color) of an extracted sprite
+    fn from_extracted(extracted_sprite: &TextModeExtractedSprite) -> (Self, Vec4) {
+        let palette_len = Self::from_bits_retain(extracted_sprite.palette_len)
+            .intersection(Self::PALETTE_LEN_RESERVED_BITS);
+        let (mask_flags, mask) = Self::from_mask(
+            extracted_sprite.mask,
+            extracted_sprite.invert_mask,
+            extracted_sprite.coverage,
+        );
+        let cell_flags = Self::from_cell(
+            extracted_sprite.skip_bg,
+            extracted_sprite.skip_fg,
+            extracted_sprite.blink,
+            extracted_sprite.cursor,
+        );
+        let flip_flags = Self::from_flip(extracted_sprite.flip_x, extracted_sprite.flip_y);
+        (palette_len | mask_flags | cell_flags | flip_flags, mask)
+    }
+
+    /// Flags and mask parameters of a mask rule
+    fn from_mask(mask: TextModeMask, invert_mask: bool, coverage: bool) -> (Self, Vec4) {
+        let mut flags = Self::empty();
+        let mask = match mask {
+            TextModeMask::Red => {
+                flags |= Self::MASK_RED;
+                Vec4::ZERO
+            }
+            TextModeMask::Luminance(threshold) => {
+                flags |= Self::MASK_LUMINANCE;
+                Vec4::new(threshold, 0.0, 0.0, 0.0)
+            }
+            TextModeMask::Alpha(threshold) => {
+                flags |= Self::MASK_ALPHA;
+                Vec4::new(threshold, 0.0, 0.0, 0.0)
+            }
+            TextModeMask::Key(color) => {
+                flags |= Self::MASK_KEY;
+                color.to_vec4()
+            }
         };
+        if invert_mask {
+            flags |= Self::INVERT_MASK;
+        }
+        if coverage {
+            flags |= Self::COVERAGE;
+        }
+        (flags, mask)
+    }
 
-        let mut view_key = TextModeSpritePipelineKey::from_hdr(view.hdr) | msaa_key;
+    /// Flags flipping the glyph UVs
+    fn from_flip(flip_x: bool, flip_y: bool) -> Self {
+        let mut flags = Self::empty();
+        if flip_x {
+            flags |= Self::FLIP_X;
+        }
+        if flip_y {
+            flags |= Self::FLIP_Y;
+        }
+        flags
+    }
 
-        if !view.hdr {
-            if let Some(tonemapping) = tonemapping {
-                view_key |= TextModeSpritePipelineKey::TONEMAP_IN_SHADER;
-                view_key |= match tonemapping {
-                    Tonemapping::None => TextModeSpritePipelineKey::TONEMAP_METHOD_NONE,
-                    Tonemapping::Reinhard => TextModeSpritePipelineKey::TONEMAP_METHOD_REINHARD,
-                    Tonemapping::ReinhardLuminance => {
-                        TextModeSpritePipelineKey::TONEMAP_METHOD_REINHARD_LUMINANCE
-                    }
-                    Tonemapping::AcesFitted => TextModeSpritePipelineKey::TONEMAP_METHOD_ACES_FITTED,
-                    Tonemapping::AgX => TextModeSpritePipelineKey::TONEMAP_METHOD_AGX,
-                    Tonemapping::SomewhatBoringDisplayTransform => {
-                        TextModeSpritePipelineKey::TONEMAP_METHOD_SOMEWHAT_BORING_DISPLAY_TRANSFORM
-                    }
-                    Tonemapping::TonyMcMapface => TextModeSpritePipelineKey::TONEMAP_METHOD_TONY_MC_MAPFACE,
-                    Tonemapping::BlenderFilmic => TextModeSpritePipelineKey::TONEMAP_METHOD_BLENDER_FILMIC,
-                };
-            }
-            if let Some(DebandDither::Enabled) = dither {
-                view_key |= TextModeSpritePipelineKey::DEBAND_DITHER;
+    /// Flags of the skipped pixels, blink mode and cursor of a cell
+    fn from_cell(skip_bg: bool, skip_fg: bool, blink: TextModeBlink, cursor: Option<TextModeCursor>) -> Self {
+        let mut flags = Self::empty();
+        if skip_bg {
+            flags |= Self::SKIP_BG;
+        }
+        if skip_fg {
+            flags |= Self::SKIP_FG;
+        }
+        flags |= match blink {
+            TextModeBlink::None => Self::empty(),
+            TextModeBlink::Fg => Self::BLINK_FG,
+            TextModeBlink::Swap => Self::BLINK_SWAP,
+            TextModeBlink::Hide => Self::BLINK_HIDE,
+        };
+        if let Some(cursor) = cursor {
+            flags |= match cursor.shape {
+                TextModeCursorShape::Block => Self::CURSOR_BLOCK,
+                TextModeCursorShape::Underline => Self::CURSOR_UNDERLINE,
+                TextModeCursorShape::Bar => Self::CURSOR_BAR,
+            };
+            if cursor.blink {
+                flags |= Self::CURSOR_BLINK;
             }
         }
+        flags
+    }
+}
 
-        let pipeline = pipelines.specialize(&pipeline_cache, &sprite_pipeline, view_key,);
+/// Layout of the per sprite data uploaded to the GPU every frame
+#[derive(Resource, ExtractResource, Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+pub enum TextModeInstanceLayout {
+    /// 32 bit float colors and 3D transforms
+    #[default]
+    Full,
+    /// 8 bit sRGB colors with the sprite alpha folded in, and 2D transforms: colors are clamped
+    /// to `[0, 1]` and rotations out of the XY plane are dropped
+    Compact,
+}
 
-        view_entities.clear();
-        view_entities.extend(
-            visible_entities
-                .iter::<With<TextModeSprite>>()
-                .map(|e| e.index() as usize),
-        );
+impl TextModeInstanceLayout {
+    /// Whether the compact layout is used for a sprite or grid, 3D ones always using the full layout
+    fn is_compact(self, mode_3d: Option<&TextModeExtracted3d>) -> bool {
+        self == Self::Compact && mode_3d.is_none()
+    }
 
-        transparent_phase
-            .items
-            .reserve(extracted_sprites.sprites.len());
+    /// Size of the data uploaded per atlas sprite or grid cell, in bytes. Sprites drawn from a custom rect
+    /// or a whole image always upload 100 bytes
+    pub fn instance_size(self) -> usize {
+        match self {
+            Self::Full => std::mem::size_of::<TextModeSpriteInstance>(),
+            Self::Compact => std::mem::size_of::<TextModeCompactSpriteInstance>(),
+        }
+    }
+}
+
+/// Instance data built from an extracted sprite
+trait TextModeInstance: Pod {
+    /// Builds an instance, `bg` and `fg` having the sprite alpha folded in. Glyph instances only keep
+    /// the `glyph` index, custom rect instances only keep `uv_offset_scale`
+    fn from(
+        transform: &Affine3A,
+        bg: LinearRgba,
+        fg: LinearRgba,
+        flags: TextModeSpriteInstanceFlags,
+        glyph: u32,
+        uv_offset_scale: &Vec4,
+    ) -> Self;
+
+    /// Computes the instance data of an extracted sprite drawn from a texture of size `image_size`
+    fn from_extracted(extracted_sprite: &TextModeExtractedSprite, image_size: Vec2) -> Self {
+        // By default, the size of the quad is the size of the texture
+        let mut quad_size = image_size;
+
+        // Calculate vertex data for this item
+        let mut uv_offset_scale: Vec4;
+
+        // If a rect is specified, adjust UVs and the size of the quad
+        if let Some(rect) = extracted_sprite.rect {
+            uv_offset_scale = uv_offset_scale_of(rect, image_size);
+            quad_size = rect.size();
+        } else {
+            uv_offset_scale = Vec4::new(0.0, 1.0, 1.0, -1.0);
+        }
 
-        for (entity, extracted_sprite) in extracted_sprites.sprites.iter() {
-            let index = extracted_sprite.original_entity.unwrap_or(*entity).index();
+        if extracted_sprite.flip_x {
+            uv_offset_scale.x += uv_offset_scale.z;
+            uv_offset_scale.z *= -1.0;
+        }
+        if extracted_sprite.flip_y {
+            uv_offset_scale.y += uv_offset_scale.w;
+            uv_offset_scale.w *= -1.0;
+        }
 
-            if !view_entities.contains(index as usize) {
-                continue;
-            }
+        // Override the size if a custom one is specified
+        if let Some(custom_size) = extracted_sprite.custom_size {
+            quad_size = custom_size;
+        }
 
-            // These items will be sorted by depth with other phase items
-            let sort_key = FloatOrd(extracted_sprite.transform.translation().z);
+        let translation = quad_size * (-extracted_sprite.anchor - Vec2::splat(0.5));
+        let scale = quad_size.extend(1.0);
 
-            // Add the item to the render phase
-            transparent_phase.add(Transparent2d {
-                draw_function: draw_sprite_function,
-                pipeline,
-                entity: *entity,
-                sort_key,
-                // batch_range and dynamic_offset will be calculated in prepare_sprites
-                batch_range: 0..0,
-                extra_index: PhaseItemExtraIndex::NONE,
-            });
+        let rotation = extracted_sprite.rotation % 4;
+        let rotation_affine = if rotation == 0 { Affine3A::IDENTITY } else {
+            Affine3A::from_translation((quad_size * Vec2::new(0.5, 0.5)).extend(0.0))
+                * Affine3A::from_rotation_z(PI / 2.0 * f32::from(rotation))
+                * Affine3A::from_translation((quad_size * Vec2::new(-0.5, -0.5)).extend(0.0))
+        };
+
+        let transform =
+            extracted_sprite.transform.affine()
+            * Affine3A::from_translation(translation.extend(0.0))
+            * rotation_affine
+            * Affine3A::from_scale(scale)
+        ;
+
+        // bg and fg are the first two colors of the palette
+        let [bg, fg, ..] = extracted_sprite.colors();
+        let (flags, _) = TextModeSpriteInstanceFlags::from_extracted(extracted_sprite);
+        let glyph = extracted_sprite.glyph.map_or(u32::MAX, |(_, glyph)| glyph);
+
+        Self::from(&transform, bg, fg, flags, glyph, &uv_offset_scale)
+    }
+}
+
+impl TextModeExtractedSprite {
+    /// Pipeline key of the sprites drawn from a custom rect or from the glyph table, with or without
+    /// an indexed palette
+    fn pipeline_key(view_key: TextModeSpritePipelineKey, rect: bool, indexed: bool) -> TextModeSpritePipelineKey {
+        let mut key = view_key;
+        if rect {
+            // Custom rect instances have a single layout
+            key = key.difference(TextModeSpritePipelineKey::COMPACT_INSTANCES) | TextModeSpritePipelineKey::CUSTOM_RECTS;
+        }
+        if indexed {
+            key |= TextModeSpritePipelineKey::INDEXED_PALETTE;
+        }
+        key
+    }
+
+    /// Palette colors of the sprite, `bg` and `fg` first, with the sprite alpha folded in
+    fn colors(&self) -> [LinearRgba; MAX_SPRITE_PALETTE_LEN] {
+        let palette = if self.palette_len > 0 {
+            self.palette
+        } else {
+            [self.bg, self.fg, LinearRgba::NONE, LinearRgba::NONE]
+        };
+        palette.map(|color| color.with_alpha(color.alpha * self.alpha))
+    }
+
+    /// Per batch data of the sprite, sprites with different data are drawn in different batches
+    fn batch_uniform(&self) -> TextModeBatchUniform {
+        let [_, _, palette_2, palette_3] = self.colors();
+        let (pivot, billboard) = TextModeExtracted3d::billboard(self.mode_3d.as_ref());
+        TextModeBatchUniform {
+            palette_2: palette_2.to_vec4(),
+            palette_3: palette_3.to_vec4(),
+            mask: TextModeSpriteInstanceFlags::from_extracted(self).1,
+            pivot,
+            billboard,
+        }
+    }
+}
+
+/// UV offset and scale of the texture `rect`, from its bottom left corner
+fn uv_offset_scale_of(rect: Rect, image_size: Vec2) -> Vec4 {
+    let rect_size = rect.size();
+    Vec4::new(
+        rect.min.x / image_size.x,
+        rect.max.y / image_size.y,
+        rect_size.x / image_size.x,
+        -rect_size.y / image_size.y,
+    )
+}
+
+/// Transposed 3x4 model matrix of an instance
+fn model_transpose(transform: &Affine3A) -> [[f32; 4]; 3] {
+    let transpose_model_3x3 = transform.matrix3.transpose();
+    [
+        transpose_model_3x3.x_axis.extend(transform.translation.x).to_array(),
+        transpose_model_3x3.y_axis.extend(transform.translation.y).to_array(),
+        transpose_model_3x3.z_axis.extend(transform.translation.z).to_array(),
+    ]
+}
+
+/// Instance data of the [`TextModeInstanceLayout::Full`] layout
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+pub(crate) struct TextModeSpriteInstance {
+    pub i_model_transpose: [[f32; 4]; 3],
+    /// Colors with the sprite alpha folded in
+    pub i_bg: [f32; 4],
+    pub i_fg: [f32; 4],
+    /// Index of the glyph in the glyph table of the atlas layout
+    pub i_glyph: u32,
+    pub i_flags: u32,
+}
+
+impl TextModeInstance for TextModeSpriteInstance {
+    #[inline]
+    fn from(transform: &Affine3A, bg: LinearRgba, fg: LinearRgba, flags: TextModeSpriteInstanceFlags, glyph: u32, _uv_offset_scale: &Vec4) -> Self {
+        Self {
+            i_model_transpose: model_transpose(transform),
+            i_bg: bg.to_f32_array(),
+            i_fg: fg.to_f32_array(),
+            i_glyph: glyph,
+            i_flags: flags.bits(),
+        }
+    }
+}
+
+impl TextModeSpriteInstance {
+    /// Layout of the instance buffer, must match the `VertexInput` of `text_mode_sprite.wgsl`
+    fn vertex_buffer_layout() -> VertexBufferLayout {
+        let attributes = [
+            (VertexFormat::Float32x4, 0),  // i_model_transpose_col0
+            (VertexFormat::Float32x4, 16), // i_model_transpose_col1
+            (VertexFormat::Float32x4, 32), // i_model_transpose_col2
+            (VertexFormat::Float32x4, 48), // i_bg
+            (VertexFormat::Float32x4, 64), // i_fg
+            (VertexFormat::Uint32, 80),    // i_glyph
+            (VertexFormat::Uint32, 84),    // i_flags
+        ];
+        instance_buffer_layout::<Self>(&attributes)
+    }
+}
+
+/// Packed instance data of the [`TextModeInstanceLayout::Compact`] layout
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+pub(crate) struct TextModeCompactSpriteInstance {
+    /// Columns of the 2D linear part of the model transform
+    pub i_model: [f32; 4],
+    pub i_translation: [f32; 3],
+    /// Index of the glyph in the glyph table of the atlas layout
+    pub i_glyph: u32,
+    /// Colors, sRGB encoded with the sprite alpha folded in
+    pub i_bg: [u8; 4],
+    pub i_fg: [u8; 4],
+    pub i_flags: u32,
+}
+
+impl TextModeInstance for TextModeCompactSpriteInstance {
+    #[inline]
+    fn from(transform: &Affine3A, bg: LinearRgba, fg: LinearRgba, flags: TextModeSpriteInstanceFlags, glyph: u32, _uv_offset_scale: &Vec4) -> Self {
+        let (x_axis, y_axis) = (transform.matrix3.x_axis, transform.matrix3.y_axis);
+        Self {
+            i_model: [x_axis.x, x_axis.y, y_axis.x, y_axis.y],
+            i_translation: transform.translation.to_array(),
+            i_glyph: glyph,
+            i_bg: Srgba::from(bg).to_u8_array(),
+            i_fg: Srgba::from(fg).to_u8_array(),
+            i_flags: flags.bits(),
+        }
+    }
+}
+
+impl TextModeCompactSpriteInstance {
+    /// Layout of the instance buffer, must match the `COMPACT_INSTANCES` `VertexInput` of `text_mode_sprite.wgsl`
+    fn vertex_buffer_layout() -> VertexBufferLayout {
+        let attributes = [
+            (VertexFormat::Float32x4, 0),  // i_model
+            (VertexFormat::Float32x3, 16), // i_translation
+            (VertexFormat::Uint32, 28),    // i_glyph
+            (VertexFormat::Unorm8x4, 32),  // i_bg
+            (VertexFormat::Unorm8x4, 36),  // i_fg
+            (VertexFormat::Uint32, 40),    // i_flags
+        ];
+        instance_buffer_layout::<Self>(&attributes)
+    }
+}
+
+/// Instance data of the sprites drawn from a custom rect or a whole image, whatever the [`TextModeInstanceLayout`]
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+pub(crate) struct TextModeRectSpriteInstance {
+    pub i_model_transpose: [[f32; 4]; 3],
+    /// Colors with the sprite alpha folded in
+    pub i_bg: [f32; 4],
+    pub i_fg: [f32; 4],
+    pub i_uv: [f32; 4],
+    pub i_flags: u32,
+}
+
+impl TextModeInstance for TextModeRectSpriteInstance {
+    #[inline]
+    fn from(transform: &Affine3A, bg: LinearRgba, fg: LinearRgba, flags: TextModeSpriteInstanceFlags, _glyph: u32, uv_offset_scale: &Vec4) -> Self {
+        Self {
+            i_model_transpose: model_transpose(transform),
+            i_bg: bg.to_f32_array(),
+            i_fg: fg.to_f32_array(),
+            i_uv: uv_offset_scale.to_array(),
+            i_flags: flags.bits(),
+        }
+    }
+}
+
+impl TextModeRectSpriteInstance {
+    /// Layout of the instance buffer, must match the `CUSTOM_RECTS` `VertexInput` of `text_mode_sprite.wgsl`
+    fn vertex_buffer_layout() -> VertexBufferLayout {
+        let attributes = [
+            (VertexFormat::Float32x4, 0),  // i_model_transpose_col0
+            (VertexFormat::Float32x4, 16), // i_model_transpose_col1
+            (VertexFormat::Float32x4, 32), // i_model_transpose_col2
+            (VertexFormat::Float32x4, 48), // i_bg
+            (VertexFormat::Float32x4, 64), // i_fg
+            (VertexFormat::Float32x4, 80), // i_uv_offset_scale
+            (VertexFormat::Uint32, 96),    // i_flags
+        ];
+        instance_buffer_layout::<Self>(&attributes)
+    }
+}
+
+/// Per instance vertex buffer layout of `T`, with consecutive shader locations
+fn instance_buffer_layout<T>(attributes: &[(VertexFormat, u64)]) -> VertexBufferLayout {
+    VertexBufferLayout {
+        array_stride: std::mem::size_of::<T>() as u64,
+        step_mode: VertexStepMode::Instance,
+        attributes: attributes
+            .iter()
+            .enumerate()
+            .map(|(shader_location, &(format, offset))| VertexAttribute {
+                format,
+                offset,
+                shader_location: shader_location as u32,
+            })
+            .collect(),
+    }
+}
+
+/// Cell of a [`TextModeGrid::gpu_driven`] grid, must match the `GPU_DRIVEN_GRID` `Cell` of `text_mode_sprite.wgsl`
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+pub(crate) struct TextModePackedCell {
+    /// Index of the glyph in the atlas layout, glyphs missing from the layout are not drawn
+    pub glyph: u32,
+    /// sRGB encoded colors
+    pub fg: u32,
+    pub bg: u32,
+    pub flags: u32,
+}
+
+impl TextModePackedCell {
+    /// Cell left empty, past the end of a scrollback row
+    const EMPTY: Self = Self {
+        glyph: u32::MAX,
+        fg: 0,
+        bg: 0,
+        flags: 0,
+    };
+
+    fn new(cell: &TextModeCell, cursor: Option<TextModeCursor>, mask_flags: TextModeSpriteInstanceFlags) -> Self {
+        let mut flags = mask_flags
+            | TextModeSpriteInstanceFlags::from_cell(cell.skip_bg, cell.skip_fg, cell.blink, cursor)
+            | TextModeSpriteInstanceFlags::from_flip(cell.flip_x, cell.flip_y);
+        flags |= TextModeSpriteInstanceFlags::from_bits_retain(u32::from(cell.rotation % 4) << 16);
+        Self {
+            glyph: u32::try_from(cell.index).unwrap_or(u32::MAX),
+            fg: u32::from_le_bytes(Srgba::from(cell.fg).to_u8_array()),
+            bg: u32::from_le_bytes(Srgba::from(cell.bg).to_u8_array()),
+            flags: flags.bits(),
+        }
+    }
+}
+
+mod uniforms {
+    // The field checks generated by `ShaderType` are never used for private types
+    #![allow(dead_code)]
+
+    use bevy::prelude::*;
+    use bevy::render::render_resource::ShaderType;
+
+    /// Data shared by the instances of a batch, must match the `Batch` of `text_mode_sprite.wgsl`
+    #[derive(ShaderType, Clone, Copy, Default, PartialEq)]
+    pub(crate) struct TextModeBatchUniform {
+        /// Indexed palette colors past `bg` and `fg`, with the sprite alpha folded in
+        pub palette_2: Vec4,
+        pub palette_3: Vec4,
+        /// Mask parameters, threshold or key color
+        pub mask: Vec4,
+        /// Translation of a billboard entity, its model transforms having no rotation
+        pub pivot: Vec3,
+        /// Billboard mode, see [`TextModeBillboard`](crate::TextModeBillboard)
+        pub billboard: u32,
+    }
+
+    /// Per grid data of a [`TextModeGrid::gpu_driven`](crate::TextModeGrid::gpu_driven) grid,
+    /// must match the `GPU_DRIVEN_GRID` `Grid` of `text_mode_sprite.wgsl`
+    #[derive(ShaderType, Clone, Copy, Default)]
+    pub(crate) struct TextModeGridUniform {
+        pub world_from_local: Mat4,
+        /// Top left corner of the first cell
+        pub origin: Vec2,
+        pub cell_size: Vec2,
+        pub mask: Vec4,
+        pub width: u32,
+        /// Offset of the grid in the cell buffer
+        pub first_cell: u32,
+        pub alpha: f32,
+        /// Billboard mode, `world_from_local` having no rotation
+        pub billboard: u32,
+    }
+}
+
+/// Cells of a [`TextModeGrid::gpu_driven`] grid, ready to be copied to the storage buffers
+pub(crate) struct TextModeExtractedPackedGrid {
+    pub uniform: TextModeGridUniform,
+    pub cells: Vec<TextModePackedCell>,
+}
+
+/// Whether the device supports the storage buffers of [`TextModeGrid::gpu_driven`] grids
+fn gpu_driven_grids_supported(render_device: &RenderDevice) -> bool {
+    render_device.limits().max_storage_buffers_per_shader_stage >= 1
+}
+
+/// See [bevy::sprite::SpriteMeta]
+#[derive(Resource)]
+pub struct TextModeSpriteMeta {
+    sprite_index_buffer: RawBufferVec<u32>,
+    sprite_instance_buffer: TextModeInstanceBuffer<TextModeSpriteInstance>,
+    compact_instance_buffer: TextModeInstanceBuffer<TextModeCompactSpriteInstance>,
+    rect_instance_buffer: TextModeInstanceBuffer<TextModeRectSpriteInstance>,
+    grid_cell_buffer: TextModeInstanceBuffer<TextModePackedCell>,
+    grid_uniforms: DynamicUniformBuffer<TextModeGridUniform>,
+    batch_uniforms: DynamicUniformBuffer<TextModeBatchUniform>,
+    /// Bind group of the batch uniforms
+    batch_bind_group: Option<BindGroup>,
+    /// Bind group of the uniforms and cells of the GPU driven grids
+    grid_bind_group: Option<BindGroup>,
+}
+
+impl Default for TextModeSpriteMeta {
+    fn default() -> Self {
+        Self {
+            sprite_index_buffer: RawBufferVec::<u32>::new(BufferUsages::INDEX),
+            sprite_instance_buffer: TextModeInstanceBuffer::new(BufferUsages::VERTEX),
+            compact_instance_buffer: TextModeInstanceBuffer::new(BufferUsages::VERTEX),
+            rect_instance_buffer: TextModeInstanceBuffer::new(BufferUsages::VERTEX),
+            grid_cell_buffer: TextModeInstanceBuffer::new(BufferUsages::STORAGE),
+            grid_uniforms: DynamicUniformBuffer::default(),
+            batch_uniforms: DynamicUniformBuffer::default(),
+            batch_bind_group: None,
+            grid_bind_group: None,
+        }
+    }
+}
+
+/// Blink and cursor blink phases of the [`TextModeBlinkTimer`], bound with the view
+#[derive(Resource, Default)]
+pub struct TextModeBlinkUniformBuffer {
+    buffer: UniformBuffer<Vec2>,
+}
+
+pub fn extract_text_mode_blink_timer(
+    mut blink_uniform: ResMut<TextModeBlinkUniformBuffer>,
+    timer: Extract<Res<TextModeBlinkTimer>>,
+) {
+    blink_uniform.buffer.set(Vec2::new(timer.phase(), timer.cursor_phase()));
+}
+
+pub fn prepare_text_mode_blink_uniform(
+    mut blink_uniform: ResMut<TextModeBlinkUniformBuffer>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+) {
+    blink_uniform.buffer.write_buffer(&render_device, &render_queue);
+}
+
+#[derive(Component)]
+pub struct TextModeSpriteViewBindGroup {
+    pub value: BindGroup,
+}
+
+#[derive(PartialEq, Eq, Clone)]
+pub struct TextModeSpriteBatch {
+    image_handle_id: AssetId<Image>,
+    /// Range of instances, or of cells of a GPU driven grid
+    range: Range<u32>,
+    /// Dynamic offset of the batch uniform, or of the grid uniform of a GPU driven grid
+    uniform_offset: u32,
+    /// Atlas layout whose glyph table is bound, `None` for custom rect instances
+    layout: Option<AssetId<TextureAtlasLayout>>,
+    instances: TextModeBatchInstances,
+}
+
+/// Buffer holding the instances of a batch
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+enum TextModeBatchInstances {
+    /// [`TextModeInstanceLayout::Full`] glyph instances
+    Full,
+    /// [`TextModeInstanceLayout::Compact`] glyph instances
+    Compact,
+    /// Instances of the sprites drawn from a custom rect or a whole image
+    Rect,
+    /// Cells of a GPU driven grid, read by instance index
+    GridCells,
+}
+
+/// Batches of the phase items, keyed by view and item entity as an entity can be drawn by several views
+#[derive(Resource, Default)]
+pub struct TextModeSpriteBatches {
+    values: HashMap<(Entity, Entity), TextModeSpriteBatch>,
+}
+
+impl TextModeSpriteBatches {
+    fn get(&self, view: Entity, item: &impl PhaseItem) -> Option<&TextModeSpriteBatch> {
+        self.values.get(&(view, item.entity()))
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct TextModeImageBindGroups {
+    values: HashMap<AssetId<Image>, BindGroup>,
+}
+
+/// Pipeline key bits of a view
+fn view_key(
+    msaa_key: TextModeSpritePipelineKey,
+    view: &ExtractedView,
+    tonemapping: Option<&Tonemapping>,
+    dither: Option<&DebandDither>,
+) -> TextModeSpritePipelineKey {
+    let mut view_key = TextModeSpritePipelineKey::from_hdr(view.hdr) | msaa_key;
+
+    if !view.hdr {
+        if let Some(tonemapping) = tonemapping {
+            view_key |= TextModeSpritePipelineKey::TONEMAP_IN_SHADER;
+            view_key |= match tonemapping {
+                Tonemapping::None => TextModeSpritePipelineKey::TONEMAP_METHOD_NONE,
+                Tonemapping::Reinhard => TextModeSpritePipelineKey::TONEMAP_METHOD_REINHARD,
+                Tonemapping::ReinhardLuminance => {
+                    TextModeSpritePipelineKey::TONEMAP_METHOD_REINHARD_LUMINANCE
+                }
+                Tonemapping::AcesFitted => TextModeSpritePipelineKey::TONEMAP_METHOD_ACES_FITTED,
+                Tonemapping::AgX => TextModeSpritePipelineKey::TONEMAP_METHOD_AGX,
+                Tonemapping::SomewhatBoringDisplayTransform => {
+                    TextModeSpritePipelineKey::TONEMAP_METHOD_SOMEWHAT_BORING_DISPLAY_TRANSFORM
+                }
+                Tonemapping::TonyMcMapface => TextModeSpritePipelineKey::TONEMAP_METHOD_TONY_MC_MAPFACE,
+                Tonemapping::BlenderFilmic => TextModeSpritePipelineKey::TONEMAP_METHOD_BLENDER_FILMIC,
+            };
+        }
+        if let Some(DebandDither::Enabled) = dither {
+            view_key |= TextModeSpritePipelineKey::DEBAND_DITHER;
+        }
+    }
+    view_key
+}
+
+/// See [bevy::sprite::queue_sprites]
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn queue_text_mode_sprites(
+    mut view_entities: Local<FixedBitSet>,
+    draw_functions: Res<DrawFunctions<Transparent2d>>,
+    sprite_pipeline: Res<TextModeSpritePipeline>,
+    mut pipelines: ResMut<SpecializedRenderPipelines<TextModeSpritePipeline>>,
+    pipeline_cache: Res<PipelineCache>,
+    msaa: Res<Msaa>,
+    instance_layout: Res<TextModeInstanceLayout>,
+    extracted_sprites: Res<ExtractedTextModeSprites>,
+    extracted_grids: Res<ExtractedTextModeGrids>,
+    mut transparent_render_phases: ResMut<ViewSortedRenderPhases<Transparent2d>>,
+    mut views: Query<(
+        Entity,
+        &VisibleEntities,
+        &ExtractedView,
+        Option<&Tonemapping>,
+        Option<&DebandDither>,
+    )>,
+) {
+    let mut msaa_key = TextModeSpritePipelineKey::from_msaa_samples(msaa.samples());
+    if *instance_layout == TextModeInstanceLayout::Compact {
+        msaa_key |= TextModeSpritePipelineKey::COMPACT_INSTANCES;
+    }
+
+    let draw_sprite_function = draw_functions.read().id::<DrawTextModeSprite>();
+
+    for (view_entity, visible_entities, view, tonemapping, dither) in &mut views {
+        let Some(transparent_phase) = transparent_render_phases.get_mut(&view_entity) else {
+            continue;
+        };
+
+        let view_key = view_key(msaa_key, view, tonemapping, dither);
+
+        let pipeline = pipelines.specialize(&pipeline_cache, &sprite_pipeline, view_key);
+        let [sprite_pipeline_ids, rect_pipeline_ids] = [false, true].map(|rect| {
+            [false, true].map(|indexed| {
+                let key = TextModeExtractedSprite::pipeline_key(view_key, rect, indexed);
+                pipelines.specialize(&pipeline_cache, &sprite_pipeline, key)
+            })
+        });
+        let gpu_driven_pipeline = pipelines.specialize(
+            &pipeline_cache,
+            &sprite_pipeline,
+            view_key.difference(TextModeSpritePipelineKey::COMPACT_INSTANCES) | TextModeSpritePipelineKey::GPU_DRIVEN_GRID,
+        );
+
+        view_entities.clear();
+        view_entities.extend(
+            visible_entities
+                .iter::<WithTextModeSprite>()
+                .map(|e| e.index() as usize),
+        );
+
+        transparent_phase
+            .items
+            .reserve(extracted_sprites.sprites.len() + extracted_grids.grids.len());
+
+        for (entity, extracted_quads) in extracted_sprites.sprites.iter() {
+            let extracted_sprite = extracted_quads.first();
+
+            // 3D sprites are queued by queue_text_mode_sprites_3d
+            if !view_entities.contains(entity.index() as usize) || extracted_sprite.mode_3d.is_some() {
+                continue;
+            }
+
+            // These items will be sorted by depth with other phase items
+            let sort_key = FloatOrd(extracted_quads.transform.translation().z);
+
+            let pipeline_ids = if extracted_sprite.glyph.is_some() { sprite_pipeline_ids } else { rect_pipeline_ids };
+
+            // Add the item to the render phase
+            transparent_phase.add(Transparent2d {
+                draw_function: draw_sprite_function,
+                pipeline: pipeline_ids[usize::from(extracted_sprite.palette_len > 0)],
+                entity: *entity,
+                sort_key,
+                // batch_range and dynamic_offset will be calculated in prepare_sprites
+                batch_range: 0..0,
+                extra_index: PhaseItemExtraIndex::NONE,
+            });
+        }
+
+        for (entity, extracted_grid) in extracted_grids.grids.iter() {
+            if !view_entities.contains(entity.index() as usize) || extracted_grid.mode_3d.is_some() {
+                continue;
+            }
+
+            // The whole grid is a single phase item
+            transparent_phase.add(Transparent2d {
+                draw_function: draw_sprite_function,
+                pipeline: if extracted_grid.packed.is_some() { gpu_driven_pipeline } else { pipeline },
+                entity: *entity,
+                sort_key: FloatOrd(extracted_grid.transform.translation().z),
+                batch_range: 0..0,
+                extra_index: PhaseItemExtraIndex::NONE,
+            });
+        }
+    }
+}
+
+/// Queues the [`TextMode3d`] sprites and grids in the transparent 3D phase
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn queue_text_mode_sprites_3d(
+    mut view_entities: Local<FixedBitSet>,
+    draw_functions: Res<DrawFunctions<Transparent3d>>,
+    sprite_pipeline: Res<TextModeSpritePipeline>,
+    mut pipelines: ResMut<SpecializedRenderPipelines<TextModeSpritePipeline>>,
+    pipeline_cache: Res<PipelineCache>,
+    msaa: Res<Msaa>,
+    extracted_sprites: Res<ExtractedTextModeSprites>,
+    extracted_grids: Res<ExtractedTextModeGrids>,
+    mut transparent_render_phases: ResMut<ViewSortedRenderPhases<Transparent3d>>,
+    mut views: Query<(
+        Entity,
+        &VisibleEntities,
+        &ExtractedView,
+        Option<&Tonemapping>,
+        Option<&DebandDither>,
+    )>,
+) {
+    // 3D sprites and grids always use the full instance layout
+    let msaa_key = TextModeSpritePipelineKey::from_msaa_samples(msaa.samples());
+
+    let draw_sprite_function = draw_functions.read().id::<DrawTextModeSprite>();
+
+    for (view_entity, visible_entities, view, tonemapping, dither) in &mut views {
+        let Some(transparent_phase) = transparent_render_phases.get_mut(&view_entity) else {
+            continue;
+        };
+
+        let view_key = view_key(msaa_key, view, tonemapping, dither) | TextModeSpritePipelineKey::DEPTH_TEST;
+        let rangefinder = view.rangefinder3d();
+
+        view_entities.clear();
+        view_entities.extend(
+            visible_entities
+                .iter::<WithTextModeSprite>()
+                .map(|e| e.index() as usize),
+        );
+
+        let mut specialize = |mode_3d: &TextModeExtracted3d, key: TextModeSpritePipelineKey| {
+            let key = if mode_3d.settings.depth_write { key | TextModeSpritePipelineKey::DEPTH_WRITE } else { key };
+            pipelines.specialize(&pipeline_cache, &sprite_pipeline, key)
+        };
+
+        for (entity, extracted_quads) in extracted_sprites.sprites.iter() {
+            let extracted_sprite = extracted_quads.first();
+            let Some(mode_3d) = &extracted_sprite.mode_3d else {
+                continue;
+            };
+            if !view_entities.contains(entity.index() as usize) {
+                continue;
+            }
+
+            let key = TextModeExtractedSprite::pipeline_key(
+                view_key,
+                extracted_sprite.glyph.is_none(),
+                extracted_sprite.palette_len > 0,
+            );
+            transparent_phase.add(Transparent3d {
+                distance: rangefinder.distance_translation(&extracted_quads.transform.translation()),
+                pipeline: specialize(mode_3d, key),
+                entity: *entity,
+                draw_function: draw_sprite_function,
+                batch_range: 0..0,
+                extra_index: PhaseItemExtraIndex::NONE,
+            });
+        }
+
+        for (entity, extracted_grid) in extracted_grids.grids.iter() {
+            let Some(mode_3d) = &extracted_grid.mode_3d else {
+                continue;
+            };
+            if !view_entities.contains(entity.index() as usize) {
+                continue;
+            }
+
+            let key = if extracted_grid.packed.is_some() {
+                view_key | TextModeSpritePipelineKey::GPU_DRIVEN_GRID
+            } else {
+                view_key
+            };
+            transparent_phase.add(Transparent3d {
+                distance: rangefinder.distance_translation(&extracted_grid.transform.translation()),
+                pipeline: specialize(mode_3d, key),
+                entity: *entity,
+                draw_function: draw_sprite_function,
+                batch_range: 0..0,
+                extra_index: PhaseItemExtraIndex::NONE,
+            });
         }
     }
 }
@@ -632,12 +1769,13 @@ pub fn prepare_text_mode_sprite_view_bind_groups(
     render_device: Res<RenderDevice>,
     sprite_pipeline: Res<TextModeSpritePipeline>,
     view_uniforms: Res<ViewUniforms>,
+    blink_uniform: Res<TextModeBlinkUniformBuffer>,
     views: Query<(Entity, &Tonemapping), With<ExtractedView>>,
     tonemapping_luts: Res<TonemappingLuts>,
     images: Res<RenderAssets<GpuImage>>,
     fallback_image: Res<FallbackImage>,
 ) {
-    let Some(view_binding) = view_uniforms.uniforms.binding() else {
+    let (Some(view_binding), Some(blink_binding)) = (view_uniforms.uniforms.binding(), blink_uniform.buffer.binding()) else {
         return;
     };
 
@@ -651,6 +1789,7 @@ pub fn prepare_text_mode_sprite_view_bind_groups(
                 (0, view_binding.clone()),
                 (1, lut_bindings.0),
                 (2, lut_bindings.1),
+                (3, blink_binding.clone()),
             )),
         );
 
@@ -662,16 +1801,19 @@ pub fn prepare_text_mode_sprite_view_bind_groups(
 
 #[allow(clippy::too_many_arguments)]
 pub fn prepare_text_mode_sprite_image_bind_groups(
-    mut commands: Commands,
     mut previous_len: Local<usize>,
     render_device: Res<RenderDevice>,
     render_queue: Res<RenderQueue>,
     mut sprite_meta: ResMut<TextModeSpriteMeta>,
+    instance_layout: Res<TextModeInstanceLayout>,
+    instance_stats: Res<TextModeInstanceStats>,
     sprite_pipeline: Res<TextModeSpritePipeline>,
     mut image_bind_groups: ResMut<TextModeImageBindGroups>,
+    mut sprite_batches: ResMut<TextModeSpriteBatches>,
     gpu_images: Res<RenderAssets<GpuImage>>,
     extracted_sprites: Res<ExtractedTextModeSprites>,
-    mut phases: ResMut<ViewSortedRenderPhases<Transparent2d>>,
+    extracted_grids: Res<ExtractedTextModeGrids>,
+    (mut phases_2d, mut phases_3d): (ResMut<ViewSortedRenderPhases<Transparent2d>>, ResMut<ViewSortedRenderPhases<Transparent3d>>),
     events: Res<SpriteAssetEvents>,
 ) {
     // If an image has changed, the GpuImage has (probably) changed
@@ -685,43 +1827,189 @@ pub fn prepare_text_mode_sprite_image_bind_groups(
         };
     }
 
-    let mut batches: Vec<(Entity, TextModeSpriteBatch)> = Vec::with_capacity(*previous_len);
+    // Instances stay resident between frames, only the uniforms are rebuilt
+    sprite_meta.grid_uniforms.clear();
+    sprite_meta.batch_uniforms.clear();
+
+    let mut batcher = TextModeBatcher {
+        render_device: &render_device,
+        sprite_pipeline: &sprite_pipeline,
+        gpu_images: &gpu_images,
+        extracted_sprites: &extracted_sprites,
+        extracted_grids: &extracted_grids,
+        instance_layout: *instance_layout,
+        sprite_meta: &mut sprite_meta,
+        image_bind_groups: &mut image_bind_groups,
+        batches: Vec::with_capacity(*previous_len),
+        fragmented_batches: 0,
+    };
+    for (view_entity, transparent_phase) in phases_2d.iter_mut() {
+        batcher.batch(*view_entity, transparent_phase);
+    }
+    for (view_entity, transparent_phase) in phases_3d.iter_mut() {
+        batcher.batch(*view_entity, transparent_phase);
+    }
+    let (batches, fragmented_batches) = (batcher.batches, batcher.fragmented_batches);
+
+    // Only the slots written this frame are uploaded
+    let sprite_meta = &mut *sprite_meta;
+    let full_upload = sprite_meta
+        .sprite_instance_buffer
+        .end_frame(&render_device, &render_queue);
+    let compact_upload = sprite_meta
+        .compact_instance_buffer
+        .end_frame(&render_device, &render_queue);
+    let rect_upload = sprite_meta
+        .rect_instance_buffer
+        .end_frame(&render_device, &render_queue);
+    let grid_upload = sprite_meta
+        .grid_cell_buffer
+        .end_frame(&render_device, &render_queue);
+    instance_stats.set(full_upload + compact_upload + rect_upload + grid_upload);
+
+    // When the draw order doesn't follow the slots anymore, the instances are written again in draw order
+    if fragmented_batches > MAX_FRAGMENTED_BATCHES.max(batches.len() / 4) {
+        sprite_meta.sprite_instance_buffer.relayout();
+        sprite_meta.compact_instance_buffer.relayout();
+        sprite_meta.rect_instance_buffer.relayout();
+    }
+
+    sprite_meta
+        .batch_uniforms
+        .write_buffer(&render_device, &render_queue);
+    sprite_meta.batch_bind_group = sprite_meta.batch_uniforms.binding().map(|uniforms| {
+        render_device.create_bind_group(
+            "text_mode_batch_bind_group",
+            &sprite_pipeline.batch_layout,
+            &BindGroupEntries::single(uniforms),
+        )
+    });
+
+    // The cells and uniforms of the GPU driven grids, their glyph tables being bound separately
+    sprite_meta
+        .grid_uniforms
+        .write_buffer(&render_device, &render_queue);
+    sprite_meta.grid_bind_group = match (
+        &sprite_pipeline.grid_layout,
+        sprite_meta.grid_uniforms.binding(),
+        sprite_meta.grid_cell_buffer.buffer(),
+    ) {
+        (Some(bind_group_layout), Some(uniforms), Some(cells)) => Some(render_device.create_bind_group(
+            "text_mode_grid_bind_group",
+            bind_group_layout,
+            &BindGroupEntries::sequential((uniforms, cells.as_entire_binding())),
+        )),
+        _ => None,
+    };
+
+    if sprite_meta.sprite_index_buffer.len() != 6 {
+        sprite_meta.sprite_index_buffer.clear();
+
+        // NOTE: This code is creating 6 indices pointing to 4 vertices.
+        // The vertices form the corners of a quad based on their two least significant bits.
+        // 10   11
+        //
+        // 00   01
+        // The sprite shader can then use the two least significant bits as the vertex index.
+        // The rest of the properties to transform the vertex positions and UVs (which are
+        // implicit) are baked into the instance transform, and UV offset and scale.
+        // See bevy_sprite/src/render/sprite.wgsl for the details.
+        sprite_meta.sprite_index_buffer.push(2);
+        sprite_meta.sprite_index_buffer.push(0);
+        sprite_meta.sprite_index_buffer.push(1);
+        sprite_meta.sprite_index_buffer.push(1);
+        sprite_meta.sprite_index_buffer.push(3);
+        sprite_meta.sprite_index_buffer.push(2);
 
-    // Clear the sprite instances
-    sprite_meta.sprite_instance_buffer.clear();
+        sprite_meta
+            .sprite_index_buffer
+            .write_buffer(&render_device, &render_queue);
+    }
 
-    // Index buffer indices
-    let mut index = 0;
+    *previous_len = batches.len();
+    sprite_batches.values.clear();
+    sprite_batches.values.extend(batches);
+}
 
-    let image_bind_groups = &mut *image_bind_groups;
+/// Batches split by non contiguous instances tolerated before the instances are laid out again,
+/// along with a quarter of the batches
+const MAX_FRAGMENTED_BATCHES: usize = 8;
+
+/// Phase items batching state of [`prepare_text_mode_sprite_image_bind_groups`]
+struct TextModeBatcher<'a> {
+    render_device: &'a RenderDevice,
+    sprite_pipeline: &'a TextModeSpritePipeline,
+    gpu_images: &'a RenderAssets<GpuImage>,
+    extracted_sprites: &'a ExtractedTextModeSprites,
+    extracted_grids: &'a ExtractedTextModeGrids,
+    instance_layout: TextModeInstanceLayout,
+    sprite_meta: &'a mut TextModeSpriteMeta,
+    image_bind_groups: &'a mut TextModeImageBindGroups,
+    batches: Vec<((Entity, Entity), TextModeSpriteBatch)>,
+    /// Batches split only because the instances of consecutive items aren't contiguous
+    fragmented_batches: usize,
+}
 
-    for transparent_phase in phases.values_mut() {
+impl TextModeBatcher<'_> {
+    /// Batches consecutive items of the `phase` of `view` sharing an image, a pipeline, a batch uniform and
+    /// contiguous instances, writing the instances of the changed items
+    fn batch<P: SortedPhaseItem + CachedRenderPipelinePhaseItem>(&mut self, view: Entity, phase: &mut SortedRenderPhase<P>) {
         let mut batch_item_index = 0;
         let mut batch_image_size = Vec2::ZERO;
         let mut batch_image_handle = AssetId::invalid();
-
-        for item_index in 0..transparent_phase.items.len() {
-            let item = &transparent_phase.items[item_index];
-            let Some(extracted_sprite) = extracted_sprites.sprites.get(&item.entity) else {
-                batch_image_handle = AssetId::invalid();
-                continue;
+        let mut batch_pipeline = CachedRenderPipelineId::INVALID;
+        let mut batch_uniform = None;
+        let mut batch_layout = None;
+
+        for item_index in 0..phase.items.len() {
+            let item = &phase.items[item_index];
+            let (entity, item_pipeline) = (item.entity(), item.cached_pipeline());
+            let extracted_quads = self.extracted_sprites.sprites.get(&entity);
+            let extracted_grid = self.extracted_grids.grids.get(&entity);
+            let (image_handle_id, mode_3d, changed) = match (extracted_quads, extracted_grid) {
+                (Some(quads), _) => (quads.first().image_handle_id, quads.first().mode_3d.as_ref(), quads.changed),
+                (None, Some(grid)) => (grid.image_handle_id, grid.mode_3d.as_ref(), grid.changed),
+                (None, None) => {
+                    batch_image_handle = AssetId::invalid();
+                    continue;
+                }
+            };
+            let compact = self.instance_layout.is_compact(mode_3d);
+            let (uniform, layout) = match (extracted_quads, extracted_grid) {
+                (Some(quads), _) => (quads.first().batch_uniform(), quads.first().glyph.map(|(layout, _)| layout)),
+                (None, grid) => (grid.map(|grid| grid.uniform).unwrap_or_default(), grid.map(|grid| grid.layout_id)),
+            };
+            let instances = match (layout, compact) {
+                (None, _) => TextModeBatchInstances::Rect,
+                (Some(_), true) => TextModeBatchInstances::Compact,
+                (Some(_), false) => TextModeBatchInstances::Full,
             };
 
-            let batch_image_changed = batch_image_handle != extracted_sprite.image_handle_id;
-            if batch_image_changed {
-                let Some(gpu_image) = gpu_images.get(extracted_sprite.image_handle_id) else {
+            // Sprites drawn with different pipelines, batch uniforms or glyph tables can't be batched together,
+            // every GPU driven grid is drawn on its own
+            let packed_grid = extracted_grid.and_then(|grid| grid.packed.as_ref());
+            let batch_changed = batch_image_handle != image_handle_id
+                || batch_pipeline != item_pipeline
+                || batch_uniform != Some(uniform)
+                || batch_layout != layout
+                || packed_grid.is_some();
+            if batch_changed {
+                let Some(gpu_image) = self.gpu_images.get(image_handle_id) else {
                     continue;
                 };
 
                 batch_image_size = gpu_image.size.as_vec2();
-                batch_image_handle = extracted_sprite.image_handle_id;
-                image_bind_groups
+                batch_image_handle = image_handle_id;
+                batch_pipeline = item_pipeline;
+                batch_uniform = Some(uniform);
+                batch_layout = layout;
+                self.image_bind_groups
                     .values
                     .entry(batch_image_handle)
                     .or_insert_with(|| {
-                        render_device.create_bind_group(
+                        self.render_device.create_bind_group(
                             "text_mode_sprite_material_bind_group",
-                            &sprite_pipeline.material_layout,
+                            &self.sprite_pipeline.material_layout,
                             &BindGroupEntries::sequential((
                                 &gpu_image.texture_view,
                                 &gpu_image.sampler,
@@ -730,123 +2018,118 @@ pub fn prepare_text_mode_sprite_image_bind_groups(
                     });
             }
 
-            // By default, the size of the quad is the size of the texture
-            let mut quad_size = batch_image_size;
-
-            // Calculate vertex data for this item
-            let mut uv_offset_scale: Vec4;
-
-            // If a rect is specified, adjust UVs and the size of the quad
-            if let Some(rect) = extracted_sprite.rect {
-                let rect_size = rect.size();
-                uv_offset_scale = Vec4::new(
-                    rect.min.x / batch_image_size.x,
-                    rect.max.y / batch_image_size.y,
-                    rect_size.x / batch_image_size.x,
-                    -rect_size.y / batch_image_size.y,
-                );
-                quad_size = rect_size;
-            } else {
-                uv_offset_scale = Vec4::new(0.0, 1.0, 1.0, -1.0);
-            }
-
-            if extracted_sprite.flip_x {
-                uv_offset_scale.x += uv_offset_scale.z;
-                uv_offset_scale.z *= -1.0;
-            }
-            if extracted_sprite.flip_y {
-                uv_offset_scale.y += uv_offset_scale.w;
-                uv_offset_scale.w *= -1.0;
-            }
+            let sprite_meta = &mut *self.sprite_meta;
+            if let Some(packed_grid) = packed_grid {
+                let range = sprite_meta
+                    .grid_cell_buffer
+                    .resident(entity, changed)
+                    .unwrap_or_else(|| sprite_meta.grid_cell_buffer.write(entity, packed_grid.cells.iter().copied()));
+                let uniform = TextModeGridUniform {
+                    first_cell: range.start,
+                    ..packed_grid.uniform
+                };
+                let uniform_offset = sprite_meta.grid_uniforms.push(&uniform);
 
-            // Override the size if a custom one is specified
-            if let Some(custom_size) = extracted_sprite.custom_size {
-                quad_size = custom_size;
+                // A single phase item drawing all the cells
+                self.batches.push((
+                    (view, entity),
+                    TextModeSpriteBatch {
+                        image_handle_id: batch_image_handle,
+                        range,
+                        uniform_offset,
+                        layout,
+                        instances: TextModeBatchInstances::GridCells,
+                    },
+                ));
+                batch_item_index = item_index;
+                batch_uniform = None;
+                phase.items[item_index].batch_range_mut().end += 1;
+                continue;
             }
 
-            let translation = quad_size * (-extracted_sprite.anchor - Vec2::splat(0.5));
-            let scale = quad_size.extend(1.0);
-
-            let rotation = extracted_sprite.rotation % 4;
-            let rotation_affine = if rotation == 0 { Affine3A::IDENTITY } else {
-                Affine3A::from_translation((quad_size * Vec2::new(0.5, 0.5)).extend(0.0))
-                    * Affine3A::from_rotation_z(PI / 2.0 * f32::from(rotation))
-                    * Affine3A::from_translation((quad_size * Vec2::new(-0.5, -0.5)).extend(0.0))
+            // Unchanged items reuse their resident instances
+            let quads = extracted_quads.map_or(&[][..], |quads| &quads.quads[..]);
+            let range = match instances {
+                TextModeBatchInstances::Compact => resident_instances(
+                    &mut sprite_meta.compact_instance_buffer,
+                    entity,
+                    changed,
+                    quads,
+                    extracted_grid.map_or(&[][..], |grid| &grid.compact_instances[..]),
+                    batch_image_size,
+                ),
+                TextModeBatchInstances::Rect => resident_instances(
+                    &mut sprite_meta.rect_instance_buffer,
+                    entity,
+                    changed,
+                    quads,
+                    &[],
+                    batch_image_size,
+                ),
+                _ => resident_instances(
+                    &mut sprite_meta.sprite_instance_buffer,
+                    entity,
+                    changed,
+                    quads,
+                    extracted_grid.map_or(&[][..], |grid| &grid.instances[..]),
+                    batch_image_size,
+                ),
             };
 
-            let transform =
-                extracted_sprite.transform.affine()
-                * Affine3A::from_translation(translation.extend(0.0))
-                * rotation_affine
-                * Affine3A::from_scale(scale)
-            ;
-
-            // Store the vertex data and add the item to the render phase
-            sprite_meta
-                .sprite_instance_buffer
-                .push(TextModeSpriteInstance::from(
-                    &transform,
-                    &extracted_sprite.bg,
-                    &extracted_sprite.fg,
-                    extracted_sprite.alpha,
-                    &uv_offset_scale,
-                ));
-
-            if batch_image_changed {
+            // Items are only merged when their instances follow the instances of the batch
+            let contiguous = !batch_changed && self.batches.last().is_some_and(|(_, batch)| batch.range.end == range.start);
+            if !contiguous {
+                if !batch_changed {
+                    self.fragmented_batches += 1;
+                }
                 batch_item_index = item_index;
 
-                batches.push((
-                    item.entity,
+                self.batches.push((
+                    (view, entity),
                     TextModeSpriteBatch {
                         image_handle_id: batch_image_handle,
-                        range: index..index,
+                        range: range.start..range.start,
+                        uniform_offset: sprite_meta.batch_uniforms.push(&uniform),
+                        layout,
+                        instances,
                     },
                 ));
             }
 
-            transparent_phase.items[batch_item_index]
-                .batch_range_mut()
-                .end += 1;
-            batches.last_mut().unwrap().1.range.end += 1;
-            index += 1;
+            // The batch range of the first item counts the merged phase items, skipped when rendering,
+            // whereas the range of the batch counts the instances
+            phase.items[batch_item_index].batch_range_mut().end += 1;
+            self.batches.last_mut().unwrap().1.range.end = range.end;
         }
     }
-    sprite_meta
-        .sprite_instance_buffer
-        .write_buffer(&render_device, &render_queue);
-
-    if sprite_meta.sprite_index_buffer.len() != 6 {
-        sprite_meta.sprite_index_buffer.clear();
-
-        // NOTE: This code is creating 6 indices pointing to 4 vertices.
-        // The vertices form the corners of a quad based on their two least significant bits.
-        // 10   11
-        //
-        // 00   01
-        // The sprite shader can then use the two least significant bits as the vertex index.
-        // The rest of the properties to transform the vertex positions and UVs (which are
-        // implicit) are baked into the instance transform, and UV offset and scale.
-        // See bevy_sprite/src/render/sprite.wgsl for the details.
-        sprite_meta.sprite_index_buffer.push(2);
-        sprite_meta.sprite_index_buffer.push(0);
-        sprite_meta.sprite_index_buffer.push(1);
-        sprite_meta.sprite_index_buffer.push(1);
-        sprite_meta.sprite_index_buffer.push(3);
-        sprite_meta.sprite_index_buffer.push(2);
+}
 
-        sprite_meta
-            .sprite_index_buffer
-            .write_buffer(&render_device, &render_queue);
+/// Range of the resident instances of the quads of an extracted sprite, or else of an extracted grid,
+/// writing them if the item changed
+fn resident_instances<T: TextModeInstance>(
+    buffer: &mut TextModeInstanceBuffer<T>,
+    entity: Entity,
+    changed: bool,
+    quads: &[TextModeExtractedSprite],
+    grid_instances: &[T],
+    image_size: Vec2,
+) -> Range<u32> {
+    if let Some(range) = buffer.resident(entity, changed) {
+        return range;
+    }
+    if quads.is_empty() {
+        buffer.write(entity, grid_instances.iter().copied())
+    } else {
+        buffer.write(entity, quads.iter().map(|quad| T::from_extracted(quad, image_size)))
     }
-
-    *previous_len = batches.len();
-    commands.insert_or_spawn_batch(batches);
 }
 
 pub type DrawTextModeSprite = (
     SetItemPipeline,
     SetTextModeSpriteViewBindGroup<0>,
     SetTextModeSpriteTextureBindGroup<1>,
+    SetTextModeBatchBindGroup<2>,
+    SetTextModeGlyphTableBindGroup<3>,
     DrawTextModeSpriteBatch,
 );
 
@@ -869,19 +2152,19 @@ impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetTextModeSpriteViewBin
 }
 pub struct SetTextModeSpriteTextureBindGroup<const I: usize>;
 impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetTextModeSpriteTextureBindGroup<I> {
-    type Param = SRes<TextModeImageBindGroups>;
-    type ViewQuery = ();
-    type ItemQuery = Read<TextModeSpriteBatch>;
+    type Param = (SRes<TextModeImageBindGroups>, SRes<TextModeSpriteBatches>);
+    type ViewQuery = Entity;
+    type ItemQuery = ();
 
     fn render<'w>(
-        _item: &P,
-        _view: (),
-        batch: Option<&'_ TextModeSpriteBatch>,
-        image_bind_groups: SystemParamItem<'w, '_, Self::Param>,
+        item: &P,
+        view: Entity,
+        _entity: Option<()>,
+        (image_bind_groups, batches): SystemParamItem<'w, '_, Self::Param>,
         pass: &mut TrackedRenderPass<'w>,
     ) -> RenderCommandResult {
         let image_bind_groups = image_bind_groups.into_inner();
-        let Some(batch) = batch else {
+        let Some(batch) = batches.get(view, item) else {
             return RenderCommandResult::Failure;
         };
 
@@ -897,21 +2180,81 @@ impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetTextModeSpriteTexture
     }
 }
 
+/// Binds the batch uniform, or the uniform and storage buffers of GPU driven grids
+pub struct SetTextModeBatchBindGroup<const I: usize>;
+impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetTextModeBatchBindGroup<I> {
+    type Param = (SRes<TextModeSpriteMeta>, SRes<TextModeSpriteBatches>);
+    type ViewQuery = Entity;
+    type ItemQuery = ();
+
+    fn render<'w>(
+        item: &P,
+        view: Entity,
+        _entity: Option<()>,
+        (sprite_meta, batches): SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some(batch) = batches.into_inner().get(view, item) else {
+            return RenderCommandResult::Failure;
+        };
+        let sprite_meta = sprite_meta.into_inner();
+        let bind_group = match batch.instances {
+            TextModeBatchInstances::GridCells => sprite_meta.grid_bind_group.as_ref(),
+            _ => sprite_meta.batch_bind_group.as_ref(),
+        };
+        let Some(bind_group) = bind_group else {
+            return RenderCommandResult::Failure;
+        };
+
+        pass.set_bind_group(I, bind_group, &[batch.uniform_offset]);
+        RenderCommandResult::Success
+    }
+}
+
+/// Binds the glyph table of the atlas layout of the batch, custom rect instances having none
+pub struct SetTextModeGlyphTableBindGroup<const I: usize>;
+impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetTextModeGlyphTableBindGroup<I> {
+    type Param = (SRes<TextModeGpuAtlasLayouts>, SRes<TextModeSpriteBatches>);
+    type ViewQuery = Entity;
+    type ItemQuery = ();
+
+    fn render<'w>(
+        item: &P,
+        view: Entity,
+        _entity: Option<()>,
+        (gpu_layouts, batches): SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some(batch) = batches.into_inner().get(view, item) else {
+            return RenderCommandResult::Failure;
+        };
+        let Some(layout_id) = batch.layout else {
+            return RenderCommandResult::Success;
+        };
+        let Some(bind_group) = gpu_layouts.into_inner().bind_group(layout_id) else {
+            return RenderCommandResult::Failure;
+        };
+
+        pass.set_bind_group(I, bind_group, &[]);
+        RenderCommandResult::Success
+    }
+}
+
 pub struct DrawTextModeSpriteBatch;
 impl<P: PhaseItem> RenderCommand<P> for DrawTextModeSpriteBatch {
-    type Param = SRes<TextModeSpriteMeta>;
-    type ViewQuery = ();
-    type ItemQuery = Read<TextModeSpriteBatch>;
+    type Param = (SRes<TextModeSpriteMeta>, SRes<TextModeSpriteBatches>);
+    type ViewQuery = Entity;
+    type ItemQuery = ();
 
     fn render<'w>(
-        _item: &P,
-        _view: (),
-        batch: Option<&'_ TextModeSpriteBatch>,
-        sprite_meta: SystemParamItem<'w, '_, Self::Param>,
+        item: &P,
+        view: Entity,
+        _entity: Option<()>,
+        (sprite_meta, batches): SystemParamItem<'w, '_, Self::Param>,
         pass: &mut TrackedRenderPass<'w>,
     ) -> RenderCommandResult {
         let sprite_meta = sprite_meta.into_inner();
-        let Some(batch) = batch else {
+        let Some(batch) = batches.into_inner().get(view, item) else {
             return RenderCommandResult::Failure;
         };
 
@@ -920,15 +2263,158 @@ impl<P: PhaseItem> RenderCommand<P> for DrawTextModeSpriteBatch {
             0,
             IndexFormat::Uint32,
         );
-        pass.set_vertex_buffer(
-            0,
-            sprite_meta
-                .sprite_instance_buffer
-                .buffer()
-                .unwrap()
-                .slice(..),
-        );
+        // GPU driven grids have no instance buffer, cells are read by instance index
+        let instance_buffer = match batch.instances {
+            TextModeBatchInstances::Full => sprite_meta.sprite_instance_buffer.buffer(),
+            TextModeBatchInstances::Compact => sprite_meta.compact_instance_buffer.buffer(),
+            TextModeBatchInstances::Rect => sprite_meta.rect_instance_buffer.buffer(),
+            TextModeBatchInstances::GridCells => None,
+        };
+        if let Some(instance_buffer) = instance_buffer {
+            pass.set_vertex_buffer(0, instance_buffer.slice(..));
+        }
         pass.draw_indexed(0..6, 0, batch.range.clone());
         RenderCommandResult::Success
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extracted_sprite() -> TextModeExtractedSprite {
+        TextModeExtractedSprite {
+            transform: GlobalTransform::IDENTITY,
+            bg: LinearRgba::BLACK,
+            fg: LinearRgba::WHITE,
+            alpha: 0.5,
+            custom_size: None,
+            rect: None,
+            glyph: None,
+            image_handle_id: AssetId::invalid(),
+            flip_x: false,
+            flip_y: false,
+            rotation: 0,
+            anchor: Vec2::ZERO,
+            palette: [LinearRgba::NONE; 4],
+            palette_len: 0,
+            mask: TextModeMask::Key(LinearRgba::rgb(0.2, 0.4, 0.6)),
+            invert_mask: false,
+            skip_bg: false,
+            skip_fg: false,
+            coverage: false,
+            blink: TextModeBlink::None,
+            cursor: None,
+            mode_3d: None,
+        }
+    }
+
+    #[test]
+    fn glyph_tables() {
+        let layout = TextureAtlasLayout::from_grid(UVec2::new(8, 16), 3, 1, None, None);
+        let table = glyph_table(&layout);
+        assert_eq!(table.len(), GLYPH_TABLE_WIDTH);
+        assert_eq!(table[1], [1.0 / 3.0, 1.0, 1.0 / 3.0, -1.0]);
+        assert_eq!(table[3], [0.0; 4]);
+
+        let layout = TextureAtlasLayout::from_grid(UVec2::ONE, 257, 1, None, None);
+        assert_eq!(glyph_table(&layout).len(), 2 * GLYPH_TABLE_WIDTH);
+        assert_eq!(glyph_table(&TextureAtlasLayout::new_empty(UVec2::ONE)).len(), GLYPH_TABLE_WIDTH);
+    }
+
+    #[test]
+    fn glyph_flips() {
+        let mut sprite = extracted_sprite();
+        sprite.glyph = Some((AssetId::invalid(), 2));
+        sprite.flip_y = true;
+        let instance = TextModeSpriteInstance::from_extracted(&sprite, Vec2::ONE);
+        assert_eq!(instance.i_glyph, 2);
+        assert_eq!(instance.i_flags & TextModeSpriteInstanceFlags::FLIP_Y.bits(), TextModeSpriteInstanceFlags::FLIP_Y.bits());
+        assert_eq!(instance.i_flags & TextModeSpriteInstanceFlags::FLIP_X.bits(), 0);
+    }
+
+    #[test]
+    fn instance_sizes() {
+        assert_eq!(TextModeInstanceLayout::Full.instance_size(), 88);
+        assert_eq!(TextModeInstanceLayout::Compact.instance_size(), 44);
+        assert_eq!(std::mem::size_of::<TextModeRectSpriteInstance>(), 100);
+        for (layout, size) in [
+            (TextModeSpriteInstance::vertex_buffer_layout(), std::mem::size_of::<TextModeSpriteInstance>()),
+            (TextModeCompactSpriteInstance::vertex_buffer_layout(), std::mem::size_of::<TextModeCompactSpriteInstance>()),
+            (TextModeRectSpriteInstance::vertex_buffer_layout(), std::mem::size_of::<TextModeRectSpriteInstance>()),
+        ] {
+            assert_eq!(layout.array_stride, size as u64);
+            let end = layout.attributes.iter().map(|a| a.offset + a.format.size()).max();
+            assert_eq!(end, Some(size as u64));
+        }
+    }
+
+    #[test]
+    fn folded_alpha() {
+        let sprite = extracted_sprite();
+        let instance = TextModeSpriteInstance::from_extracted(&sprite, Vec2::ONE);
+        assert_eq!(instance.i_bg, [0.0, 0.0, 0.0, 0.5]);
+        assert_eq!(instance.i_fg, [1.0, 1.0, 1.0, 0.5]);
+        let compact = TextModeCompactSpriteInstance::from_extracted(&sprite, Vec2::ONE);
+        assert_eq!(compact.i_fg, [255, 255, 255, 128]);
+
+        let indexed = TextModeExtractedSprite {
+            palette: [LinearRgba::BLACK, LinearRgba::WHITE, LinearRgba::RED, LinearRgba::BLUE],
+            palette_len: 4,
+            ..extracted_sprite()
+        };
+        let uniform = indexed.batch_uniform();
+        assert_eq!(uniform.palette_2, Vec4::new(1.0, 0.0, 0.0, 0.5));
+        assert_eq!(uniform.palette_3, Vec4::new(0.0, 0.0, 1.0, 0.5));
+    }
+
+    #[test]
+    fn billboards() {
+        let transform = GlobalTransform::from(
+            Transform::from_xyz(1.0, 2.0, 3.0)
+                .with_rotation(Quat::from_rotation_x(1.0))
+                .with_scale(Vec3::splat(2.0)),
+        );
+        let billboard = TextModeExtracted3d::new(
+            TextMode3d {
+                billboard: TextModeBillboard::Cylindrical,
+                ..default()
+            },
+            &transform,
+        );
+
+        // The vertex shader turns billboards around their translation, their instances having no rotation
+        let drawn = TextModeExtracted3d::drawn_transform(Some(&billboard), &transform);
+        let (scale, rotation, translation) = drawn.to_scale_rotation_translation();
+        assert!(scale.abs_diff_eq(Vec3::splat(2.0), 1e-5));
+        assert!(rotation.abs_diff_eq(Quat::IDENTITY, 1e-5));
+        assert_eq!(translation, Vec3::new(1.0, 2.0, 3.0));
+        assert_eq!(TextModeExtracted3d::billboard(Some(&billboard)), (translation, 2));
+
+        let sprite = TextModeExtractedSprite {
+            mode_3d: Some(billboard),
+            ..extracted_sprite()
+        };
+        assert_eq!(sprite.batch_uniform().pivot, translation);
+
+        // Other 3D entities keep their transform
+        let oriented = TextModeExtracted3d::new(TextMode3d::default(), &transform);
+        assert_eq!(TextModeExtracted3d::drawn_transform(Some(&oriented), &transform), transform);
+        assert_eq!(TextModeExtracted3d::billboard(Some(&oriented)).1, 0);
+    }
+
+    #[test]
+    fn compact_layout_2d_only() {
+        let mode_3d = TextModeExtracted3d::new(TextMode3d::default(), &GlobalTransform::IDENTITY);
+        assert!(TextModeInstanceLayout::Compact.is_compact(None));
+        assert!(!TextModeInstanceLayout::Compact.is_compact(Some(&mode_3d)));
+        assert!(!TextModeInstanceLayout::Full.is_compact(None));
+    }
+
+    #[test]
+    fn exact_mask() {
+        // Key colors are not quantized, whatever the instance layout
+        let uniform = extracted_sprite().batch_uniform();
+        assert_eq!(uniform.mask, Vec4::new(0.2, 0.4, 0.6, 1.0));
+    }
+}
@@ -0,0 +1,41 @@
+use bevy::prelude::*;
+
+/// Orientation of a [`TextMode3d`] sprite or grid relative to the camera
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+pub enum TextModeBillboard {
+    /// Oriented by the entity transform
+    #[default]
+    None,
+    /// Parallel to the camera plane
+    Spherical,
+    /// Rotated around the Y axis to face the camera, staying upright
+    Cylindrical,
+}
+
+impl TextModeBillboard {
+    /// Billboard mode of `text_mode_sprite.wgsl`, billboards being turned in the vertex shader
+    pub(crate) fn shader_mode(self) -> u32 {
+        match self {
+            Self::None => 0,
+            Self::Spherical => 1,
+            Self::Cylindrical => 2,
+        }
+    }
+}
+
+/// Draws a [`TextModeSprite`](crate::TextModeSprite) or [`TextModeGrid`](crate::TextModeGrid)
+/// in the transparent 3D phase of 3D cameras, with depth testing, instead of the 2D phase.
+///
+/// Sprites are drawn in the XY plane of their transform. 3D sprites and grids always use the
+/// [`TextModeInstanceLayout::Full`](crate::TextModeInstanceLayout::Full) layout, which keeps rotations
+/// out of that plane, even when the compact layout is selected for 2D.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+pub struct TextMode3d {
+    /// Billboards keep the translation and scale of their transform, the rotation is replaced.
+    /// They are turned in the vertex shader, facing every camera drawing them.
+    pub billboard: TextModeBillboard,
+    /// Writes the depth of the drawn pixels, so that opaque cells hide what is drawn behind them later.
+    ///
+    /// Discarded pixels (skipped background or foreground) don't write depth.
+    pub depth_write: bool,
+}
@@ -0,0 +1,284 @@
+use bevy::prelude::*;
+
+use crate::TextModeBlink;
+
+/// The 16 standard VGA colors, in ANSI order (black, red, green, yellow, blue, magenta, cyan, white,
+/// then their high intensity variants)
+const ANSI_COLORS: [[u8; 3]; 16] = [
+    [0x00, 0x00, 0x00],
+    [0xAA, 0x00, 0x00],
+    [0x00, 0xAA, 0x00],
+    [0xAA, 0x55, 0x00],
+    [0x00, 0x00, 0xAA],
+    [0xAA, 0x00, 0xAA],
+    [0x00, 0xAA, 0xAA],
+    [0xAA, 0xAA, 0xAA],
+    [0x55, 0x55, 0x55],
+    [0xFF, 0x55, 0x55],
+    [0x55, 0xFF, 0x55],
+    [0xFF, 0xFF, 0x55],
+    [0x55, 0x55, 0xFF],
+    [0xFF, 0x55, 0xFF],
+    [0x55, 0xFF, 0xFF],
+    [0xFF, 0xFF, 0xFF],
+];
+
+/// Color `index` of the xterm 256 color palette, the first 16 colors being the VGA colors
+pub fn ansi_color(index: u8) -> LinearRgba {
+    let [r, g, b] = match index {
+        0..=15 => ANSI_COLORS[index as usize],
+        16..=231 => {
+            let level = |c: u8| if c == 0 { 0 } else { 55 + c * 40 };
+            let i = index - 16;
+            [level(i / 36), level(i / 6 % 6), level(i % 6)]
+        }
+        _ => [8 + (index - 232) * 10; 3],
+    };
+    Srgba::rgb_u8(r, g, b).into()
+}
+
+/// A color set by an SGR sequence
+#[derive(Debug, Clone, Copy, PartialEq, Default, Reflect)]
+pub enum AnsiColor {
+    /// The default foreground or background color
+    #[default]
+    Default,
+    /// Index in the 256 color palette, see [`ansi_color`]
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+impl AnsiColor {
+    /// Resolves the color, `bright` selecting the high intensity variant of the 8 base colors
+    pub fn to_linear(self, default: LinearRgba, bright: bool) -> LinearRgba {
+        match self {
+            AnsiColor::Default => default,
+            AnsiColor::Indexed(i @ 0..=7) if bright => ansi_color(i + 8),
+            AnsiColor::Indexed(i) => ansi_color(i),
+            AnsiColor::Rgb(r, g, b) => Srgba::rgb_u8(r, g, b).into(),
+        }
+    }
+}
+
+/// Character attributes set by SGR (`ESC [ ... m`) sequences
+#[derive(Debug, Clone, Copy, PartialEq, Default, Reflect)]
+pub struct AnsiAttributes {
+    pub fg: AnsiColor,
+    pub bg: AnsiColor,
+    pub bold: bool,
+    pub blink: bool,
+    pub inverse: bool,
+}
+
+impl AnsiAttributes {
+    /// Applies the parameters of an SGR sequence, unknown parameters are ignored
+    pub fn apply_sgr(&mut self, params: &[u16]) {
+        if params.is_empty() {
+            *self = Self::default();
+            return;
+        }
+        let mut params = params.iter().copied();
+        while let Some(param) = params.next() {
+            match param {
+                0 => *self = Self::default(),
+                1 => self.bold = true,
+                5 | 6 => self.blink = true,
+                7 => self.inverse = true,
+                21 | 22 => self.bold = false,
+                25 => self.blink = false,
+                27 => self.inverse = false,
+                30..=37 => self.fg = AnsiColor::Indexed((param - 30) as u8),
+                38 => self.fg = extended_color(&mut params).unwrap_or(self.fg),
+                39 => self.fg = AnsiColor::Default,
+                40..=47 => self.bg = AnsiColor::Indexed((param - 40) as u8),
+                48 => self.bg = extended_color(&mut params).unwrap_or(self.bg),
+                49 => self.bg = AnsiColor::Default,
+                90..=97 => self.fg = AnsiColor::Indexed((param - 90 + 8) as u8),
+                100..=107 => self.bg = AnsiColor::Indexed((param - 100 + 8) as u8),
+                _ => {}
+            }
+        }
+    }
+
+    /// Resolves the `(bg, fg)` colors of a cell.
+    ///
+    /// Bold selects a high intensity foreground. With `ice_colors`, blink selects a high intensity
+    /// background instead of blinking.
+    pub fn colors(&self, default_bg: LinearRgba, default_fg: LinearRgba, ice_colors: bool) -> (LinearRgba, LinearRgba) {
+        let bg = self.bg.to_linear(default_bg, ice_colors && self.blink);
+        let fg = self.fg.to_linear(default_fg, self.bold);
+        if self.inverse {
+            (fg, bg)
+        } else {
+            (bg, fg)
+        }
+    }
+
+    /// Blinking of a cell, the foreground blinks unless `ice_colors` is set
+    pub fn blink(&self, ice_colors: bool) -> TextModeBlink {
+        match self.blink && !ice_colors {
+            true => TextModeBlink::Fg,
+            false => TextModeBlink::None,
+        }
+    }
+}
+
+/// Reads the `5;n` or `2;r;g;b` parameters following an extended color SGR parameter
+fn extended_color(params: &mut impl Iterator<Item = u16>) -> Option<AnsiColor> {
+    let mut component = || params.next().map(|p| p.min(255) as u8);
+    match component()? {
+        5 => component().map(AnsiColor::Indexed),
+        2 => Some(AnsiColor::Rgb(component()?, component()?, component()?)),
+        _ => None,
+    }
+}
+
+/// An action decoded by [`AnsiParser`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnsiAction {
+    /// A printable byte
+    Print(u8),
+    /// A C0 control byte, e.g. `\n`
+    Control(u8),
+    /// A control sequence `ESC [ private? params intermediates? action`.
+    /// Missing parameters are `0`.
+    Csi {
+        params: Vec<u16>,
+        private: Option<u8>,
+        intermediate: Option<u8>,
+        action: u8,
+    },
+    /// An escape sequence `ESC intermediate? action`
+    Esc { intermediate: Option<u8>, action: u8 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+enum AnsiParserState {
+    #[default]
+    Ground,
+    Escape,
+    Csi,
+    /// Operating system command or other string, ignored until `BEL` or `ESC \`
+    String,
+    StringEscape,
+}
+
+/// Streaming decoder of ANSI escape sequences.
+///
+/// Bytes are fed one at a time with [`AnsiParser::advance`], sequences may be split across calls.
+#[derive(Debug, Clone, Default)]
+pub struct AnsiParser {
+    state: AnsiParserState,
+    params: Vec<u16>,
+    has_param: bool,
+    private: Option<u8>,
+    intermediate: Option<u8>,
+}
+
+impl AnsiParser {
+    /// Decodes the next byte, returning an action when a character or sequence is complete
+    pub fn advance(&mut self, byte: u8) -> Option<AnsiAction> {
+        match self.state {
+            AnsiParserState::Ground => match byte {
+                0x1B => {
+                    self.state = AnsiParserState::Escape;
+                    self.intermediate = None;
+                    None
+                }
+                0x00..=0x1F | 0x7F => Some(AnsiAction::Control(byte)),
+                _ => Some(AnsiAction::Print(byte)),
+            },
+            AnsiParserState::Escape => match byte {
+                b'[' => {
+                    self.state = AnsiParserState::Csi;
+                    self.params.clear();
+                    self.has_param = false;
+                    self.private = None;
+                    None
+                }
+                b']' | b'P' | b'X' | b'^' | b'_' => {
+                    self.state = AnsiParserState::String;
+                    None
+                }
+                0x20..=0x2F => {
+                    self.intermediate = Some(byte);
+                    None
+                }
+                0x1B => None,
+                _ => {
+                    self.state = AnsiParserState::Ground;
+                    Some(AnsiAction::Esc {
+                        intermediate: self.intermediate,
+                        action: byte,
+                    })
+                }
+            },
+            AnsiParserState::Csi => match byte {
+                b'0'..=b'9' => {
+                    if !self.has_param {
+                        self.params.push(0);
+                        self.has_param = true;
+                    }
+                    let param = self.params.last_mut().unwrap();
+                    *param = param.saturating_mul(10).saturating_add((byte - b'0') as u16);
+                    None
+                }
+                b';' | b':' => {
+                    if !self.has_param {
+                        self.params.push(0);
+                    }
+                    self.has_param = false;
+                    None
+                }
+                b'<'..=b'?' if self.params.is_empty() && !self.has_param => {
+                    self.private = Some(byte);
+                    None
+                }
+                0x20..=0x2F => {
+                    self.intermediate = Some(byte);
+                    None
+                }
+                0x40..=0x7E => {
+                    self.state = AnsiParserState::Ground;
+                    Some(AnsiAction::Csi {
+                        params: std::mem::take(&mut self.params),
+                        private: self.private,
+                        intermediate: self.intermediate,
+                        action: byte,
+                    })
+                }
+                0x1B => {
+                    self.state = AnsiParserState::Escape;
+                    self.intermediate = None;
+                    None
+                }
+                // Control bytes are executed within sequences
+                0x00..=0x1F => Some(AnsiAction::Control(byte)),
+                _ => None,
+            },
+            AnsiParserState::String => {
+                match byte {
+                    0x07 => self.state = AnsiParserState::Ground,
+                    0x1B => self.state = AnsiParserState::StringEscape,
+                    _ => {}
+                }
+                None
+            }
+            AnsiParserState::StringEscape => {
+                self.state = match byte {
+                    b'\\' => AnsiParserState::Ground,
+                    _ => AnsiParserState::String,
+                };
+                None
+            }
+        }
+    }
+}
+
+/// Returns parameter `i` of a control sequence, `default` if it is missing or `0`
+pub(crate) fn ansi_param(params: &[u16], i: usize, default: u16) -> u16 {
+    match params.get(i) {
+        Some(&p) if p != 0 => p,
+        _ => default,
+    }
+}
@@ -0,0 +1,95 @@
+use bevy::prelude::*;
+
+/// How a sprite or a grid cell blinks, see [`TextModeBlinkTimer`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+pub enum TextModeBlink {
+    #[default]
+    None,
+    /// Foreground pixels are drawn with the background color during the off phase
+    Fg,
+    /// Background and foreground colors are swapped during the off phase
+    Swap,
+    /// Nothing is drawn during the off phase
+    Hide,
+}
+
+/// Global timer driving blinking sprites, cells and cursors.
+///
+/// Blinking is computed in the shader from the timer phases, blinking sprites are never modified.
+#[derive(Resource, Debug, Clone, Reflect)]
+pub struct TextModeBlinkTimer {
+    /// Duration of a blink cycle in seconds, half of it being the off phase.
+    /// Non positive periods disable blinking.
+    pub period: f32,
+    /// Duration of a cursor blink cycle in seconds, non positive periods disabling cursor blinking
+    pub cursor_period: f32,
+    pub paused: bool,
+    /// Time elapsed while the timer was not paused, in seconds
+    pub elapsed: f64,
+}
+
+impl Default for TextModeBlinkTimer {
+    fn default() -> Self {
+        Self {
+            period: 1.0,
+            cursor_period: 0.5,
+            paused: false,
+            elapsed: 0.0,
+        }
+    }
+}
+
+impl TextModeBlinkTimer {
+    /// Position in the blink cycle, from `0` to `1`, the off phase starting at `0.5`
+    pub fn phase(&self) -> f32 {
+        cycle_phase(self.elapsed, self.period)
+    }
+
+    /// Position in the cursor blink cycle, from `0` to `1`, the off phase starting at `0.5`
+    pub fn cursor_phase(&self) -> f32 {
+        cycle_phase(self.elapsed, self.cursor_period)
+    }
+}
+
+/// Stays in the on phase when `period` is not positive
+fn cycle_phase(elapsed: f64, period: f32) -> f32 {
+    match period > 0.0 {
+        true => (elapsed / period as f64).fract() as f32,
+        false => 0.0,
+    }
+}
+
+pub(crate) fn advance_text_mode_blink_timer(mut timer: ResMut<TextModeBlinkTimer>, time: Res<Time>) {
+    if !timer.paused {
+        timer.elapsed += time.delta_seconds_f64();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn phases() {
+        let timer = TextModeBlinkTimer {
+            elapsed: 2.75,
+            ..default()
+        };
+        assert_eq!(timer.phase(), 0.75);
+        assert_eq!(timer.cursor_phase(), 0.5);
+    }
+
+    #[test]
+    fn non_positive_periods() {
+        for period in [0.0, -1.0, f32::NAN] {
+            let timer = TextModeBlinkTimer {
+                period,
+                cursor_period: period,
+                elapsed: 2.75,
+                ..default()
+            };
+            assert_eq!(timer.phase(), 0.0);
+            assert_eq!(timer.cursor_phase(), 0.0);
+        }
+    }
+}
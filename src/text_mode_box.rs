@@ -0,0 +1,237 @@
+use bevy::prelude::*;
+
+use crate::Charset;
+
+/// Line style of a [`TextModeBox`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+pub enum TextModeBoxStyle {
+    #[default]
+    Single,
+    Double,
+    /// Single lines with rounded corners
+    Rounded,
+    Heavy,
+}
+
+/// Box drawn by [`TextModeGrid::draw_box`](crate::TextModeGrid::draw_box)
+#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
+pub struct TextModeBox {
+    pub style: TextModeBoxStyle,
+    /// Colors of the border cells
+    pub bg: LinearRgba,
+    pub fg: LinearRgba,
+    /// Fills the interior cells with spaces of this background color
+    pub fill: Option<LinearRgba>,
+}
+
+impl Default for TextModeBox {
+    fn default() -> Self {
+        Self {
+            style: TextModeBoxStyle::Single,
+            bg: Color::BLACK.to_linear(),
+            fg: Color::WHITE.to_linear(),
+            fill: None,
+        }
+    }
+}
+
+const NONE: u8 = 0;
+const LIGHT: u8 = 1;
+const HEAVY: u8 = 2;
+const DOUBLE: u8 = 3;
+
+/// Lines leaving a cell towards the up, right, down and left sides
+pub(crate) type Arms = [u8; 4];
+
+/// Box drawing characters and their arms, rounded corners last
+const BOX_CHARS: [(char, Arms); 55] = [
+    ('─', [0, 1, 0, 1]), ('│', [1, 0, 1, 0]), ('┌', [0, 1, 1, 0]), ('┐', [0, 0, 1, 1]),
+    ('└', [1, 1, 0, 0]), ('┘', [1, 0, 0, 1]), ('├', [1, 1, 1, 0]), ('┤', [1, 0, 1, 1]),
+    ('┬', [0, 1, 1, 1]), ('┴', [1, 1, 0, 1]), ('┼', [1, 1, 1, 1]),
+    ('━', [0, 2, 0, 2]), ('┃', [2, 0, 2, 0]), ('┏', [0, 2, 2, 0]), ('┓', [0, 0, 2, 2]),
+    ('┗', [2, 2, 0, 0]), ('┛', [2, 0, 0, 2]), ('┣', [2, 2, 2, 0]), ('┫', [2, 0, 2, 2]),
+    ('┳', [0, 2, 2, 2]), ('┻', [2, 2, 0, 2]), ('╋', [2, 2, 2, 2]),
+    ('═', [0, 3, 0, 3]), ('║', [3, 0, 3, 0]), ('╔', [0, 3, 3, 0]), ('╗', [0, 0, 3, 3]),
+    ('╚', [3, 3, 0, 0]), ('╝', [3, 0, 0, 3]), ('╠', [3, 3, 3, 0]), ('╣', [3, 0, 3, 3]),
+    ('╦', [0, 3, 3, 3]), ('╩', [3, 3, 0, 3]), ('╬', [3, 3, 3, 3]),
+    ('╒', [0, 3, 1, 0]), ('╓', [0, 1, 3, 0]), ('╕', [0, 0, 1, 3]), ('╖', [0, 0, 3, 1]),
+    ('╘', [1, 3, 0, 0]), ('╙', [3, 1, 0, 0]), ('╛', [1, 0, 0, 3]), ('╜', [3, 0, 0, 1]),
+    ('╞', [1, 3, 1, 0]), ('╟', [3, 1, 3, 0]), ('╡', [1, 0, 1, 3]), ('╢', [3, 0, 3, 1]),
+    ('╤', [0, 3, 1, 3]), ('╥', [0, 1, 3, 1]), ('╧', [1, 3, 0, 3]), ('╨', [3, 1, 0, 1]),
+    ('╪', [1, 3, 1, 3]), ('╫', [3, 1, 3, 1]),
+    ('╭', [0, 1, 1, 0]), ('╮', [0, 0, 1, 1]), ('╯', [1, 0, 0, 1]), ('╰', [1, 1, 0, 0]),
+];
+
+const ROUNDED_CORNERS: usize = 51;
+
+impl TextModeBoxStyle {
+    pub(crate) fn line(self) -> u8 {
+        match self {
+            Self::Single | Self::Rounded => LIGHT,
+            Self::Heavy => HEAVY,
+            Self::Double => DOUBLE,
+        }
+    }
+}
+
+/// Arms of the box drawing character drawn at `index`, if any.
+///
+/// Only characters the charset has a glyph for are matched, a fallback glyph is not a box drawing character.
+pub(crate) fn box_arms(index: usize, charset: &impl Charset) -> Option<Arms> {
+    BOX_CHARS
+        .iter()
+        .find(|&&(c, _)| charset.glyph_index(c) == Some(index))
+        .map(|&(_, arms)| arms)
+}
+
+/// Atlas index of the box drawing character with the given arms.
+///
+/// Arms missing from the charset are drawn with lighter lines: heavy lines fall back to single lines,
+/// then mixed lines to the `style` lines, then to single lines.
+pub(crate) fn box_index(arms: Arms, style: TextModeBoxStyle, charset: &impl Charset) -> Option<usize> {
+    let find = |arms: Arms| {
+        let chars = if style == TextModeBoxStyle::Rounded {
+            // Rounded corners first
+            BOX_CHARS[ROUNDED_CORNERS..].iter().chain(&BOX_CHARS[..ROUNDED_CORNERS])
+        } else {
+            BOX_CHARS[..ROUNDED_CORNERS].iter().chain(&BOX_CHARS[ROUNDED_CORNERS..])
+        };
+        chars
+            .filter(|&&(_, a)| a == arms)
+            .find_map(|&(c, _)| charset.glyph_index(c))
+    };
+    let with_line = |line: u8| arms.map(|arm| if arm == NONE { NONE } else { line });
+    let unheavy = arms.map(|arm| if arm == HEAVY { LIGHT } else { arm });
+
+    find(arms)
+        .or_else(|| find(unheavy))
+        .or_else(|| find(with_line(style.line())))
+        .or_else(|| find(with_line(LIGHT)))
+}
+
+/// Arms of the border cell `(x, y)` of the box `rect`, `max` being excluded
+pub(crate) fn border_arms(rect: URect, x: u32, y: u32, line: u8) -> Arms {
+    let (right, bottom) = (rect.max.x - 1, rect.max.y - 1);
+    // Boxes 1 cell high or wide are lines
+    if rect.min.y == bottom {
+        return [NONE, line, NONE, line];
+    }
+    if rect.min.x == right {
+        return [line, NONE, line, NONE];
+    }
+    let horizontal = y == rect.min.y || y == bottom;
+    let vertical = x == rect.min.x || x == right;
+    [
+        vertical && y > rect.min.y,
+        horizontal && x < right,
+        vertical && y < bottom,
+        horizontal && x > rect.min.x,
+    ]
+    .map(|arm| if arm { line } else { NONE })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Cp437Charset, TextModeCell, TextModeCharset, TextModeGrid, CP437};
+
+    fn draw(grid: &mut TextModeGrid, rect: URect, style: TextModeBoxStyle, charset: &impl Charset) {
+        let style = TextModeBox {
+            style,
+            ..default()
+        };
+        grid.draw_box(rect, &style, charset);
+    }
+
+    /// Rows of a grid drawn with code page 437 indices
+    fn rows(grid: &TextModeGrid) -> Vec<String> {
+        grid.cells()
+            .chunks(grid.width())
+            .map(|row| row.iter().map(|c| CP437[c.index]).collect())
+            .collect()
+    }
+
+    fn blank_grid(width: usize, height: usize) -> TextModeGrid {
+        let mut grid = TextModeGrid::new(width, height, Handle::default());
+        grid.fill(TextModeCell {
+            index: b' ' as usize,
+            ..default()
+        });
+        grid
+    }
+
+    #[test]
+    fn single_box() {
+        let mut grid = blank_grid(4, 3);
+        draw(&mut grid, URect::new(0, 0, 4, 3), TextModeBoxStyle::Single, &Cp437Charset);
+        assert_eq!(rows(&grid), ["┌──┐", "│  │", "└──┘"]);
+    }
+
+    #[test]
+    fn thin_boxes() {
+        let mut grid = blank_grid(4, 3);
+        draw(&mut grid, URect::new(0, 0, 4, 1), TextModeBoxStyle::Single, &Cp437Charset);
+        draw(&mut grid, URect::new(0, 1, 1, 3), TextModeBoxStyle::Double, &Cp437Charset);
+        draw(&mut grid, URect::new(2, 2, 3, 3), TextModeBoxStyle::Single, &Cp437Charset);
+        assert_eq!(rows(&grid), ["────", "║   ", "║ ─ "]);
+    }
+
+    #[test]
+    fn junctions() {
+        // Boxes sharing an edge are joined with tees, crossing boxes with crosses
+        let mut grid = blank_grid(5, 5);
+        draw(&mut grid, URect::new(0, 0, 3, 3), TextModeBoxStyle::Single, &Cp437Charset);
+        draw(&mut grid, URect::new(2, 0, 5, 3), TextModeBoxStyle::Single, &Cp437Charset);
+        draw(&mut grid, URect::new(1, 2, 4, 5), TextModeBoxStyle::Single, &Cp437Charset);
+        assert_eq!(rows(&grid), ["┌─┬─┐", "│ │ │", "└┬┴┬┘", " │ │ ", " └─┘ "]);
+
+        let mut grid = blank_grid(4, 4);
+        draw(&mut grid, URect::new(0, 0, 3, 3), TextModeBoxStyle::Single, &Cp437Charset);
+        draw(&mut grid, URect::new(1, 1, 4, 4), TextModeBoxStyle::Single, &Cp437Charset);
+        assert_eq!(rows(&grid), ["┌─┐ ", "│┌┼┐", "└┼┘│", " └─┘"]);
+
+        // Mixed crosses where single lines cross double lines
+        let mut grid = blank_grid(4, 4);
+        draw(&mut grid, URect::new(0, 0, 3, 3), TextModeBoxStyle::Double, &Cp437Charset);
+        draw(&mut grid, URect::new(1, 1, 4, 4), TextModeBoxStyle::Single, &Cp437Charset);
+        assert_eq!(rows(&grid), ["╔═╗ ", "║┌╫┐", "╚╪╝│", " └─┘"]);
+
+        // Missing mixed junctions are drawn with the lines of the box
+        let mut grid = blank_grid(5, 3);
+        draw(&mut grid, URect::new(0, 0, 3, 3), TextModeBoxStyle::Single, &Cp437Charset);
+        draw(&mut grid, URect::new(2, 0, 5, 3), TextModeBoxStyle::Double, &Cp437Charset);
+        assert_eq!(rows(&grid), ["┌─╦═╗", "│ ║ ║", "└─╩═╝"]);
+    }
+
+    #[test]
+    fn missing_characters() {
+        // Code page 437 has no heavy lines nor rounded corners
+        let mut grid = blank_grid(3, 3);
+        draw(&mut grid, URect::new(0, 0, 3, 3), TextModeBoxStyle::Heavy, &Cp437Charset);
+        assert_eq!(rows(&grid), ["┌─┐", "│ │", "└─┘"]);
+        draw(&mut grid, URect::new(0, 0, 3, 3), TextModeBoxStyle::Rounded, &Cp437Charset);
+        assert_eq!(rows(&grid), ["┌─┐", "│ │", "└─┘"]);
+    }
+
+    #[test]
+    fn fallback_glyph() {
+        // A charset with a few box drawing characters and a fallback glyph
+        let mut charset = TextModeCharset::from_charset(&Cp437Charset, "┌┐└┘─│├┤┬┴┼ ?".chars());
+        charset.fallback = Some(b'?' as usize);
+
+        // The fallback glyph is not mistaken for a box drawing character
+        let mut grid = blank_grid(3, 3);
+        grid.fill(TextModeCell {
+            index: b'?' as usize,
+            ..default()
+        });
+        draw(&mut grid, URect::new(0, 0, 3, 2), TextModeBoxStyle::Single, &charset);
+        assert_eq!(rows(&grid), ["┌─┐", "└─┘", "???"]);
+
+        // Missing double lines fall back to the charset single lines rather than to the fallback glyph
+        let mut grid = blank_grid(5, 3);
+        draw(&mut grid, URect::new(0, 0, 3, 3), TextModeBoxStyle::Double, &charset);
+        draw(&mut grid, URect::new(2, 0, 5, 3), TextModeBoxStyle::Single, &charset);
+        assert_eq!(rows(&grid), ["┌─┬─┐", "│ │ │", "└─┴─┘"]);
+    }
+}
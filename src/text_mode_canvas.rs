@@ -0,0 +1,151 @@
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+
+use crate::text_mode_font::TextModeFont;
+use crate::text_mode_sauce::Sauce;
+use crate::{TextModeCell, TextModeGrid, TextModeGridBundle, TextModeSprite, TextModeSpriteBundle};
+
+/// A `width` × `height` grid of text mode cells stored as an asset, e.g. loaded from an art file
+///
+/// Cells are stored row by row, `(0, 0)` being the top left cell.
+#[derive(Asset, TypePath, Debug, Clone, Default)]
+pub struct TextModeCanvas {
+    width: usize,
+    height: usize,
+    cells: Vec<TextModeCell>,
+    /// SAUCE metadata of the source file
+    pub sauce: Option<Sauce>,
+    /// Glyph atlas generated from the font embedded in the source file
+    pub font: Option<TextModeFont>,
+}
+
+impl TextModeCanvas {
+    /// Creates a canvas filled with `cell`
+    pub fn new(width: usize, height: usize, cell: TextModeCell) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![cell; width * height],
+            sauce: None,
+            font: None,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// All cells, row by row
+    pub fn cells(&self) -> &[TextModeCell] {
+        &self.cells
+    }
+
+    /// Returns the cell at `(x, y)`, or `None` if it is out of bounds
+    pub fn get(&self, x: usize, y: usize) -> Option<&TextModeCell> {
+        (x < self.width && y < self.height).then(|| &self.cells[y * self.width + x])
+    }
+
+    /// Returns the cell at `(x, y)`, or `None` if it is out of bounds
+    pub fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut TextModeCell> {
+        (x < self.width && y < self.height).then(|| &mut self.cells[y * self.width + x])
+    }
+
+    /// Sets the cell at `(x, y)`, out of bounds positions are ignored
+    pub fn set(&mut self, x: usize, y: usize, cell: TextModeCell) {
+        if let Some(c) = self.get_mut(x, y) {
+            *c = cell;
+        }
+    }
+
+    /// Adds rows filled with `cell` until the canvas is `height` cells high
+    pub fn grow(&mut self, height: usize, cell: TextModeCell) {
+        if height > self.height {
+            self.cells.resize(self.width * height, cell);
+            self.height = height;
+        }
+    }
+
+    /// Creates a [`TextModeGrid`] drawing the canvas with the given atlas layout
+    pub fn to_grid(&self, layout: Handle<TextureAtlasLayout>) -> TextModeGrid {
+        let mut grid = TextModeGrid::new(self.width, self.height, layout);
+        grid.cells_mut().copy_from_slice(&self.cells);
+        grid
+    }
+
+    /// Creates one [`TextModeSpriteBundle`] per cell, the `(0, 0)` cell top left corner being at the origin
+    pub fn sprite_bundles<'a>(
+        &'a self,
+        texture: &'a Handle<Image>,
+        layout: &'a Handle<TextureAtlasLayout>,
+        cell_size: Vec2,
+    ) -> impl Iterator<Item = TextModeSpriteBundle> + 'a {
+        self.cells.iter().enumerate().map(move |(i, cell)| {
+            let (x, y) = (i % self.width, i / self.width);
+            TextModeSpriteBundle {
+                sprite: TextModeSprite {
+                    bg: cell.bg,
+                    fg: cell.fg,
+                    flip_x: cell.flip_x,
+                    flip_y: cell.flip_y,
+                    rotation: cell.rotation,
+                    skip_bg: cell.skip_bg,
+                    skip_fg: cell.skip_fg,
+                    blink: cell.blink,
+                    custom_size: Some(cell_size),
+                    anchor: Anchor::TopLeft,
+                    ..default()
+                },
+                atlas: TextureAtlas {
+                    layout: layout.clone(),
+                    index: cell.index,
+                },
+                texture: texture.clone(),
+                transform: Transform::from_xyz(x as f32 * cell_size.x, -(y as f32) * cell_size.y, 0.),
+                ..default()
+            }
+        })
+    }
+}
+
+/// A stack of [`TextModeCanvas`] layers, the first layer being the bottom one
+#[derive(Asset, TypePath, Debug, Clone, Default)]
+pub struct TextModeLayeredCanvas {
+    pub layers: Vec<TextModeCanvas>,
+}
+
+impl TextModeLayeredCanvas {
+    /// Creates one [`TextModeGridBundle`] per layer, layer `i` being at `z = i * layer_spacing`
+    pub fn grid_bundles<'a>(
+        &'a self,
+        texture: &'a Handle<Image>,
+        layout: &'a Handle<TextureAtlasLayout>,
+        layer_spacing: f32,
+    ) -> impl Iterator<Item = TextModeGridBundle> + 'a {
+        self.layers.iter().enumerate().map(move |(i, layer)| TextModeGridBundle {
+            grid: layer.to_grid(layout.clone()),
+            texture: texture.clone(),
+            transform: Transform::from_xyz(0., 0., i as f32 * layer_spacing),
+            ..default()
+        })
+    }
+
+    /// Creates one [`TextModeSpriteBundle`] per cell of every layer, layer `i` being at `z = i * layer_spacing`
+    pub fn sprite_bundles<'a>(
+        &'a self,
+        texture: &'a Handle<Image>,
+        layout: &'a Handle<TextureAtlasLayout>,
+        cell_size: Vec2,
+        layer_spacing: f32,
+    ) -> impl Iterator<Item = TextModeSpriteBundle> + 'a {
+        self.layers.iter().enumerate().flat_map(move |(i, layer)| {
+            layer.sprite_bundles(texture, layout, cell_size).map(move |mut bundle| {
+                bundle.transform.translation.z = i as f32 * layer_spacing;
+                bundle
+            })
+        })
+    }
+}
@@ -0,0 +1,812 @@
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::asset::io::Reader;
+use bevy::prelude::*;
+use flate2::read::GzDecoder;
+use std::io::Read;
+use thiserror::Error;
+
+use crate::text_mode_ansi::{ansi_color, ansi_param, AnsiAction, AnsiAttributes, AnsiColor, AnsiParser};
+use crate::text_mode_font::TextModeFont;
+use crate::text_mode_sauce::Sauce;
+use crate::{TextModeBlink, TextModeCanvas, TextModeCell, TextModeCharset, TextModeLayeredCanvas};
+
+const DEFAULT_WIDTH: usize = 80;
+const TAB_WIDTH: usize = 8;
+/// Rows of an ANSI art file without a SAUCE height, cursor movements past it being an error
+const MAX_ANSI_HEIGHT: usize = 10_000;
+const SAUCE_CHARACTER: u8 = 1;
+const SAUCE_BINARY_TEXT: u8 = 5;
+const REXPAINT_TRANSPARENT: [u8; 3] = [255, 0, 255];
+/// ANSI order of the VGA attribute colors
+const VGA_TO_ANSI: [u8; 8] = [0, 4, 2, 6, 1, 5, 3, 7];
+
+const XBIN_PALETTE: u8 = 1;
+const XBIN_FONT: u8 = 1 << 1;
+const XBIN_COMPRESS: u8 = 1 << 2;
+const XBIN_NON_BLINK: u8 = 1 << 3;
+const XBIN_512_CHARS: u8 = 1 << 4;
+/// Largest number of decompressed bytes per compressed XBin byte, a 3 bytes run repeating a pair 64 times
+const XBIN_MAX_EXPANSION: usize = 43;
+
+#[derive(Debug, Error)]
+pub enum TextModeCanvasLoaderError {
+    #[error("could not read canvas: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid layer size {width}x{height}")]
+    InvalidLayerSize { width: i32, height: i32 },
+    #[error("missing `{0}` header")]
+    MissingHeader(&'static str),
+    #[error("invalid font height {0}, expected 1 to 32 pixels")]
+    InvalidFontHeight(u8),
+    #[error("canvas size {width}x{height} exceeds the file data")]
+    InvalidCanvasSize { width: usize, height: usize },
+    #[error("text past the last row {0}")]
+    TooManyRows(usize),
+}
+
+/// Writes the decoded sequences of an ANSI art file into a canvas
+struct AnsiArtWriter {
+    canvas: TextModeCanvas,
+    x: usize,
+    /// Cursor row, clamped to `max_height`
+    y: usize,
+    saved: (usize, usize),
+    attributes: AnsiAttributes,
+    ice_colors: bool,
+    /// Number of rows that can be written
+    max_height: usize,
+    /// Whether text was printed past `max_height`
+    overflow: bool,
+}
+
+impl AnsiArtWriter {
+    fn new(width: usize, max_height: usize, ice_colors: bool) -> Self {
+        Self {
+            canvas: TextModeCanvas::new(width, 0, TextModeCell::default()),
+            x: 0,
+            y: 0,
+            saved: (0, 0),
+            attributes: AnsiAttributes::default(),
+            ice_colors,
+            max_height,
+            overflow: false,
+        }
+    }
+
+    /// Row `y` clamped to the row past the last writable one
+    fn row(&self, y: usize) -> usize {
+        y.min(self.max_height)
+    }
+
+    /// A space with the current colors
+    fn blank(&self) -> TextModeCell {
+        let (bg, fg) = self.attributes.colors(ansi_color(0), ansi_color(7), self.ice_colors);
+        TextModeCell {
+            index: b' ' as usize,
+            bg,
+            fg,
+            blink: self.attributes.blink(self.ice_colors),
+            ..default()
+        }
+    }
+
+    fn print(&mut self, byte: u8) {
+        // Wrapping is deferred so that a full line followed by a line break does not skip a line
+        if self.x >= self.canvas.width() {
+            self.x = 0;
+            self.y = self.row(self.y + 1);
+        }
+        if self.y >= self.max_height {
+            self.overflow = true;
+            return;
+        }
+        let cell = TextModeCell {
+            index: byte as usize,
+            ..self.blank()
+        };
+        self.canvas.grow(self.y + 1, self.default_blank());
+        self.canvas.set(self.x, self.y, cell);
+        self.x += 1;
+    }
+
+    /// A space with the default colors, used for rows that were never written
+    fn default_blank(&self) -> TextModeCell {
+        TextModeCell {
+            index: b' ' as usize,
+            bg: ansi_color(0),
+            fg: ansi_color(7),
+            ..default()
+        }
+    }
+
+    fn erase(&mut self, from: (usize, usize), to: (usize, usize)) {
+        let width = self.canvas.width();
+        let (start, end) = (from.1 * width + from.0, to.1 * width + to.0);
+        let blank = self.blank();
+        for i in start..end.min(self.canvas.height() * width) {
+            self.canvas.set(i % width, i / width, blank);
+        }
+    }
+
+    fn control(&mut self, byte: u8) {
+        match byte {
+            b'\r' => self.x = 0,
+            b'\n' => {
+                self.x = 0;
+                self.y = self.row(self.y + 1);
+            }
+            b'\t' => self.x = ((self.x / TAB_WIDTH + 1) * TAB_WIDTH).min(self.canvas.width() - 1),
+            0x08 => self.x = self.x.saturating_sub(1),
+            // Other control bytes are CP437 glyphs in art files
+            _ => self.print(byte),
+        }
+    }
+
+    fn csi(&mut self, params: &[u16], action: u8) {
+        let n = ansi_param(params, 0, 1) as usize;
+        let last_column = self.canvas.width() - 1;
+        match action {
+            b'A' => self.y = self.y.saturating_sub(n),
+            b'B' => self.y = self.row(self.y + n),
+            b'C' => self.x = (self.x + n).min(last_column),
+            b'D' => self.x = self.x.min(last_column).saturating_sub(n),
+            b'E' => (self.x, self.y) = (0, self.row(self.y + n)),
+            b'F' => (self.x, self.y) = (0, self.y.saturating_sub(n)),
+            b'G' => self.x = (n - 1).min(last_column),
+            b'H' | b'f' => {
+                self.y = self.row(ansi_param(params, 0, 1) as usize - 1);
+                self.x = (ansi_param(params, 1, 1) as usize - 1).min(last_column);
+            }
+            b'J' => match params.first().copied().unwrap_or(0) {
+                0 => self.erase((self.x, self.y), (0, self.canvas.height())),
+                1 => self.erase((0, 0), (self.x + 1, self.y)),
+                _ => {
+                    self.erase((0, 0), (0, self.canvas.height()));
+                    (self.x, self.y) = (0, 0);
+                }
+            },
+            b'K' => match params.first().copied().unwrap_or(0) {
+                0 => self.erase((self.x, self.y), (0, self.y + 1)),
+                1 => self.erase((0, self.y), (self.x + 1, self.y)),
+                _ => self.erase((0, self.y), (0, self.y + 1)),
+            },
+            b'm' => self.attributes.apply_sgr(params),
+            b's' => self.saved = (self.x, self.y),
+            b'u' => (self.x, self.y) = self.saved,
+            // PabloDraw 24 bit colors, `ESC [ 0 ; r ; g ; b t` for the background and `1` for the foreground
+            b't' if params.len() == 4 => {
+                let [r, g, b] = [1, 2, 3].map(|i| params[i].min(255) as u8);
+                let color = AnsiColor::Rgb(r, g, b);
+                match params[0] {
+                    0 => self.attributes.bg = color,
+                    1 => self.attributes.fg = color,
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        let mut parser = AnsiParser::default();
+        for &byte in bytes {
+            if byte == 0x1A || self.overflow {
+                break;
+            }
+            match parser.advance(byte) {
+                Some(AnsiAction::Print(b)) => self.print(b),
+                Some(AnsiAction::Control(b)) => self.control(b),
+                Some(AnsiAction::Csi {
+                    params,
+                    private: None,
+                    action,
+                    ..
+                }) => self.csi(&params, action),
+                Some(AnsiAction::Esc { action: b'7', .. }) => self.saved = (self.x, self.y),
+                Some(AnsiAction::Esc { action: b'8', .. }) => (self.x, self.y) = self.saved,
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Parses an ANSI art file, with its SAUCE record if present
+fn parse_ansi(bytes: &[u8]) -> Result<TextModeCanvas, TextModeCanvasLoaderError> {
+    let (contents, sauce) = Sauce::split(bytes);
+    let character_sauce = sauce.as_ref().filter(|s| s.data_type == SAUCE_CHARACTER);
+    let width = character_sauce
+        .map(|s| s.t_info[0] as usize)
+        .filter(|&w| w > 0)
+        .unwrap_or(DEFAULT_WIDTH);
+    let ice_colors = character_sauce.is_some_and(Sauce::ice_colors);
+    let height = character_sauce.map(|s| s.t_info[1] as usize).filter(|&h| h > 0);
+
+    let mut writer = AnsiArtWriter::new(width, height.unwrap_or(MAX_ANSI_HEIGHT), ice_colors);
+    writer.write(contents);
+    if writer.overflow {
+        return Err(TextModeCanvasLoaderError::TooManyRows(writer.max_height));
+    }
+    if let Some(height) = height {
+        let blank = writer.default_blank();
+        writer.canvas.grow(height, blank);
+    }
+    writer.canvas.sauce = sauce;
+    Ok(writer.canvas)
+}
+
+/// Loads ANSI art `.ans` files into a [`TextModeCanvas`] to draw over a CP437 atlas.
+///
+/// Glyph indices are the CP437 codes of the file. 16 colors, 256 colors and 24 bit colors
+/// are supported, as well as cursor movement. The width is read from the SAUCE record,
+/// defaulting to 80 columns, and iCE colors are used when the record enables them.
+/// Text past the SAUCE height, or past 10 000 rows without one, is an error.
+#[derive(Default)]
+pub struct AnsiArtLoader;
+
+impl AssetLoader for AnsiArtLoader {
+    type Asset = TextModeCanvas;
+    type Settings = ();
+    type Error = TextModeCanvasLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<TextModeCanvas, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        parse_ansi(&bytes)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ans"]
+    }
+}
+
+fn read_bytes<const N: usize>(reader: &mut impl Read) -> std::io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_vec(reader: &mut impl Read, len: usize) -> std::io::Result<Vec<u8>> {
+    let mut bytes = vec![0; len];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_i32(reader: &mut impl Read) -> std::io::Result<i32> {
+    read_bytes(reader).map(i32::from_le_bytes)
+}
+
+/// Parses a gzipped REXPaint file
+fn parse_xp(bytes: &[u8]) -> Result<TextModeLayeredCanvas, TextModeCanvasLoaderError> {
+    let mut decompressed = Vec::new();
+    GzDecoder::new(bytes).read_to_end(&mut decompressed)?;
+    let mut reader = decompressed.as_slice();
+
+    // Files start with a negative version number, except for the oldest ones
+    let mut layer_count = read_i32(&mut reader)?;
+    if layer_count < 0 {
+        layer_count = read_i32(&mut reader)?;
+    }
+
+    let mut layers = Vec::new();
+    for _ in 0..layer_count {
+        let (width, height) = (read_i32(&mut reader)?, read_i32(&mut reader)?);
+        // Sizes are checked against the remaining data, 10 bytes per cell, before allocating the layer
+        let fits = |w: usize, h: usize| w.checked_mul(h).and_then(|n| n.checked_mul(10)).is_some_and(|len| len <= reader.len());
+        if width < 0 || height < 0 || !fits(width as usize, height as usize) {
+            return Err(TextModeCanvasLoaderError::InvalidLayerSize { width, height });
+        }
+        let (width, height) = (width as usize, height as usize);
+
+        // Cells are stored column by column
+        let mut layer = TextModeCanvas::new(width, height, TextModeCell::default());
+        let (cells, rest) = reader.split_at(width * height * 10);
+        reader = rest;
+        for (i, cell) in cells.chunks_exact(10).enumerate() {
+            let [g0, g1, g2, g3, fg_r, fg_g, fg_b, bg_r, bg_g, bg_b] = <[u8; 10]>::try_from(cell).unwrap();
+            let transparent = [bg_r, bg_g, bg_b] == REXPAINT_TRANSPARENT;
+            layer.set(i / height, i % height, TextModeCell {
+                index: u32::from_le_bytes([g0, g1, g2, g3]) as usize,
+                bg: Srgba::rgb_u8(bg_r, bg_g, bg_b).into(),
+                fg: Srgba::rgb_u8(fg_r, fg_g, fg_b).into(),
+                skip_bg: transparent,
+                skip_fg: transparent,
+                ..default()
+            });
+        }
+        layers.push(layer);
+    }
+    Ok(TextModeLayeredCanvas { layers })
+}
+
+/// Loads REXPaint `.xp` files into a [`TextModeLayeredCanvas`].
+///
+/// Each layer is also available as a [`TextModeCanvas`] labeled `layer{i}`.
+/// Cells with the transparent `(255, 0, 255)` background are not drawn.
+#[derive(Default)]
+pub struct RexPaintLoader;
+
+impl AssetLoader for RexPaintLoader {
+    type Asset = TextModeLayeredCanvas;
+    type Settings = ();
+    type Error = TextModeCanvasLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<TextModeLayeredCanvas, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let canvas = parse_xp(&bytes)?;
+        for (i, layer) in canvas.layers.iter().enumerate() {
+            load_context.add_labeled_asset(format!("layer{i}"), layer.clone());
+        }
+        Ok(canvas)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["xp"]
+    }
+}
+
+/// Cell of a VGA character and attribute pair.
+///
+/// The low nibble of the attribute is the foreground color and the high nibble is the background color,
+/// its high bit being blink unless `ice_colors` is set. With 512 characters fonts, the high bit of the
+/// foreground color selects the second half of the font.
+fn vga_cell(character: u8, attribute: u8, palette: &[LinearRgba; 16], ice_colors: bool, chars_512: bool) -> TextModeCell {
+    let mut fg = attribute & 0x0F;
+    let mut index = character as usize;
+    if chars_512 {
+        index += (fg as usize >> 3) * 256;
+        fg &= 0x07;
+    }
+    let bg = match ice_colors {
+        true => attribute >> 4,
+        false => (attribute >> 4) & 0x07,
+    };
+    TextModeCell {
+        index,
+        bg: palette[bg as usize],
+        fg: palette[fg as usize],
+        blink: match !ice_colors && attribute & 0x80 != 0 {
+            true => TextModeBlink::Fg,
+            false => TextModeBlink::None,
+        },
+        ..default()
+    }
+}
+
+/// The 16 VGA colors in attribute order
+fn vga_palette() -> [LinearRgba; 16] {
+    std::array::from_fn(|i| ansi_color(VGA_TO_ANSI[i & 7] + (i as u8 & 8)))
+}
+
+/// Decompresses XBin character and attribute pairs
+fn read_xbin_compressed(reader: &mut &[u8], len: usize) -> std::io::Result<Vec<u8>> {
+    // The buffer grows as runs are decoded rather than trusting the header size
+    let mut data = Vec::with_capacity(len.min(reader.len() * 2));
+    while data.len() < len {
+        let [run] = read_bytes(reader)?;
+        let count = (run & 0x3F) as usize + 1;
+        match run >> 6 {
+            0 => data.extend(read_vec(reader, count * 2)?),
+            1 => {
+                let [character] = read_bytes(reader)?;
+                for _ in 0..count {
+                    data.extend([character, read_bytes::<1>(reader)?[0]]);
+                }
+            }
+            2 => {
+                let [attribute] = read_bytes(reader)?;
+                for _ in 0..count {
+                    data.extend([read_bytes::<1>(reader)?[0], attribute]);
+                }
+            }
+            _ => {
+                let pair = read_bytes::<2>(reader)?;
+                for _ in 0..count {
+                    data.extend(pair);
+                }
+            }
+        }
+    }
+    data.truncate(len);
+    Ok(data)
+}
+
+/// An XBin file, its font being raw glyph bitmaps
+struct XBin {
+    canvas: TextModeCanvas,
+    font: Option<(Vec<u8>, UVec2)>,
+}
+
+fn parse_xbin(bytes: &[u8]) -> Result<XBin, TextModeCanvasLoaderError> {
+    let (contents, sauce) = Sauce::split(bytes);
+    let mut reader = contents;
+    if read_bytes::<5>(&mut reader)? != *b"XBIN\x1A" {
+        return Err(TextModeCanvasLoaderError::MissingHeader("XBIN"));
+    }
+    let [w0, w1, h0, h1, font_height, flags] = read_bytes(&mut reader)?;
+    let (width, height) = (u16::from_le_bytes([w0, w1]) as usize, u16::from_le_bytes([h0, h1]) as usize);
+
+    let mut palette = vga_palette();
+    if flags & XBIN_PALETTE != 0 {
+        // 6 bit color components
+        let components: [u8; 48] = read_bytes(&mut reader)?;
+        for (color, rgb) in palette.iter_mut().zip(components.chunks(3)) {
+            let [r, g, b] = [0, 1, 2].map(|i| (rgb[i] & 0x3F) << 2 | (rgb[i] & 0x3F) >> 4);
+            *color = Srgba::rgb_u8(r, g, b).into();
+        }
+    }
+
+    let chars_512 = flags & XBIN_512_CHARS != 0;
+    let font = if flags & XBIN_FONT != 0 {
+        if font_height == 0 || font_height > 32 {
+            return Err(TextModeCanvasLoaderError::InvalidFontHeight(font_height));
+        }
+        let glyph_count = if chars_512 { 512 } else { 256 };
+        let glyphs = read_vec(&mut reader, glyph_count * font_height as usize)?;
+        Some((glyphs, UVec2::new(8, font_height as u32)))
+    } else {
+        None
+    };
+
+    // Sizes are checked against the remaining data before allocating the cells
+    let len = width * height * 2;
+    let compressed = flags & XBIN_COMPRESS != 0;
+    let max_len = if compressed { reader.len() * XBIN_MAX_EXPANSION } else { reader.len() };
+    if len > max_len {
+        return Err(TextModeCanvasLoaderError::InvalidCanvasSize { width, height });
+    }
+    let data = if compressed {
+        read_xbin_compressed(&mut reader, len)?
+    } else {
+        read_vec(&mut reader, len)?
+    };
+
+    let ice_colors = flags & XBIN_NON_BLINK != 0;
+    let mut canvas = TextModeCanvas::new(width, height, TextModeCell::default());
+    for (i, pair) in data.chunks(2).enumerate() {
+        canvas.set(i % width, i / width, vga_cell(pair[0], pair[1], &palette, ice_colors, chars_512));
+    }
+    canvas.sauce = sauce;
+    Ok(XBin { canvas, font })
+}
+
+/// Loads XBin `.xb` files into a [`TextModeCanvas`].
+///
+/// The embedded font is rasterized into the canvas [`TextModeFont`], also available as the `texture`,
+/// `layout` and `charset` labeled assets. Files without a font are drawn over a CP437 atlas.
+#[derive(Default)]
+pub struct XBinLoader;
+
+impl AssetLoader for XBinLoader {
+    type Asset = TextModeCanvas;
+    type Settings = ();
+    type Error = TextModeCanvasLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<TextModeCanvas, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let XBin { mut canvas, font } = parse_xbin(&bytes)?;
+        if let Some((glyphs, glyph_size)) = font {
+            let glyphs = glyphs.chunks(glyph_size.y as usize);
+            canvas.font = Some(TextModeFont::add_labeled(load_context, glyphs, glyph_size, TextModeCharset::cp437()));
+        }
+        Ok(canvas)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["xb"]
+    }
+}
+
+/// Parses a BIN file, raw character and attribute pairs
+fn parse_bin(bytes: &[u8]) -> TextModeCanvas {
+    let (contents, sauce) = Sauce::split(bytes);
+    // The SAUCE file type is half the width of binary text files
+    let binary_sauce = sauce.as_ref().filter(|s| s.data_type == SAUCE_BINARY_TEXT);
+    let width = binary_sauce
+        .map(|s| s.file_type as usize * 2)
+        .filter(|&w| w > 0)
+        .unwrap_or(DEFAULT_WIDTH);
+    let ice_colors = binary_sauce.is_some_and(Sauce::ice_colors);
+
+    let palette = vga_palette();
+    let pairs = contents.chunks_exact(2);
+    let mut canvas = TextModeCanvas::new(width, pairs.len().div_ceil(width), TextModeCell::default());
+    for (i, pair) in pairs.enumerate() {
+        canvas.set(i % width, i / width, vga_cell(pair[0], pair[1], &palette, ice_colors, false));
+    }
+    canvas.sauce = sauce;
+    canvas
+}
+
+/// Loads BIN `.bin` files into a [`TextModeCanvas`] to draw over a CP437 atlas.
+///
+/// The width is read from the SAUCE record, defaulting to 80 columns.
+#[derive(Default)]
+pub struct BinLoader;
+
+impl AssetLoader for BinLoader {
+    type Asset = TextModeCanvas;
+    type Settings = ();
+    type Error = TextModeCanvasLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<TextModeCanvas, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(parse_bin(&bytes))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["bin"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    /// A REXPaint cell, `(glyph, fg, bg)`
+    type XpCell = (u32, [u8; 3], [u8; 3]);
+
+    /// A gzipped REXPaint file, layers being given as `(width, height, cells)` with cells column by column
+    fn xp(layers: &[(i32, i32, Vec<XpCell>)]) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend((-1i32).to_le_bytes());
+        data.extend((layers.len() as i32).to_le_bytes());
+        for (width, height, cells) in layers {
+            data.extend(width.to_le_bytes());
+            data.extend(height.to_le_bytes());
+            for (glyph, fg, bg) in cells {
+                data.extend(glyph.to_le_bytes());
+                data.extend(fg);
+                data.extend(bg);
+            }
+        }
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn rexpaint_layers() {
+        let (red, blue) = ([255, 0, 0], [0, 0, 255]);
+        let bytes = xp(&[
+            (2, 1, vec![(65, red, blue), (66, blue, red)]),
+            (1, 2, vec![(67, red, REXPAINT_TRANSPARENT), (68, red, blue)]),
+        ]);
+        let canvas = parse_xp(&bytes).unwrap();
+        assert_eq!(canvas.layers.len(), 2);
+
+        let bottom = &canvas.layers[0];
+        assert_eq!((bottom.width(), bottom.height()), (2, 1));
+        let cell = bottom.get(1, 0).unwrap();
+        assert_eq!(cell.index, 66);
+        assert_eq!(cell.fg, Srgba::rgb_u8(0, 0, 255).into());
+        assert_eq!(cell.bg, Srgba::rgb_u8(255, 0, 0).into());
+        assert!(!cell.skip_bg);
+
+        // Cells are stored column by column
+        let top = &canvas.layers[1];
+        assert_eq!((top.width(), top.height()), (1, 2));
+        assert_eq!(top.get(0, 0).unwrap().index, 67);
+        assert!(top.get(0, 0).unwrap().skip_bg && top.get(0, 0).unwrap().skip_fg);
+        assert_eq!(top.get(0, 1).unwrap().index, 68);
+    }
+
+    #[test]
+    fn rexpaint_without_version() {
+        let mut data = Vec::new();
+        data.extend(1i32.to_le_bytes());
+        data.extend([1i32, 1].iter().flat_map(|n| n.to_le_bytes()));
+        data.extend([7, 0, 0, 0, 1, 2, 3, 4, 5, 6]);
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&data).unwrap();
+
+        let canvas = parse_xp(&encoder.finish().unwrap()).unwrap();
+        assert_eq!(canvas.layers[0].get(0, 0).unwrap().index, 7);
+    }
+
+    #[test]
+    fn rexpaint_invalid_sizes() {
+        for (width, height) in [(-1, 1), (1, -1), (i32::MAX, i32::MAX), (2, 2)] {
+            let bytes = xp(&[(width, height, vec![(0, [0; 3], [0; 3])])]);
+            assert!(matches!(
+                parse_xp(&bytes),
+                Err(TextModeCanvasLoaderError::InvalidLayerSize { .. })
+            ));
+        }
+        assert!(matches!(parse_xp(b"not gzipped"), Err(TextModeCanvasLoaderError::Io(_))));
+    }
+
+    /// An XBin file with an `XBIN` header, followed by `rest`
+    fn xbin(width: u16, height: u16, font_height: u8, flags: u8, rest: &[u8]) -> Vec<u8> {
+        let mut bytes = b"XBIN\x1A".to_vec();
+        bytes.extend(width.to_le_bytes());
+        bytes.extend(height.to_le_bytes());
+        bytes.extend([font_height, flags]);
+        bytes.extend(rest);
+        bytes
+    }
+
+    #[test]
+    fn bin_default_width() {
+        let mut bytes = [b'A', 0x1F].repeat(81);
+        bytes.extend([b'B', 0x9F]);
+        let canvas = parse_bin(&bytes);
+        assert_eq!((canvas.width(), canvas.height()), (80, 2));
+
+        let cell = canvas.get(0, 0).unwrap();
+        assert_eq!(cell.index, b'A' as usize);
+        assert_eq!(cell.bg, ansi_color(4));
+        assert_eq!(cell.fg, ansi_color(15));
+        assert_eq!(cell.blink, TextModeBlink::None);
+
+        // The high bit of the background is blink without iCE colors
+        let cell = canvas.get(1, 1).unwrap();
+        assert_eq!(cell.index, b'B' as usize);
+        assert_eq!(cell.bg, ansi_color(4));
+        assert_eq!(cell.blink, TextModeBlink::Fg);
+        assert_eq!(canvas.get(2, 1).unwrap().index, 0);
+    }
+
+    #[test]
+    fn bin_sauce_width() {
+        let mut record = vec![0; 128];
+        record[0..7].copy_from_slice(b"SAUCE00");
+        record[94] = SAUCE_BINARY_TEXT;
+        // Half the width
+        record[95] = 2;
+        // iCE colors
+        record[105] = 1;
+        let bytes = [[b'A', 0x9F].repeat(5).as_slice(), &[0x1A], &record].concat();
+        let canvas = parse_bin(&bytes);
+        assert_eq!((canvas.width(), canvas.height()), (4, 2));
+        let cell = canvas.get(0, 1).unwrap();
+        assert_eq!(cell.bg, ansi_color(12));
+        assert_eq!(cell.blink, TextModeBlink::None);
+        assert!(canvas.sauce.is_some());
+    }
+
+    #[test]
+    fn xbin_compression() {
+        let data = [
+            [b'A', 0x07], [b'B', 0x17],
+            [b'C', 0x20], [b'D', 0x20], [b'E', 0x20],
+            [b'F', 0x31], [b'F', 0x32],
+            [b'G', 0x40], [b'G', 0x40], [b'G', 0x40], [b'G', 0x40],
+        ]
+        .concat();
+        let uncompressed = parse_xbin(&xbin(11, 1, 16, 0, &data)).unwrap().canvas;
+
+        let compressed = [
+            // Uncompressed run of 2 pairs
+            vec![0x01, b'A', 0x07, b'B', 0x17],
+            // Attribute run of 3 pairs
+            vec![0x82, 0x20, b'C', b'D', b'E'],
+            // Character run of 2 pairs
+            vec![0x41, b'F', 0x31, 0x32],
+            // Character and attribute run of 4 pairs, the last one being past the end of the canvas
+            vec![0xC4, b'G', 0x40],
+        ]
+        .concat();
+        let canvas = parse_xbin(&xbin(11, 1, 16, XBIN_COMPRESS, &compressed)).unwrap().canvas;
+        assert_eq!(canvas.cells(), uncompressed.cells());
+        assert_eq!(canvas.get(4, 0).unwrap().index, b'E' as usize);
+        assert_eq!(canvas.get(6, 0).unwrap().fg, vga_palette()[2]);
+        assert_eq!(canvas.get(10, 0).unwrap().bg, vga_palette()[4]);
+
+        let truncated = parse_xbin(&xbin(11, 1, 16, XBIN_COMPRESS, &compressed[..8]));
+        assert!(matches!(truncated, Err(TextModeCanvasLoaderError::Io(_))));
+    }
+
+    #[test]
+    fn xbin_palette_and_font() {
+        let mut palette = [0; 48];
+        palette[3..6].copy_from_slice(&[0x3F, 0x20, 0x00]);
+        let font = vec![0xFF; 512 * 2];
+        let rest = [palette.as_slice(), &font, &[b'A', 0x01, b'A', 0x09]].concat();
+        let flags = XBIN_PALETTE | XBIN_FONT | XBIN_512_CHARS | XBIN_NON_BLINK;
+        let XBin { canvas, font } = parse_xbin(&xbin(2, 1, 2, flags, &rest)).unwrap();
+
+        let (glyphs, glyph_size) = font.unwrap();
+        assert_eq!(glyphs.len(), 512 * 2);
+        assert_eq!(glyph_size, UVec2::new(8, 2));
+
+        // 6 bit components are scaled to 8 bits
+        let first = canvas.get(0, 0).unwrap();
+        assert_eq!(first.fg, Srgba::rgb_u8(0xFF, 0x82, 0x00).into());
+        // The high bit of the foreground selects the second half of 512 characters fonts
+        let second = canvas.get(1, 0).unwrap();
+        assert_eq!((first.index, second.index), (b'A' as usize, 256 + b'A' as usize));
+        assert_eq!(second.fg, first.fg);
+    }
+
+    #[test]
+    fn xbin_errors() {
+        for font_height in [0, 33] {
+            let bytes = xbin(1, 1, font_height, XBIN_FONT, &[0; 256 * 33 + 2]);
+            assert!(matches!(
+                parse_xbin(&bytes),
+                Err(TextModeCanvasLoaderError::InvalidFontHeight(h)) if h == font_height
+            ));
+        }
+        // Without the font flag the height is not used
+        assert!(parse_xbin(&xbin(1, 1, 0, 0, &[b'A', 0x07])).is_ok());
+        assert!(matches!(
+            parse_xbin(b"XBIM\x1A\x01\x00\x01\x00\x10\x00"),
+            Err(TextModeCanvasLoaderError::MissingHeader("XBIN"))
+        ));
+        assert!(matches!(parse_xbin(&xbin(2, 1, 16, XBIN_COMPRESS, &[0x01, b'A'])), Err(TextModeCanvasLoaderError::Io(_))));
+    }
+
+    /// SAUCE record of a character file of the given size
+    fn ansi_sauce(width: u16, height: u16) -> Vec<u8> {
+        let mut record = vec![0; 128];
+        record[0..7].copy_from_slice(b"SAUCE00");
+        record[94] = SAUCE_CHARACTER;
+        record[96..98].copy_from_slice(&width.to_le_bytes());
+        record[98..100].copy_from_slice(&height.to_le_bytes());
+        record
+    }
+
+    #[test]
+    fn ansi_row_limit() {
+        // Cursor movements are clamped, only printing past the last row fails
+        let moves = b"\x1b[65535B".repeat(1000);
+        assert_eq!(parse_ansi(&moves).unwrap().height(), 0);
+        let printed = [moves.as_slice(), b"A"].concat();
+        assert!(matches!(parse_ansi(&printed), Err(TextModeCanvasLoaderError::TooManyRows(MAX_ANSI_HEIGHT))));
+        assert!(matches!(
+            parse_ansi(b"\x1b[65535;1HA"),
+            Err(TextModeCanvasLoaderError::TooManyRows(MAX_ANSI_HEIGHT))
+        ));
+        let canvas = parse_ansi(&[b"\x1b[100EA".as_slice(), b"\nB"].concat()).unwrap();
+        assert_eq!(canvas.height(), 102);
+
+        // The SAUCE height is the limit when present
+        let canvas = parse_ansi(&[b"A\r\nB\x1a".as_slice(), &ansi_sauce(80, 2)].concat()).unwrap();
+        assert_eq!((canvas.width(), canvas.height()), (80, 2));
+        assert!(matches!(
+            parse_ansi(&[b"A\r\nB\r\nC\x1a".as_slice(), &ansi_sauce(80, 2)].concat()),
+            Err(TextModeCanvasLoaderError::TooManyRows(2))
+        ));
+    }
+
+    #[test]
+    fn xbin_huge_header() {
+        // Rejected before allocating the cells of the header size
+        for flags in [0, XBIN_COMPRESS] {
+            assert!(matches!(
+                parse_xbin(&xbin(u16::MAX, u16::MAX, 16, flags, &[0xC0, b'A', 0x07])),
+                Err(TextModeCanvasLoaderError::InvalidCanvasSize { width: 65535, height: 65535 })
+            ));
+        }
+        assert!(matches!(
+            parse_xbin(&xbin(2, 1, 16, 0, b"A")),
+            Err(TextModeCanvasLoaderError::InvalidCanvasSize { width: 2, height: 1 })
+        ));
+    }
+}
@@ -0,0 +1,263 @@
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+use bevy::utils::HashMap;
+
+use crate::{TextModeMarkup, TextModeSprite, TextModeSpriteBundle};
+
+/// Maps unicode scalars to texture atlas indices
+pub trait Charset: Send + Sync {
+    /// Returns the atlas index of `c`, or `None` if the charset has no glyph for it
+    fn index(&self, c: char) -> Option<usize>;
+
+    /// Returns the atlas index of `c` only if the charset has a glyph for it, ignoring any fallback glyph
+    fn glyph_index(&self, c: char) -> Option<usize> {
+        self.index(c)
+    }
+}
+
+/// Code page 437 characters, in code point order
+pub const CP437: [char; 256] = [
+    '\0', '☺', '☻', '♥', '♦', '♣', '♠', '•', '◘', '○', '◙', '♂', '♀', '♪', '♫', '☼',
+    '►', '◄', '↕', '‼', '¶', '§', '▬', '↨', '↑', '↓', '→', '←', '∟', '↔', '▲', '▼',
+    ' ', '!', '"', '#', '$', '%', '&', '\'', '(', ')', '*', '+', ',', '-', '.', '/',
+    '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', ':', ';', '<', '=', '>', '?',
+    '@', 'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O',
+    'P', 'Q', 'R', 'S', 'T', 'U', 'V', 'W', 'X', 'Y', 'Z', '[', '\\', ']', '^', '_',
+    '`', 'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o',
+    'p', 'q', 'r', 's', 't', 'u', 'v', 'w', 'x', 'y', 'z', '{', '|', '}', '~', '⌂',
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å',
+    'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ',
+    'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»',
+    '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕', '╣', '║', '╗', '╝', '╜', '╛', '┐',
+    '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦', '╠', '═', '╬', '╧',
+    '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐', '▀',
+    'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩',
+    '≡', '±', '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{a0}',
+];
+
+/// Charset for tilesets laid out in code page 437 order
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Cp437Charset;
+
+impl Charset for Cp437Charset {
+    fn index(&self, c: char) -> Option<usize> {
+        match c {
+            ' '..='~' => Some(c as usize),
+            _ => CP437.iter().position(|&cp| cp == c),
+        }
+    }
+}
+
+/// Charset for tilesets laid out in ASCII order
+#[derive(Debug, Clone, Copy)]
+pub struct AsciiCharset {
+    /// First ASCII code of the tileset
+    pub start: u8,
+    /// Atlas index of the `start` character
+    pub offset: usize,
+}
+
+impl Default for AsciiCharset {
+    fn default() -> Self {
+        Self {
+            start: b' ',
+            offset: 0,
+        }
+    }
+}
+
+impl Charset for AsciiCharset {
+    fn index(&self, c: char) -> Option<usize> {
+        if !c.is_ascii() || (c as u8) < self.start {
+            return None;
+        }
+        Some(self.offset + (c as u8 - self.start) as usize)
+    }
+}
+
+/// Charset asset storing an explicit character to atlas index table
+#[derive(Asset, TypePath, Debug, Clone, Default)]
+pub struct TextModeCharset {
+    indices: HashMap<char, usize>,
+    /// Index used for characters missing from the table
+    pub fallback: Option<usize>,
+}
+
+impl TextModeCharset {
+    /// Builds a table from the atlas indices of `chars` in `charset`
+    pub fn from_charset(charset: &impl Charset, chars: impl IntoIterator<Item = char>) -> Self {
+        Self {
+            indices: chars
+                .into_iter()
+                .filter_map(|c| charset.index(c).map(|i| (c, i)))
+                .collect(),
+            fallback: None,
+        }
+    }
+
+    /// Table of the 256 code page 437 characters
+    pub fn cp437() -> Self {
+        Self::from_charset(&Cp437Charset, CP437)
+    }
+
+    /// Table of the ASCII characters of an [`AsciiCharset`]
+    pub fn ascii(start: u8, offset: usize) -> Self {
+        Self::from_charset(&AsciiCharset { start, offset }, (start..0x80).map(char::from))
+    }
+
+    /// Maps `c` to the atlas index `index`
+    pub fn insert(&mut self, c: char, index: usize) {
+        self.indices.insert(c, index);
+    }
+}
+
+impl Charset for TextModeCharset {
+    fn index(&self, c: char) -> Option<usize> {
+        self.indices.get(&c).copied().or(self.fallback)
+    }
+
+    fn glyph_index(&self, c: char) -> Option<usize> {
+        self.indices.get(&c).copied()
+    }
+}
+
+/// Iterates over the `(x, y, index)` positions and atlas indices of the characters of `text`.
+///
+/// `'\n'` moves to the start of the next line, characters missing from `charset` are skipped.
+pub(crate) fn text_cells<'a>(
+    text: &'a str,
+    charset: &'a impl Charset,
+) -> impl Iterator<Item = (usize, usize, usize)> + 'a {
+    text.lines().enumerate().flat_map(move |(y, line)| {
+        line.chars()
+            .enumerate()
+            .filter_map(move |(x, c)| charset.index(c).map(|i| (x, y, i)))
+    })
+}
+
+/// Spawns one [`TextModeSpriteBundle`] per printed character
+pub struct TextModeSpritePrinter<'a, C: Charset> {
+    pub charset: &'a C,
+    pub texture: Handle<Image>,
+    pub layout: Handle<TextureAtlasLayout>,
+    /// Size of a character cell
+    pub cell_size: Vec2,
+    /// Position of the top left corner of the `(0, 0)` cell
+    pub origin: Vec3,
+}
+
+impl<C: Charset> TextModeSpritePrinter<'_, C> {
+    /// Spawns the characters of `text` starting at cell `(x, y)`, and returns the spawned entities
+    pub fn print(
+        &self,
+        commands: &mut Commands,
+        x: usize,
+        y: usize,
+        text: &str,
+        fg: LinearRgba,
+        bg: LinearRgba,
+    ) -> Vec<Entity> {
+        text_cells(text, self.charset)
+            .map(|(dx, dy, index)| {
+                let sprite = TextModeSprite {
+                    bg,
+                    fg,
+                    ..default()
+                };
+                self.spawn(commands, x + dx, y + dy, index, sprite)
+            })
+            .collect()
+    }
+
+    /// Spawns the characters of `markup` starting at cell `(x, y)`, and returns the spawned entities.
+    ///
+    /// `fg` and `bg` are used until a markup tag sets the colors.
+    pub fn print_markup(
+        &self,
+        commands: &mut Commands,
+        x: usize,
+        y: usize,
+        markup: &TextModeMarkup,
+        fg: LinearRgba,
+        bg: LinearRgba,
+    ) -> Vec<Entity> {
+        markup
+            .cells(self.charset)
+            .map(|(dx, dy, index, style)| {
+                let sprite = TextModeSprite {
+                    bg: style.bg.unwrap_or(bg),
+                    fg: style.fg.unwrap_or(fg),
+                    flip_x: style.flip_x,
+                    flip_y: style.flip_y,
+                    rotation: style.rotation,
+                    ..default()
+                };
+                self.spawn(commands, x + dx, y + dy, index, sprite)
+            })
+            .collect()
+    }
+
+    fn spawn(&self, commands: &mut Commands, x: usize, y: usize, index: usize, sprite: TextModeSprite) -> Entity {
+        let position = Vec2::new(x as f32, -(y as f32)) * self.cell_size;
+        commands
+            .spawn(TextModeSpriteBundle {
+                sprite: TextModeSprite {
+                    custom_size: Some(self.cell_size),
+                    anchor: Anchor::TopLeft,
+                    ..sprite
+                },
+                atlas: TextureAtlas {
+                    layout: self.layout.clone(),
+                    index,
+                },
+                texture: self.texture.clone(),
+                transform: Transform::from_translation(self.origin + position.extend(0.0)),
+                ..default()
+            })
+            .id()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cp437_index() {
+        assert_eq!(Cp437Charset.index(' '), Some(0x20));
+        assert_eq!(Cp437Charset.index('~'), Some(0x7e));
+        assert_eq!(Cp437Charset.index('☺'), Some(0x01));
+        assert_eq!(Cp437Charset.index('Ç'), Some(0x80));
+        assert_eq!(Cp437Charset.index('█'), Some(0xdb));
+        assert_eq!(Cp437Charset.index('\u{a0}'), Some(0xff));
+        assert_eq!(Cp437Charset.index('€'), None);
+    }
+
+    #[test]
+    fn ascii_index() {
+        let charset = AsciiCharset { start: b'A', offset: 10 };
+        assert_eq!(charset.index('A'), Some(10));
+        assert_eq!(charset.index('Z'), Some(35));
+        assert_eq!(charset.index('@'), None);
+        assert_eq!(charset.index('é'), None);
+        assert_eq!(AsciiCharset::default().index('!'), Some(1));
+    }
+
+    #[test]
+    fn fallback() {
+        let mut charset = TextModeCharset::ascii(b' ', 0);
+        assert_eq!(charset.index('é'), None);
+        charset.fallback = Some(0x3f);
+        assert_eq!(charset.index('é'), Some(0x3f));
+        assert_eq!(charset.glyph_index('é'), None);
+        assert_eq!(charset.glyph_index('a'), Some(0x41));
+        charset.insert('é', 0x60);
+        assert_eq!(charset.glyph_index('é'), Some(0x60));
+    }
+
+    #[test]
+    fn cell_positions() {
+        let cells: Vec<_> = text_cells("ab\n€c\n\nd", &Cp437Charset).collect();
+        assert_eq!(cells, [(0, 0, 0x61), (1, 0, 0x62), (1, 1, 0x63), (0, 3, 0x64)]);
+    }
+}
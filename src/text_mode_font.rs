@@ -0,0 +1,108 @@
+use bevy::asset::LoadContext;
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::render::texture::ImageSampler;
+
+use crate::TextModeCharset;
+
+/// Largest atlas width and height in pixels, the default `max_texture_dimension_2d` of wgpu
+pub(crate) const MAX_ATLAS_SIZE: u32 = 8192;
+
+/// A glyph atlas generated from a bitmap font.
+///
+/// Glyph pixels are white and other pixels are black, following the red channel mask convention.
+#[derive(Asset, TypePath, Debug, Clone, Default)]
+pub struct TextModeFont {
+    pub texture: Handle<Image>,
+    pub layout: Handle<TextureAtlasLayout>,
+    /// Maps characters to glyph indices
+    pub charset: Handle<TextModeCharset>,
+    /// Size of a glyph in pixels
+    pub glyph_size: UVec2,
+}
+
+/// Columns and rows of glyphs of a roughly square atlas of `count` glyphs
+pub(crate) fn atlas_grid(count: usize) -> UVec2 {
+    let columns = ((count as f64).sqrt().ceil() as u32).max(1);
+    UVec2::new(columns, (count as u32).div_ceil(columns).max(1))
+}
+
+/// Rasterizes 1 bit per pixel glyphs into a roughly square atlas, see [`atlas_grid`].
+///
+/// Glyph rows are padded to whole bytes, the most significant bit being the leftmost pixel.
+pub(crate) fn rasterize_glyphs<'a>(
+    glyphs: impl ExactSizeIterator<Item = &'a [u8]>,
+    glyph_size: UVec2,
+) -> (Image, TextureAtlasLayout) {
+    let grid = atlas_grid(glyphs.len());
+    let size = grid * glyph_size;
+    let row_bytes = glyph_size.x.div_ceil(8) as usize;
+
+    let mut data = [0, 0, 0, 255].repeat((size.x * size.y) as usize);
+    for (i, glyph) in glyphs.enumerate() {
+        let origin = UVec2::new(i as u32 % grid.x, i as u32 / grid.x) * glyph_size;
+        for (y, row) in glyph.chunks(row_bytes).take(glyph_size.y as usize).enumerate() {
+            for x in 0..glyph_size.x as usize {
+                if row.get(x / 8).is_some_and(|b| b & (0x80 >> (x % 8)) != 0) {
+                    let pixel = ((origin.y as usize + y) * size.x as usize + origin.x as usize + x) * 4;
+                    data[pixel..pixel + 3].copy_from_slice(&[255; 3]);
+                }
+            }
+        }
+    }
+
+    let mut image = Image::new(
+        Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    image.sampler = ImageSampler::nearest();
+    let layout = TextureAtlasLayout::from_grid(glyph_size, grid.x, grid.y, None, None);
+    (image, layout)
+}
+
+impl TextModeFont {
+    /// Rasterizes the glyphs and adds the atlas as the `texture`, `layout` and `charset` labeled assets
+    pub(crate) fn add_labeled<'a>(
+        load_context: &mut LoadContext<'_>,
+        glyphs: impl ExactSizeIterator<Item = &'a [u8]>,
+        glyph_size: UVec2,
+        charset: TextModeCharset,
+    ) -> Self {
+        let (image, layout) = rasterize_glyphs(glyphs, glyph_size);
+        Self {
+            texture: load_context.add_labeled_asset("texture".to_string(), image),
+            layout: load_context.add_labeled_asset("layout".to_string(), layout),
+            charset: load_context.add_labeled_asset("charset".to_string(), charset),
+            glyph_size,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn square_atlas() {
+        assert_eq!(atlas_grid(0), UVec2::new(1, 1));
+        assert_eq!(atlas_grid(256), UVec2::new(16, 16));
+        assert_eq!(atlas_grid(257), UVec2::new(17, 16));
+        // GNU Unifont fits in the texture limit at 16 pixels
+        assert_eq!(atlas_grid(57_000), UVec2::new(239, 239));
+        assert!(atlas_grid(57_000).max_element() * 16 <= MAX_ATLAS_SIZE);
+
+        let glyphs = [[0xFFu8; 2]; 5];
+        let (image, layout) = rasterize_glyphs(glyphs.iter().map(|g| &g[..]), UVec2::new(8, 2));
+        assert_eq!(image.size(), UVec2::new(24, 4));
+        assert_eq!(layout.textures.len(), 6);
+        assert_eq!(layout.textures[4].min, UVec2::new(8, 2));
+    }
+}
@@ -0,0 +1,481 @@
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::asset::io::Reader;
+use bevy::prelude::*;
+use thiserror::Error;
+
+use crate::text_mode_font::{atlas_grid, MAX_ATLAS_SIZE};
+use crate::{TextModeCharset, TextModeFont};
+
+const PSF1_MAGIC: [u8; 2] = [0x36, 0x04];
+const PSF1_MODE_512: u8 = 0x01;
+const PSF1_MODE_HAS_TAB: u8 = 0x02;
+const PSF1_MODE_SEQ: u8 = 0x04;
+const PSF1_SEPARATOR: u16 = 0xFFFF;
+const PSF1_START_SEQ: u16 = 0xFFFE;
+
+const PSF2_MAGIC: [u8; 4] = [0x72, 0xB5, 0x4A, 0x86];
+const PSF2_HAS_UNICODE_TABLE: u32 = 0x01;
+const PSF2_SEPARATOR: u8 = 0xFF;
+const PSF2_START_SEQ: u8 = 0xFE;
+
+/// Largest glyph width and height in pixels
+const MAX_GLYPH_SIZE: i64 = 256;
+
+#[derive(Debug, Error)]
+pub enum TextModeFontLoaderError {
+    #[error("could not read font: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("font is not valid UTF-8")]
+    Utf8(#[from] std::str::Utf8Error),
+    #[error("not a PSF1 or PSF2 font")]
+    InvalidMagic,
+    #[error("unexpected end of font")]
+    UnexpectedEof,
+    #[error("missing `{0}` header")]
+    MissingHeader(&'static str),
+    #[error("invalid line {line}: `{content}`")]
+    InvalidLine { line: usize, content: String },
+    #[error("invalid glyph size {width}x{height}")]
+    InvalidGlyphSize { width: i64, height: i64 },
+    #[error("glyph atlas of {width}x{height} pixels exceeds the texture size limit")]
+    AtlasTooLarge { width: u32, height: u32 },
+}
+
+/// Glyph bitmaps, 1 bit per pixel with rows padded to whole bytes
+struct Glyphs {
+    data: Vec<u8>,
+    glyph_len: usize,
+    glyph_size: UVec2,
+    charset: TextModeCharset,
+}
+
+impl Glyphs {
+    /// Size of the atlas of the glyphs, checked against [`MAX_ATLAS_SIZE`]
+    fn atlas_size(&self) -> Result<UVec2, TextModeFontLoaderError> {
+        let count = self.data.len() / self.glyph_len.max(1);
+        let size = atlas_grid(count).as_u64vec2() * self.glyph_size.as_u64vec2();
+        match size.max_element() <= u64::from(MAX_ATLAS_SIZE) {
+            true => Ok(size.as_uvec2()),
+            false => Err(TextModeFontLoaderError::AtlasTooLarge {
+                width: size.x.min(u64::from(u32::MAX)) as u32,
+                height: size.y.min(u64::from(u32::MAX)) as u32,
+            }),
+        }
+    }
+
+    fn add_labeled(self, load_context: &mut LoadContext<'_>) -> Result<TextModeFont, TextModeFontLoaderError> {
+        self.atlas_size()?;
+        Ok(TextModeFont::add_labeled(load_context, self.data.chunks(self.glyph_len), self.glyph_size, self.charset))
+    }
+}
+
+fn slice(bytes: &[u8], start: usize, len: usize) -> Result<&[u8], TextModeFontLoaderError> {
+    let end = start.checked_add(len).ok_or(TextModeFontLoaderError::UnexpectedEof)?;
+    bytes.get(start..end).ok_or(TextModeFontLoaderError::UnexpectedEof)
+}
+
+/// Checks that glyphs are 1 to [`MAX_GLYPH_SIZE`] pixels wide and high
+fn glyph_size(width: i64, height: i64) -> Result<UVec2, TextModeFontLoaderError> {
+    let valid = |n| (1..=MAX_GLYPH_SIZE).contains(&n);
+    match valid(width) && valid(height) {
+        true => Ok(UVec2::new(width as u32, height as u32)),
+        false => Err(TextModeFontLoaderError::InvalidGlyphSize { width, height }),
+    }
+}
+
+fn u32_at(bytes: &[u8], i: usize) -> Result<u32, TextModeFontLoaderError> {
+    let b = slice(bytes, i, 4)?;
+    Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn parse_psf1(bytes: &[u8]) -> Result<Glyphs, TextModeFontLoaderError> {
+    let (mode, height) = (bytes[2], bytes[3] as usize);
+    let glyph_size = glyph_size(8, height as i64)?;
+    let count = if mode & PSF1_MODE_512 != 0 { 512 } else { 256 };
+    let data = slice(bytes, 4, count * height)?.to_vec();
+
+    let mut charset = TextModeCharset::default();
+    if mode & (PSF1_MODE_HAS_TAB | PSF1_MODE_SEQ) != 0 {
+        let table = &bytes[4 + count * height..];
+        let mut values = table.chunks_exact(2).map(|b| u16::from_le_bytes([b[0], b[1]]));
+        for index in 0..count {
+            // Sequences of several characters cannot be mapped to a single glyph
+            let mut in_sequence = false;
+            for value in values.by_ref() {
+                match value {
+                    PSF1_SEPARATOR => break,
+                    PSF1_START_SEQ => in_sequence = true,
+                    _ if in_sequence => {}
+                    _ => {
+                        if let Some(c) = char::from_u32(value as u32) {
+                            charset.insert(c, index);
+                        }
+                    }
+                }
+            }
+        }
+    } else {
+        charset = TextModeCharset::cp437();
+    }
+
+    Ok(Glyphs {
+        data,
+        glyph_len: height,
+        glyph_size,
+        charset,
+    })
+}
+
+fn parse_psf2(bytes: &[u8]) -> Result<Glyphs, TextModeFontLoaderError> {
+    let header_size = u32_at(bytes, 8)? as usize;
+    let flags = u32_at(bytes, 12)?;
+    let count = u32_at(bytes, 16)? as usize;
+    let glyph_len = u32_at(bytes, 20)? as usize;
+    let glyph_size = glyph_size(u32_at(bytes, 28)? as i64, u32_at(bytes, 24)? as i64)?;
+    // Glyphs may be padded, but must hold every row
+    if glyph_len < glyph_size.x.div_ceil(8) as usize * glyph_size.y as usize {
+        return Err(TextModeFontLoaderError::InvalidGlyphSize {
+            width: glyph_size.x as i64,
+            height: glyph_size.y as i64,
+        });
+    }
+    let len = count.checked_mul(glyph_len).ok_or(TextModeFontLoaderError::UnexpectedEof)?;
+    let data = slice(bytes, header_size, len)?.to_vec();
+
+    let mut charset = TextModeCharset::default();
+    if flags & PSF2_HAS_UNICODE_TABLE != 0 {
+        let mut entries = bytes[header_size + len..].split(|&b| b == PSF2_SEPARATOR);
+        for (index, entry) in entries.by_ref().take(count).enumerate() {
+            // Sequences of several characters cannot be mapped to a single glyph
+            let singles = entry.split(|&b| b == PSF2_START_SEQ).next().unwrap_or_default();
+            for c in String::from_utf8_lossy(singles).chars().filter(|&c| c != char::REPLACEMENT_CHARACTER) {
+                charset.insert(c, index);
+            }
+        }
+    } else {
+        charset = TextModeCharset::cp437();
+    }
+
+    Ok(Glyphs {
+        data,
+        glyph_len,
+        glyph_size,
+        charset,
+    })
+}
+
+fn parse_psf(bytes: &[u8]) -> Result<Glyphs, TextModeFontLoaderError> {
+    if bytes.starts_with(&PSF2_MAGIC) {
+        parse_psf2(bytes)
+    } else if bytes.len() >= 4 && bytes.starts_with(&PSF1_MAGIC) {
+        parse_psf1(bytes)
+    } else {
+        Err(TextModeFontLoaderError::InvalidMagic)
+    }
+}
+
+fn invalid_line(line: usize, content: &str) -> TextModeFontLoaderError {
+    TextModeFontLoaderError::InvalidLine {
+        line: line + 1,
+        content: content.to_string(),
+    }
+}
+
+/// Parses the integer values following a BDF keyword, widened so that offsets can be added without overflowing
+fn bdf_values<const N: usize>(line: usize, content: &str) -> Result<[i64; N], TextModeFontLoaderError> {
+    let values: Vec<i64> = content
+        .split_whitespace()
+        .skip(1)
+        .map(|v| v.parse::<i32>().ok().map(i64::from))
+        .collect::<Option<_>>()
+        .ok_or_else(|| invalid_line(line, content))?;
+    values.try_into().map_err(|_| invalid_line(line, content))
+}
+
+/// Parses a BDF font, glyphs are placed in the font bounding box
+fn parse_bdf(text: &str) -> Result<Glyphs, TextModeFontLoaderError> {
+    let mut lines = text.lines().map(str::trim).enumerate();
+    if !lines.next().is_some_and(|(_, l)| l.starts_with("STARTFONT")) {
+        return Err(TextModeFontLoaderError::MissingHeader("STARTFONT"));
+    }
+
+    let mut bounds = None;
+    let mut glyphs = Vec::new();
+    let mut glyph_count = 0;
+    let mut charset = TextModeCharset::default();
+    let mut encoding = None;
+    let mut glyph_bounds = [0; 4];
+    while let Some((i, line)) = lines.next() {
+        let keyword = line.split_whitespace().next().unwrap_or_default();
+        match keyword {
+            "FONTBOUNDINGBOX" => {
+                let values = bdf_values::<4>(i, line)?;
+                glyph_size(values[0], values[1])?;
+                bounds = Some(values);
+            }
+            "STARTCHAR" => {
+                encoding = None;
+                glyph_bounds = [0; 4];
+            }
+            "ENCODING" => encoding = line.split_whitespace().nth(1).and_then(|e| e.parse::<u32>().ok()),
+            "BBX" => {
+                glyph_bounds = bdf_values::<4>(i, line)?;
+                // Empty glyphs such as spaces may have a zero sized bounding box
+                let [width, height, ..] = glyph_bounds;
+                if !(0..=MAX_GLYPH_SIZE).contains(&width) || !(0..=MAX_GLYPH_SIZE).contains(&height) {
+                    return Err(TextModeFontLoaderError::InvalidGlyphSize { width, height });
+                }
+            }
+            "BITMAP" => {
+                let [width, height, x_offset, y_offset] =
+                    bounds.ok_or(TextModeFontLoaderError::MissingHeader("FONTBOUNDINGBOX"))?;
+                let [glyph_width, glyph_height, glyph_x, glyph_y] = glyph_bounds;
+                let row_len = (width as usize).div_ceil(8);
+                let mut glyph = vec![0; row_len * height as usize];
+
+                // Bitmap rows are top to bottom, offsets being relative to the baseline
+                let top = height + y_offset - glyph_height - glyph_y;
+                let left = glyph_x - x_offset;
+                for row in 0..glyph_height {
+                    let (j, content) = lines.next().ok_or(TextModeFontLoaderError::UnexpectedEof)?;
+                    let digits = content.chars().map(|c| c.to_digit(16)).collect::<Option<Vec<_>>>();
+                    // Rows are padded to whole bytes, wider rows do not fit in the glyph bounding box
+                    let digits = digits
+                        .filter(|d| !d.is_empty() && d.len() <= (glyph_width as usize).div_ceil(8) * 2)
+                        .ok_or_else(|| invalid_line(j, content))?;
+                    let y = top + row;
+                    for x in 0..glyph_width.min(digits.len() as i64 * 4) {
+                        let cell_x = left + x;
+                        let set = digits[x as usize / 4] & (0x8 >> (x % 4)) != 0;
+                        if set && (0..width).contains(&cell_x) && (0..height).contains(&y) {
+                            glyph[y as usize * row_len + cell_x as usize / 8] |= 0x80 >> (cell_x % 8);
+                        }
+                    }
+                }
+
+                if let Some(c) = encoding.and_then(char::from_u32) {
+                    charset.insert(c, glyph_count);
+                }
+                glyphs.extend(glyph);
+                glyph_count += 1;
+            }
+            _ => {}
+        }
+    }
+
+    let [width, height, _, _] = bounds.ok_or(TextModeFontLoaderError::MissingHeader("FONTBOUNDINGBOX"))?;
+    let glyph_size = glyph_size(width, height)?;
+    Ok(Glyphs {
+        data: glyphs,
+        glyph_len: glyph_size.x.div_ceil(8) as usize * glyph_size.y as usize,
+        glyph_size,
+        charset,
+    })
+}
+
+/// Loads PSF1 and PSF2 `.psf` console fonts into a [`TextModeFont`].
+///
+/// The atlas is also available as the `texture`, `layout` and `charset` labeled assets.
+/// Fonts without a Unicode table are mapped as code page 437.
+#[derive(Default)]
+pub struct PsfFontLoader;
+
+impl AssetLoader for PsfFontLoader {
+    type Asset = TextModeFont;
+    type Settings = ();
+    type Error = TextModeFontLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<TextModeFont, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        parse_psf(&bytes)?.add_labeled(load_context)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["psf", "psfu"]
+    }
+}
+
+/// Loads X11 `.bdf` bitmap fonts into a [`TextModeFont`].
+///
+/// Every glyph is drawn in a cell the size of the font bounding box, in file order.
+/// The atlas is also available as the `texture`, `layout` and `charset` labeled assets.
+#[derive(Default)]
+pub struct BdfFontLoader;
+
+impl AssetLoader for BdfFontLoader {
+    type Asset = TextModeFont;
+    type Settings = ();
+    type Error = TextModeFontLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<TextModeFont, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        parse_bdf(std::str::from_utf8(&bytes)?)?.add_labeled(load_context)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["bdf"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Charset;
+
+    fn psf2_font(flags: u32, count: u32, glyph_len: u32, height: u32, width: u32, rest: &[u8]) -> Vec<u8> {
+        let mut bytes = PSF2_MAGIC.to_vec();
+        for value in [0, 32, flags, count, glyph_len, height, width] {
+            bytes.extend(value.to_le_bytes());
+        }
+        bytes.extend(rest);
+        bytes
+    }
+
+    fn bdf(bounds: &str, glyph: &str) -> String {
+        format!("STARTFONT 2.1\nFONTBOUNDINGBOX {bounds}\nCHARS 1\n{glyph}ENDFONT\n")
+    }
+
+    #[test]
+    fn psf1() {
+        let mut bytes = vec![PSF1_MAGIC[0], PSF1_MAGIC[1], PSF1_MODE_HAS_TAB, 2];
+        bytes.extend((0..256).flat_map(|i| [i as u8, !(i as u8)]));
+        // Glyph 0 maps `A` and the `a` + combining acute sequence, glyph 1 maps `B`
+        bytes.extend([0x41, 0x00, 0xFE, 0xFF, 0x61, 0x00, 0x01, 0x03, 0xFF, 0xFF, 0x42, 0x00, 0xFF, 0xFF]);
+        let glyphs = parse_psf(&bytes).unwrap();
+        assert_eq!(glyphs.glyph_size, UVec2::new(8, 2));
+        assert_eq!(glyphs.data.len(), 512);
+        assert_eq!(&glyphs.data[2..4], [1, 0xFE]);
+        assert_eq!(glyphs.charset.index('A'), Some(0));
+        assert_eq!(glyphs.charset.index('B'), Some(1));
+        assert_eq!(glyphs.charset.index('a'), None);
+
+        // Without a Unicode table fonts are mapped as code page 437
+        bytes[2] = 0;
+        assert_eq!(parse_psf(&bytes).unwrap().charset.index('☺'), Some(1));
+    }
+
+    #[test]
+    fn psf1_errors() {
+        let bytes = [PSF1_MAGIC[0], PSF1_MAGIC[1], 0, 0];
+        assert!(matches!(
+            parse_psf(&bytes),
+            Err(TextModeFontLoaderError::InvalidGlyphSize { width: 8, height: 0 })
+        ));
+        let bytes = [PSF1_MAGIC[0], PSF1_MAGIC[1], 0, 8, 0xFF];
+        assert!(matches!(parse_psf(&bytes), Err(TextModeFontLoaderError::UnexpectedEof)));
+        assert!(matches!(parse_psf(&PSF1_MAGIC), Err(TextModeFontLoaderError::InvalidMagic)));
+    }
+
+    #[test]
+    fn psf2() {
+        // 2 glyphs of 10x2 pixels, 2 bytes per row
+        let mut rest = vec![0xFF, 0xC0, 0x80, 0x40, 1, 2, 3, 4];
+        rest.extend("Aé".as_bytes());
+        rest.push(PSF2_SEPARATOR);
+        rest.extend("B".as_bytes());
+        rest.push(PSF2_START_SEQ);
+        rest.extend("C\u{301}".as_bytes());
+        rest.push(PSF2_SEPARATOR);
+        let glyphs = parse_psf(&psf2_font(PSF2_HAS_UNICODE_TABLE, 2, 4, 2, 10, &rest)).unwrap();
+        assert_eq!(glyphs.glyph_size, UVec2::new(10, 2));
+        assert_eq!(glyphs.glyph_len, 4);
+        assert_eq!(glyphs.data, rest[..8]);
+        assert_eq!(glyphs.charset.index('A'), Some(0));
+        assert_eq!(glyphs.charset.index('é'), Some(0));
+        assert_eq!(glyphs.charset.index('B'), Some(1));
+        assert_eq!(glyphs.charset.index('C'), None);
+    }
+
+    #[test]
+    fn psf2_errors() {
+        let rest = [0; 16];
+        for (glyph_len, height, width) in [(0, 2, 8), (2, 0, 8), (2, 2, 0), (2, 2, 257), (2, 2, 9)] {
+            assert!(matches!(
+                parse_psf(&psf2_font(0, 1, glyph_len, height, width, &rest)),
+                Err(TextModeFontLoaderError::InvalidGlyphSize { .. })
+            ));
+        }
+        assert!(matches!(
+            parse_psf(&psf2_font(0, u32::MAX, u32::MAX, 2, 8, &rest)),
+            Err(TextModeFontLoaderError::UnexpectedEof)
+        ));
+        assert!(matches!(parse_psf(&PSF2_MAGIC), Err(TextModeFontLoaderError::UnexpectedEof)));
+    }
+
+    #[test]
+    fn atlas_size_limit() {
+        // 256 pixels wide glyphs, 34 per row
+        let bytes = psf2_font(0, 1100, 32, 1, 256, &[0; 1100 * 32]);
+        assert!(matches!(
+            parse_psf(&bytes).unwrap().atlas_size(),
+            Err(TextModeFontLoaderError::AtlasTooLarge { width: 8704, height: 33 })
+        ));
+        let bytes = psf2_font(0, 1024, 32, 1, 256, &[0; 1024 * 32]);
+        assert_eq!(parse_psf(&bytes).unwrap().atlas_size().unwrap(), UVec2::new(8192, 32));
+    }
+
+    #[test]
+    fn bdf_glyphs() {
+        // A 3x2 glyph one pixel right of the cell origin, its bottom one pixel above the baseline of a 8x4 cell with a descent of 1
+        let glyph = "STARTCHAR A\nENCODING 65\nBBX 3 2 1 1\nBITMAP\nA0\n40\nENDCHAR\n\
+            STARTCHAR space\nENCODING 32\nBBX 0 0 0 0\nBITMAP\nENDCHAR\n";
+        let glyphs = parse_bdf(&bdf("8 4 0 -1", glyph)).unwrap();
+        assert_eq!(glyphs.glyph_size, UVec2::new(8, 4));
+        assert_eq!(glyphs.glyph_len, 4);
+        assert_eq!(glyphs.data, [0x50, 0x20, 0x00, 0x00, 0, 0, 0, 0]);
+        assert_eq!(glyphs.charset.index('A'), Some(0));
+        assert_eq!(glyphs.charset.index(' '), Some(1));
+
+        // Rows wider than 32 hex digits
+        let glyph = format!("STARTCHAR wide\nENCODING 66\nBBX 136 1 0 0\nBITMAP\n80{}01\nENDCHAR\n", "00".repeat(15));
+        let glyphs = parse_bdf(&bdf("136 1 0 0", &glyph)).unwrap();
+        assert_eq!(glyphs.glyph_len, 17);
+        assert_eq!(glyphs.data[0], 0x80);
+        assert_eq!(glyphs.data[16], 0x01);
+    }
+
+    #[test]
+    fn bdf_errors() {
+        let glyph = "STARTCHAR A\nENCODING 65\nBBX 8 1 0 0\nBITMAP\nFF\nENDCHAR\n";
+        for bounds in ["0 8 0 0", "8 0 0 0", "-8 8 0 0", "8 -8 0 0", "8 1000 0 0"] {
+            assert!(matches!(
+                parse_bdf(&bdf(bounds, glyph)),
+                Err(TextModeFontLoaderError::InvalidGlyphSize { .. })
+            ));
+        }
+        let glyph = "STARTCHAR A\nBBX -1 1 0 0\nBITMAP\nENDCHAR\n";
+        assert!(matches!(
+            parse_bdf(&bdf("8 8 0 0", glyph)),
+            Err(TextModeFontLoaderError::InvalidGlyphSize { width: -1, height: 1 })
+        ));
+
+        // Rows wider than the glyph bounding box, or not hexadecimal
+        for row in ["FF00", "", "G0"] {
+            let glyph = format!("STARTCHAR A\nBBX 8 1 0 0\nBITMAP\n{row}\nENDCHAR\n");
+            assert!(matches!(
+                parse_bdf(&bdf("8 8 0 0", &glyph)),
+                Err(TextModeFontLoaderError::InvalidLine { line: 7, .. })
+            ));
+        }
+
+        let truncated = "STARTFONT 2.1\nFONTBOUNDINGBOX 8 8 0 0\nSTARTCHAR A\nBBX 8 2 0 0\nBITMAP\nFF\n";
+        assert!(matches!(parse_bdf(truncated), Err(TextModeFontLoaderError::UnexpectedEof)));
+        assert!(matches!(
+            parse_bdf("STARTFONT 2.1\nSTARTCHAR A\nBITMAP\nENDCHAR\n"),
+            Err(TextModeFontLoaderError::MissingHeader("FONTBOUNDINGBOX"))
+        ));
+        assert!(matches!(parse_bdf("FONT x\n"), Err(TextModeFontLoaderError::MissingHeader("STARTFONT"))));
+    }
+}
@@ -0,0 +1,636 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+
+use crate::plugin::TextModeExtractedSprite;
+use crate::text_mode_box::{border_arms, box_arms, box_index};
+use crate::{TextModeBlink, TextModeBox, TextModeLaidOutText, TextModeMarkup, TextModeMask, TextModeTextLayout};
+use crate::text_mode_charset::{Charset, text_cells};
+
+/// A single cell of a [`TextModeGrid`]
+#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
+pub struct TextModeCell {
+    /// Index of the glyph in the grid texture atlas layout
+    pub index: usize,
+    pub bg: LinearRgba,
+    pub fg: LinearRgba,
+    pub flip_x: bool,
+    pub flip_y: bool,
+    pub rotation: u8,
+    /// Discards the background pixels of the cell
+    pub skip_bg: bool,
+    /// Discards the foreground pixels of the cell
+    pub skip_fg: bool,
+    pub blink: TextModeBlink,
+}
+
+impl Default for TextModeCell {
+    fn default() -> Self {
+        Self {
+            index: 0,
+            bg: Color::WHITE.to_linear(),
+            fg: Color::BLACK.to_linear(),
+            flip_x: false,
+            flip_y: false,
+            rotation: 0,
+            skip_bg: false,
+            skip_fg: false,
+            blink: TextModeBlink::None,
+        }
+    }
+}
+
+/// Shape of a [`TextModeCursor`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+pub enum TextModeCursorShape {
+    /// Swaps the background and foreground colors of the cell
+    #[default]
+    Block,
+    /// A line at the bottom of the cell, drawn with the foreground color
+    Underline,
+    /// A line at the left of the cell, drawn with the foreground color
+    Bar,
+}
+
+/// Cursor drawn over a cell of a [`TextModeGrid`]
+#[derive(Debug, Clone, Copy, PartialEq, Default, Reflect)]
+pub struct TextModeCursor {
+    pub x: usize,
+    pub y: usize,
+    pub shape: TextModeCursorShape,
+    /// Blinks with the cursor period of the [`TextModeBlinkTimer`](crate::TextModeBlinkTimer)
+    pub blink: bool,
+}
+
+/// A `width` × `height` grid of text mode cells rendered from a single entity.
+///
+/// Cells are stored row by row, `(0, 0)` being the top left cell.
+/// Every cell is drawn from the same texture atlas, no entity is spawned per cell.
+///
+/// Rows scrolled out of the top of the grid are kept in a scrollback history of up to
+/// `scrollback_limit` rows, shown by [`TextModeGrid::set_view_offset`].
+#[derive(Component, Debug, Clone, Reflect)]
+pub struct TextModeGrid {
+    width: usize,
+    height: usize,
+    cells: Vec<TextModeCell>,
+    /// Rows scrolled out of the grid, the oldest first
+    scrollback: VecDeque<Vec<TextModeCell>>,
+    view_offset: f32,
+    /// Maximum number of rows kept in the scrollback history
+    pub scrollback_limit: usize,
+    /// Texture atlas layout shared by all cells
+    pub layout: Handle<TextureAtlasLayout>,
+    pub alpha: f32,
+    /// Size of a cell, defaults to the size of the first texture of the atlas layout
+    pub cell_size: Option<Vec2>,
+    /// Anchor of the whole grid
+    pub anchor: Anchor,
+    pub mask: TextModeMask,
+    /// Swaps the foreground and background pixels of the mask
+    pub invert_mask: bool,
+    /// Mixes `bg` and `fg` by the mask value, see [`TextModeSprite::coverage`](crate::TextModeSprite::coverage)
+    pub coverage: bool,
+    pub cursor: Option<TextModeCursor>,
+    /// Draws the grid with a single draw call from a storage buffer of packed cells, the vertex shader
+    /// placing the cells and looking up the glyphs. Colors are stored as 8 bit sRGB.
+    ///
+    /// Suited to large grids, ignored where storage buffers are not supported (WebGL2).
+    pub gpu_driven: bool,
+}
+
+impl Default for TextModeGrid {
+    fn default() -> Self {
+        Self {
+            width: 0,
+            height: 0,
+            cells: Vec::new(),
+            scrollback: VecDeque::new(),
+            view_offset: 0.0,
+            scrollback_limit: 0,
+            layout: Handle::default(),
+            alpha: 1.0,
+            cell_size: None,
+            anchor: Anchor::TopLeft,
+            mask: TextModeMask::default(),
+            invert_mask: false,
+            coverage: false,
+            cursor: None,
+            gpu_driven: false,
+        }
+    }
+}
+
+impl TextModeGrid {
+    /// Creates a grid filled with default cells
+    pub fn new(width: usize, height: usize, layout: Handle<TextureAtlasLayout>) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![TextModeCell::default(); width * height],
+            layout,
+            ..default()
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// All cells, row by row
+    pub fn cells(&self) -> &[TextModeCell] {
+        &self.cells
+    }
+
+    /// All cells, row by row
+    pub fn cells_mut(&mut self) -> &mut [TextModeCell] {
+        &mut self.cells
+    }
+
+    /// Returns the cell at `(x, y)`, or `None` if it is out of bounds
+    pub fn get(&self, x: usize, y: usize) -> Option<&TextModeCell> {
+        self.cell_index(x, y).map(|i| &self.cells[i])
+    }
+
+    /// Returns the cell at `(x, y)`, or `None` if it is out of bounds
+    pub fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut TextModeCell> {
+        self.cell_index(x, y).map(|i| &mut self.cells[i])
+    }
+
+    /// Sets the cell at `(x, y)`, out of bounds positions are ignored
+    pub fn set(&mut self, x: usize, y: usize, cell: TextModeCell) {
+        if let Some(c) = self.get_mut(x, y) {
+            *c = cell;
+        }
+    }
+
+    /// Sets every cell of the grid to `cell`
+    pub fn fill(&mut self, cell: TextModeCell) {
+        self.cells.fill(cell);
+    }
+
+    /// Resizes the grid, keeping the cells that are still in bounds.
+    /// New cells are set to `cell`.
+    pub fn resize(&mut self, width: usize, height: usize, cell: TextModeCell) {
+        let mut cells = vec![cell; width * height];
+        for y in 0..height.min(self.height) {
+            for x in 0..width.min(self.width) {
+                cells[y * width + x] = self.cells[y * self.width + x];
+            }
+        }
+        self.width = width;
+        self.height = height;
+        self.cells = cells;
+    }
+
+    /// Writes the characters of `text` starting at cell `(x, y)`.
+    ///
+    /// `'\n'` moves to the next line, characters out of bounds or missing from `charset` are skipped.
+    /// Returns the number of written cells.
+    pub fn print(
+        &mut self,
+        x: usize,
+        y: usize,
+        text: &str,
+        fg: LinearRgba,
+        bg: LinearRgba,
+        charset: &impl Charset,
+    ) -> usize {
+        let mut written = 0;
+        for (dx, dy, index) in text_cells(text, charset) {
+            if let Some(cell) = self.get_mut(x + dx, y + dy) {
+                *cell = TextModeCell {
+                    index,
+                    bg,
+                    fg,
+                    ..default()
+                };
+                written += 1;
+            }
+        }
+        written
+    }
+
+    /// Draws the border of the box `rect`, `max` being excluded, and fills its interior if `fill` is set.
+    ///
+    /// Borders drawn over box drawing characters are joined with tees and crosses.
+    /// A box 1 cell high is drawn as a horizontal line, including a 1 × 1 box, and a box 1 cell wide
+    /// as a vertical line. Cells out of bounds are skipped.
+    pub fn draw_box(&mut self, rect: URect, style: &TextModeBox, charset: &impl Charset) {
+        if rect.is_empty() {
+            return;
+        }
+        let line = style.style.line();
+        for y in rect.min.y..rect.max.y {
+            for x in rect.min.x..rect.max.x {
+                let Some(cell) = self.get_mut(x as usize, y as usize) else {
+                    continue;
+                };
+                let arms = border_arms(rect, x, y, line);
+                if arms == [0; 4] {
+                    if let Some(fill) = style.fill.filter(|_| x > rect.min.x && y > rect.min.y) {
+                        *cell = TextModeCell {
+                            index: charset.index(' ').unwrap_or(cell.index),
+                            bg: fill,
+                            fg: style.fg,
+                            ..default()
+                        };
+                    }
+                    continue;
+                }
+
+                // Arms of the box take precedence over the arms of the character below
+                let below = box_arms(cell.index, charset).unwrap_or_default();
+                let joined = std::array::from_fn(|i| if arms[i] != 0 { arms[i] } else { below[i] });
+                if let Some(index) = box_index(joined, style.style, charset) {
+                    *cell = TextModeCell {
+                        index,
+                        bg: style.bg,
+                        fg: style.fg,
+                        ..default()
+                    };
+                }
+            }
+        }
+    }
+
+    /// Writes the characters of `markup` starting at cell `(x, y)`, see [`TextModeGrid::print`].
+    ///
+    /// `fg` and `bg` are used until a markup tag sets the colors.
+    pub fn print_markup(
+        &mut self,
+        x: usize,
+        y: usize,
+        markup: &TextModeMarkup,
+        fg: LinearRgba,
+        bg: LinearRgba,
+        charset: &impl Charset,
+    ) -> usize {
+        let mut written = 0;
+        for (dx, dy, index, style) in markup.cells(charset) {
+            if let Some(cell) = self.get_mut(x + dx, y + dy) {
+                *cell = TextModeCell {
+                    index,
+                    bg: style.bg.unwrap_or(bg),
+                    fg: style.fg.unwrap_or(fg),
+                    flip_x: style.flip_x,
+                    flip_y: style.flip_y,
+                    rotation: style.rotation,
+                    ..default()
+                };
+                written += 1;
+            }
+        }
+        written
+    }
+
+    /// Writes `text` laid out by `layout`, characters out of bounds are skipped
+    pub fn print_layout(
+        &mut self,
+        layout: &TextModeTextLayout,
+        text: &str,
+        fg: LinearRgba,
+        bg: LinearRgba,
+        charset: &impl Charset,
+    ) -> TextModeLaidOutText {
+        let laid_out = layout.layout(text, charset);
+        for &(x, y, index) in &laid_out.cells {
+            if let Some(cell) = self.get_mut(x, y) {
+                *cell = TextModeCell {
+                    index,
+                    bg,
+                    fg,
+                    ..default()
+                };
+            }
+        }
+        laid_out
+    }
+
+    /// Scrolls the whole grid up by `n` rows, see [`TextModeGrid::scroll_region`]
+    pub fn scroll_up(&mut self, n: usize, cell: TextModeCell) {
+        self.scroll_region(0, self.height.saturating_sub(1), n as isize, cell);
+    }
+
+    /// Scrolls the whole grid down by `n` rows, see [`TextModeGrid::scroll_region`]
+    pub fn scroll_down(&mut self, n: usize, cell: TextModeCell) {
+        self.scroll_region(0, self.height.saturating_sub(1), -(n as isize), cell);
+    }
+
+    /// Moves the rows `top..=bottom` up by `n` rows, or down if `n` is negative.
+    /// Freed rows are set to `cell`.
+    ///
+    /// Rows scrolled out of the top of the grid are pushed to the scrollback history.
+    /// When the view is scrolled back, it keeps showing the same rows.
+    pub fn scroll_region(&mut self, top: usize, bottom: usize, n: isize, cell: TextModeCell) {
+        if top > 0 || n <= 0 || bottom >= self.height {
+            self.shift_rows(top, bottom, n, cell);
+            return;
+        }
+        let shift = n.unsigned_abs().min(bottom + 1);
+        let scrolled_out: Vec<_> = self.cells[..shift * self.width].chunks(self.width).map(<[_]>::to_vec).collect();
+        self.shift_rows(top, bottom, n, cell);
+        for row in scrolled_out {
+            self.push_scrollback(row);
+        }
+    }
+
+    /// Moves the rows `top..=bottom` up by `n` rows, or down if `n` is negative, without
+    /// touching the scrollback history. Freed rows are set to `cell`.
+    pub fn shift_rows(&mut self, top: usize, bottom: usize, n: isize, cell: TextModeCell) {
+        if top > bottom || bottom >= self.height {
+            return;
+        }
+        let rows = &mut self.cells[top * self.width..(bottom + 1) * self.width];
+        let shift = n.unsigned_abs().min(bottom + 1 - top) * self.width;
+        if n >= 0 {
+            rows.rotate_left(shift);
+            let len = rows.len();
+            rows[len - shift..].fill(cell);
+        } else {
+            rows.rotate_right(shift);
+            rows[..shift].fill(cell);
+        }
+    }
+
+    fn push_scrollback(&mut self, row: Vec<TextModeCell>) {
+        self.scrollback.push_back(row);
+        if self.scrollback.len() > self.scrollback_limit {
+            // The limit may have been lowered since the last push
+            let excess = self.scrollback.len() - self.scrollback_limit;
+            self.scrollback.drain(..excess);
+        }
+        // The view keeps showing the same rows, unless they were dropped from the history
+        if self.view_offset > 0.0 {
+            self.view_offset = (self.view_offset + 1.0).min(self.scrollback.len() as f32);
+        }
+    }
+
+    /// Rows scrolled out of the grid, the oldest first
+    pub fn scrollback(&self) -> &VecDeque<Vec<TextModeCell>> {
+        &self.scrollback
+    }
+
+    /// Clears the scrollback history and scrolls the view back to the grid rows
+    pub fn clear_scrollback(&mut self) {
+        self.scrollback.clear();
+        self.view_offset = 0.0;
+    }
+
+    /// Number of scrollback rows shown above the grid rows
+    pub fn view_offset(&self) -> f32 {
+        self.view_offset
+    }
+
+    /// Scrolls the view `rows` rows back into the scrollback history, `0` showing the grid rows.
+    ///
+    /// A fractional offset scrolls by pixels: cells are moved by a fraction of the cell height
+    /// and the partially visible rows overflow the grid bounds by less than a cell.
+    pub fn set_view_offset(&mut self, rows: f32) {
+        self.view_offset = rows.clamp(0.0, self.scrollback.len() as f32);
+    }
+
+    /// Row shown `y` rows below the top of the grid rows, negative rows being in the scrollback history
+    fn row(&self, y: isize) -> Option<&[TextModeCell]> {
+        if y >= 0 {
+            let y = y as usize;
+            (y < self.height).then(|| &self.cells[y * self.width..(y + 1) * self.width])
+        } else {
+            let i = self.scrollback.len().checked_sub(y.unsigned_abs())?;
+            Some(&self.scrollback[i])
+        }
+    }
+
+    fn cell_index(&self, x: usize, y: usize) -> Option<usize> {
+        if x < self.width && y < self.height {
+            Some(y * self.width + x)
+        } else {
+            None
+        }
+    }
+
+    /// Computes [`TextModeExtractedSprite`] iterator from the grid cells
+    ///
+    /// # Arguments
+    ///
+    /// * `transform` - the grid entity global transform
+    /// * `layout` - the grid texture atlas layout
+    /// * `handle` - the grid texture handle
+    pub(crate) fn extract_text_mode_sprites<'a>(
+        &'a self,
+        transform: &'a GlobalTransform,
+        layout: &'a TextureAtlasLayout,
+        handle: &'a Handle<Image>,
+    ) -> impl Iterator<Item = TextModeExtractedSprite> + 'a {
+        let (cell_size, top_left, rows_back, first_row) = self.drawn_rows(layout);
+        let rows = (first_row..self.height as isize).filter_map(move |y| Some((y, self.row(y - rows_back)?)));
+
+        let cells = rows.flat_map(|(y, row)| row.iter().take(self.width).enumerate().map(move |(x, cell)| (x, y, cell)));
+
+        cells.filter_map(move |(x, y, cell)| {
+            let rect = layout.textures.get(cell.index)?.as_rect();
+            let offset = top_left + Vec2::new(x as f32, -(y as f32)) * cell_size;
+            Some(TextModeExtractedSprite {
+                bg: cell.bg,
+                fg: cell.fg,
+                alpha: self.alpha,
+                transform: transform.mul_transform(Transform::from_translation(offset.extend(0.0))),
+                rect: Some(rect),
+                glyph: Some((self.layout.id(), u32::try_from(cell.index).ok()?)),
+                custom_size: Some(cell_size),
+                flip_x: cell.flip_x,
+                flip_y: cell.flip_y,
+                rotation: cell.rotation,
+                image_handle_id: handle.id(),
+                anchor: Anchor::TopLeft.as_vec(),
+                palette: [LinearRgba::NONE; 4],
+                palette_len: 0,
+                mask: self.mask,
+                invert_mask: self.invert_mask,
+                skip_bg: cell.skip_bg,
+                skip_fg: cell.skip_fg,
+                coverage: self.coverage,
+                blink: cell.blink,
+                cursor: self.cursor.filter(|c| (c.x, c.y as isize) == (x, y - rows_back)),
+                mode_3d: None,
+            })
+        })
+    }
+
+    /// Computes the cells of a [`TextModeGrid::gpu_driven`] grid
+    ///
+    /// Returns the cell size, the top left corner of the first drawn cell, and `width` cells
+    /// per drawn row with their cursor, `None` for the cells missing from the scrollback history
+    pub(crate) fn extract_drawn_cells<'a>(
+        &'a self,
+        layout: &TextureAtlasLayout,
+    ) -> (Vec2, Vec2, impl Iterator<Item = Option<(&'a TextModeCell, Option<TextModeCursor>)>> + 'a) {
+        let (cell_size, top_left, rows_back, first_row) = self.drawn_rows(layout);
+        let origin = top_left - Vec2::new(0.0, first_row as f32 * cell_size.y);
+        let cells = (first_row..self.height as isize).flat_map(move |y| {
+            let row = self.row(y - rows_back).unwrap_or_default();
+            (0..self.width).map(move |x| {
+                let cursor = self.cursor.filter(|c| (c.x, c.y as isize) == (x, y - rows_back));
+                row.get(x).map(|cell| (cell, cursor))
+            })
+        });
+        (cell_size, origin, cells)
+    }
+
+    /// Returns the cell size, the top left corner of the grid rows, and the offset and first screen row
+    /// of the drawn rows.
+    ///
+    /// Screen row `y` shows the row `y - rows_back`, a partially visible row being drawn
+    /// above the first row when scrolled by a fraction of a row.
+    fn drawn_rows(&self, layout: &TextureAtlasLayout) -> (Vec2, Vec2, isize, isize) {
+        let cell_size = self
+            .cell_size
+            .or_else(|| layout.textures.first().map(|r| r.size().as_vec2()))
+            .unwrap_or_default();
+        let grid_size = cell_size * Vec2::new(self.width as f32, self.height as f32);
+        let top_left = (Vec2::new(-0.5, 0.5) - self.anchor.as_vec()) * grid_size;
+
+        let rows_back = self.view_offset.floor() as isize;
+        let fraction = self.view_offset.fract();
+        let first_row = if fraction > 0.0 { -1 } else { 0 };
+        let top_left = top_left - Vec2::new(0.0, fraction * cell_size.y);
+        (cell_size, top_left, rows_back, first_row)
+    }
+}
+
+#[derive(Bundle, Clone, Default)]
+pub struct TextModeGridBundle {
+    pub grid: TextModeGrid,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+    pub texture: Handle<Image>,
+    pub visibility: Visibility,
+    pub inherited_visibility: InheritedVisibility,
+    pub view_visibility: ViewVisibility,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(index: usize) -> TextModeCell {
+        TextModeCell {
+            index,
+            ..default()
+        }
+    }
+
+    /// A `width` × `height` grid, the cells of row `y` having the index `y`
+    fn grid(width: usize, height: usize) -> TextModeGrid {
+        let mut grid = TextModeGrid::new(width, height, Handle::default());
+        for (i, c) in grid.cells_mut().iter_mut().enumerate() {
+            *c = cell(i / width);
+        }
+        grid
+    }
+
+    fn indices(rows: impl IntoIterator<Item = impl AsRef<[TextModeCell]>>) -> Vec<Vec<usize>> {
+        rows.into_iter().map(|row| row.as_ref().iter().map(|c| c.index).collect()).collect()
+    }
+
+    #[test]
+    fn scrollback_limit() {
+        let mut grid = grid(2, 3);
+        grid.scroll_up(1, cell(9));
+        assert!(grid.scrollback().is_empty());
+
+        grid.scrollback_limit = 2;
+        grid.scroll_up(1, cell(9));
+        grid.scroll_up(2, cell(9));
+        // The oldest rows are dropped first
+        assert_eq!(indices(grid.scrollback()), [[2, 2], [9, 9]]);
+        assert_eq!(indices(grid.cells().chunks(2)), [[9, 9], [9, 9], [9, 9]]);
+
+        let mut grid = self::grid(2, 3);
+        grid.scrollback_limit = 10;
+        grid.scroll_up(2, cell(9));
+        assert_eq!(indices(grid.scrollback()), [[0, 0], [1, 1]]);
+        assert_eq!(indices(grid.cells().chunks(2)), [[2, 2], [9, 9], [9, 9]]);
+
+        // Lowering the limit trims the history on the next push
+        grid.scrollback_limit = 1;
+        grid.scroll_up(1, cell(9));
+        assert_eq!(indices(grid.scrollback()), [[2, 2]]);
+    }
+
+    #[test]
+    fn scrollback_sources() {
+        let mut grid = grid(1, 4);
+        grid.scrollback_limit = 10;
+        // Rows scrolled down, or out of a region below the first row, are not kept
+        grid.scroll_down(1, cell(9));
+        grid.scroll_region(1, 3, 1, cell(9));
+        grid.shift_rows(0, 3, 1, cell(9));
+        assert!(grid.scrollback().is_empty());
+
+        // Scrolling more rows than the region keeps every row once
+        let mut grid = self::grid(1, 3);
+        grid.scrollback_limit = 10;
+        grid.scroll_region(0, 1, 5, cell(9));
+        assert_eq!(indices(grid.scrollback()), [[0], [1]]);
+        assert_eq!(indices(grid.cells().chunks(1)), [[9], [9], [2]]);
+    }
+
+    #[test]
+    fn view_offset() {
+        let mut grid = grid(1, 2);
+        grid.scrollback_limit = 3;
+        grid.set_view_offset(1.0);
+        assert_eq!(grid.view_offset(), 0.0);
+
+        grid.scroll_up(2, cell(9));
+        grid.set_view_offset(5.0);
+        assert_eq!(grid.view_offset(), 2.0);
+        grid.set_view_offset(1.5);
+        assert_eq!(grid.view_offset(), 1.5);
+
+        // The view keeps showing the same rows while the history grows
+        grid.scroll_up(1, cell(9));
+        assert_eq!(grid.view_offset(), 2.5);
+        assert_eq!(grid.scrollback().len(), 3);
+
+        // Including when the history is full, until the shown rows are dropped from it
+        grid.set_view_offset(1.5);
+        grid.scroll_up(1, cell(9));
+        assert_eq!(grid.scrollback().len(), 3);
+        assert_eq!(grid.view_offset(), 2.5);
+        grid.scroll_up(1, cell(9));
+        assert_eq!(grid.view_offset(), 3.0);
+        grid.scrollback_limit = 1;
+        grid.scroll_up(1, cell(9));
+        assert_eq!(grid.view_offset(), 1.0);
+
+        grid.clear_scrollback();
+        assert!(grid.scrollback().is_empty());
+        assert_eq!(grid.view_offset(), 0.0);
+    }
+
+    #[test]
+    fn resize() {
+        let mut grid = grid(3, 2);
+        grid.resize(2, 3, cell(9));
+        assert_eq!((grid.width(), grid.height()), (2, 3));
+        assert_eq!(indices(grid.cells().chunks(2)), [[0, 0], [1, 1], [9, 9]]);
+
+        grid.resize(4, 1, cell(8));
+        assert_eq!(indices(grid.cells().chunks(4)), [[0, 0, 8, 8]]);
+        assert_eq!(grid.get(3, 0).map(|c| c.index), Some(8));
+        assert_eq!(grid.get(0, 1), None);
+
+        grid.resize(0, 0, cell(8));
+        assert!(grid.cells().is_empty());
+        grid.scroll_up(1, cell(9));
+        grid.resize(1, 1, cell(7));
+        assert_eq!(indices(grid.cells().chunks(1)), [[7]]);
+    }
+}
@@ -0,0 +1,366 @@
+use std::ops::Range;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use bevy::ecs::entity::EntityHashMap;
+use bevy::prelude::*;
+use bevy::render::render_resource::{Buffer, BufferUsages, RawBufferVec};
+use bevy::render::renderer::{RenderDevice, RenderQueue};
+use bytemuck::Pod;
+
+/// Unchanged instances between two written ranges closer than this are uploaded with them,
+/// to avoid many small writes
+const MERGE_GAP: usize = 8;
+
+/// Instance upload statistics of the last rendered frame.
+///
+/// The statistics are shared with the render world, with pipelined rendering they are one frame late.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct TextModeInstanceStats(Arc<TextModeInstanceCounters>);
+
+#[derive(Debug, Default)]
+struct TextModeInstanceCounters {
+    instances: AtomicUsize,
+    uploaded_instances: AtomicUsize,
+    uploaded_bytes: AtomicUsize,
+    writes: AtomicUsize,
+}
+
+impl TextModeInstanceStats {
+    /// Number of sprite and grid cell instances drawn
+    pub fn instances(&self) -> usize {
+        self.0.instances.load(Ordering::Relaxed)
+    }
+
+    /// Number of instances written to the GPU, instances left unchanged since the previous frame are not
+    pub fn uploaded_instances(&self) -> usize {
+        self.0.uploaded_instances.load(Ordering::Relaxed)
+    }
+
+    pub fn uploaded_bytes(&self) -> usize {
+        self.0.uploaded_bytes.load(Ordering::Relaxed)
+    }
+
+    /// Number of buffer writes, one per dirty range of instances
+    pub fn writes(&self) -> usize {
+        self.0.writes.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn set(&self, upload: TextModeInstanceUpload) {
+        self.0.instances.store(upload.instances, Ordering::Relaxed);
+        self.0.uploaded_instances.store(upload.uploaded_instances, Ordering::Relaxed);
+        self.0.uploaded_bytes.store(upload.uploaded_bytes, Ordering::Relaxed);
+        self.0.writes.store(upload.writes, Ordering::Relaxed);
+    }
+}
+
+/// Upload statistics of a [`TextModeInstanceBuffer`] write
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct TextModeInstanceUpload {
+    pub instances: usize,
+    pub uploaded_instances: usize,
+    pub uploaded_bytes: usize,
+    pub writes: usize,
+}
+
+impl std::ops::Add for TextModeInstanceUpload {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            instances: self.instances + other.instances,
+            uploaded_instances: self.uploaded_instances + other.uploaded_instances,
+            uploaded_bytes: self.uploaded_bytes + other.uploaded_bytes,
+            writes: self.writes + other.writes,
+        }
+    }
+}
+
+/// Instances of an entity in a [`TextModeInstanceBuffer`]
+struct TextModeInstanceSlot {
+    range: Range<usize>,
+    /// Whether the slot was drawn this frame, unused slots being freed at the end of the frame
+    used: bool,
+}
+
+/// Instance buffer keeping the instances of each entity resident on the GPU.
+///
+/// Every entity owns a slot of the buffer. Unchanged entities reuse their slot without rebuilding
+/// their instances, changed entities rewrite their slot and only the written slots are uploaded.
+/// Slots of entities that are not drawn anymore are freed and reused by new entities.
+pub(crate) struct TextModeInstanceBuffer<T: Pod> {
+    /// Instances of every slot, mirrored in the GPU buffer
+    buffer: RawBufferVec<T>,
+    slots: EntityHashMap<TextModeInstanceSlot>,
+    /// Free ranges of `buffer`, sorted and not adjacent
+    free: Vec<Range<usize>>,
+    /// Ranges written since the last upload
+    dirty: Vec<Range<usize>>,
+}
+
+impl<T: Pod> TextModeInstanceBuffer<T> {
+    pub fn new(buffer_usage: BufferUsages) -> Self {
+        Self {
+            buffer: RawBufferVec::new(buffer_usage),
+            slots: EntityHashMap::default(),
+            free: Vec::new(),
+            dirty: Vec::new(),
+        }
+    }
+
+    pub fn buffer(&self) -> Option<&Buffer> {
+        self.buffer.buffer()
+    }
+
+    /// Range of the resident instances of `entity`, `None` if they have to be written because the entity
+    /// changed or has no slot. Instances already used this frame are always resident
+    pub fn resident(&mut self, entity: Entity, changed: bool) -> Option<Range<u32>> {
+        let slot = self.slots.get_mut(&entity)?;
+        if changed && !slot.used {
+            return None;
+        }
+        slot.used = true;
+        Some(slot.range.start as u32..slot.range.end as u32)
+    }
+
+    /// Writes the instances of `entity` to its slot, moving to a new slot if their count changed
+    pub fn write<I>(&mut self, entity: Entity, values: I) -> Range<u32>
+    where
+        I: IntoIterator<Item = T>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let values = values.into_iter();
+        let len = values.len();
+        let range = match self.slots.get(&entity) {
+            Some(slot) if slot.range.len() == len => slot.range.clone(),
+            slot => {
+                if let Some(slot) = slot {
+                    let range = slot.range.clone();
+                    self.free(range);
+                }
+                self.allocate(len)
+            }
+        };
+
+        for (instance, value) in self.buffer.values_mut()[range.clone()].iter_mut().zip(values) {
+            *instance = value;
+        }
+        if !range.is_empty() {
+            self.dirty.push(range.clone());
+        }
+        self.slots.insert(entity, TextModeInstanceSlot { range: range.clone(), used: true });
+        range.start as u32..range.end as u32
+    }
+
+    /// Drops every slot, the instances being written again in draw order on the next frame
+    /// to make the slots of consecutive phase items contiguous again
+    pub fn relayout(&mut self) {
+        self.slots.clear();
+        self.free.clear();
+        self.dirty.clear();
+        self.buffer.clear();
+    }
+
+    /// Frees the slots that were not used this frame and uploads the written slots
+    pub fn end_frame(&mut self, device: &RenderDevice, queue: &RenderQueue) -> TextModeInstanceUpload {
+        let mut dirty = self.finish_frame();
+        let len = self.buffer.len();
+        let mut upload = TextModeInstanceUpload {
+            instances: self.slots.values().map(|slot| slot.range.len()).sum(),
+            ..default()
+        };
+        if len == 0 {
+            return upload;
+        }
+
+        // A new buffer has no resident instances
+        let capacity = self.buffer.capacity();
+        self.buffer.reserve(len, device);
+        if self.buffer.capacity() != capacity {
+            dirty.clear();
+            dirty.push(0..len);
+        }
+        let (Some(buffer), values) = (self.buffer.buffer(), self.buffer.values()) else {
+            return upload;
+        };
+
+        let size = std::mem::size_of::<T>();
+        for range in dirty {
+            let bytes: &[u8] = bytemuck::cast_slice(&values[range.clone()]);
+            queue.write_buffer(buffer, (range.start * size) as u64, bytes);
+            upload.uploaded_instances += range.len();
+            upload.uploaded_bytes += bytes.len();
+            upload.writes += 1;
+        }
+        upload
+    }
+
+    /// Frees the unused slots and returns the ranges to upload, merging ranges separated by small gaps
+    fn finish_frame(&mut self) -> Vec<Range<usize>> {
+        let unused: Vec<_> = self
+            .slots
+            .iter()
+            .filter(|(_, slot)| !slot.used)
+            .map(|(&entity, _)| entity)
+            .collect();
+        for entity in unused {
+            if let Some(slot) = self.slots.remove(&entity) {
+                self.free(slot.range);
+            }
+        }
+        for slot in self.slots.values_mut() {
+            slot.used = false;
+        }
+
+        let len = self.buffer.len();
+        let mut dirty = std::mem::take(&mut self.dirty);
+        dirty.sort_by_key(|range| range.start);
+        let mut ranges: Vec<Range<usize>> = Vec::with_capacity(dirty.len());
+        for range in dirty {
+            let range = range.start..range.end.min(len);
+            if range.is_empty() {
+                continue;
+            }
+            match ranges.last_mut() {
+                Some(last) if range.start <= last.end + MERGE_GAP => last.end = last.end.max(range.end),
+                _ => ranges.push(range),
+            }
+        }
+        ranges
+    }
+
+    /// Finds a free range of `len` instances, growing the buffer if none is large enough
+    fn allocate(&mut self, len: usize) -> Range<usize> {
+        if len == 0 {
+            return 0..0;
+        }
+        if let Some(i) = self.free.iter().position(|range| range.len() >= len) {
+            let start = self.free[i].start;
+            self.free[i].start += len;
+            if self.free[i].is_empty() {
+                self.free.remove(i);
+            }
+            return start..start + len;
+        }
+
+        let start = self.buffer.len();
+        self.buffer.values_mut().resize(start + len, T::zeroed());
+        start..start + len
+    }
+
+    /// Returns `range` to the free list, merging it with its neighbors and shrinking the buffer if it ends it
+    fn free(&mut self, range: Range<usize>) {
+        if range.is_empty() {
+            return;
+        }
+        let i = self.free.partition_point(|free| free.start < range.start);
+        self.free.insert(i, range);
+        if i + 1 < self.free.len() && self.free[i].end == self.free[i + 1].start {
+            self.free[i].end = self.free.remove(i + 1).end;
+        }
+        if i > 0 && self.free[i - 1].end == self.free[i].start {
+            self.free[i - 1].end = self.free.remove(i).end;
+        }
+
+        if self.free.last().is_some_and(|free| free.end == self.buffer.len()) {
+            let free = self.free.pop().unwrap();
+            self.buffer.values_mut().truncate(free.start);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer() -> TextModeInstanceBuffer<u32> {
+        TextModeInstanceBuffer::new(BufferUsages::VERTEX)
+    }
+
+    #[test]
+    fn resident_slots() {
+        let mut buffer = buffer();
+        let (a, b) = (Entity::from_raw(1), Entity::from_raw(2));
+        assert_eq!(buffer.resident(a, false), None);
+        assert_eq!(buffer.write(a, [1, 2]), 0..2);
+        assert_eq!(buffer.write(b, [3, 4, 5]), 2..5);
+        assert_eq!(buffer.finish_frame(), vec![0..5]);
+
+        // Unchanged entities keep their slot and upload nothing
+        assert_eq!(buffer.resident(a, false), Some(0..2));
+        assert_eq!(buffer.resident(b, true), None);
+        assert_eq!(buffer.write(b, [6, 7, 8]), 2..5);
+        // Already written this frame, as when drawn by a second view
+        assert_eq!(buffer.resident(b, true), Some(2..5));
+        assert_eq!(buffer.finish_frame(), vec![2..5]);
+        assert_eq!(buffer.buffer.values(), &vec![1, 2, 6, 7, 8]);
+    }
+
+    #[test]
+    fn reused_slots() {
+        let mut buffer = buffer();
+        let [a, b, c, d] = [1, 2, 3, 4].map(Entity::from_raw);
+        buffer.write(a, [0; 2]);
+        buffer.write(b, [0; 3]);
+        buffer.write(c, [0; 1]);
+        buffer.finish_frame();
+
+        // b is not drawn anymore, its slot is reused by d
+        buffer.resident(a, false);
+        buffer.resident(c, false);
+        assert!(buffer.finish_frame().is_empty());
+        assert_eq!(buffer.free, vec![2..5]);
+        buffer.resident(a, false);
+        buffer.resident(c, false);
+        assert_eq!(buffer.write(d, [0; 2]), 2..4);
+        assert_eq!(buffer.free, vec![4..5]);
+
+        // A resized slot moves, its previous slot merging with the free range at the end of the buffer
+        assert_eq!(buffer.write(c, [0; 4]), 4..8);
+        assert!(buffer.free.is_empty());
+        buffer.finish_frame();
+        buffer.resident(a, false);
+        buffer.resident(d, false);
+        buffer.finish_frame();
+        assert_eq!(buffer.buffer.len(), 4);
+        assert!(buffer.free.is_empty());
+    }
+
+    #[test]
+    fn merged_writes() {
+        let mut buffer = buffer();
+        let entities = [1, 2, 3, 4].map(Entity::from_raw);
+        for entity in entities {
+            buffer.write(entity, [0; 10]);
+        }
+        buffer.finish_frame();
+
+        for entity in entities {
+            buffer.resident(entity, false);
+        }
+        buffer.write(entities[0], [1; 10]);
+        buffer.write(entities[3], [1; 10]);
+        assert_eq!(buffer.finish_frame(), vec![0..10, 30..40]);
+
+        for entity in entities {
+            buffer.resident(entity, false);
+        }
+        buffer.write(entities[3], [2; 10]);
+        buffer.write(entities[2], [2; 10]);
+        assert_eq!(buffer.finish_frame(), vec![20..40]);
+    }
+
+    #[test]
+    fn relayout() {
+        let mut buffer = buffer();
+        let (a, b) = (Entity::from_raw(1), Entity::from_raw(2));
+        buffer.write(a, [1]);
+        buffer.write(b, [2]);
+        buffer.finish_frame();
+        buffer.relayout();
+
+        assert_eq!(buffer.resident(b, false), None);
+        assert_eq!(buffer.write(b, [2]), 0..1);
+        assert_eq!(buffer.write(a, [1]), 1..2);
+    }
+}
@@ -0,0 +1,287 @@
+use bevy::prelude::*;
+use thiserror::Error;
+
+use crate::{Charset, TextModePalette};
+
+#[derive(Debug, Error, PartialEq)]
+pub enum TextModeMarkupError {
+    #[error("unclosed tag at {position}")]
+    UnclosedTag { position: usize },
+    #[error("unknown tag `{tag}` at {position}")]
+    UnknownTag { position: usize, tag: String },
+    #[error("unknown color `{color}` at {position}")]
+    UnknownColor { position: usize, color: String },
+    #[error("invalid `{tag}` value `{value}` at {position}")]
+    InvalidValue { position: usize, tag: String, value: String },
+}
+
+/// Style of a character of a [`TextModeMarkup`], `None` colors falling back to the printing colors
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct TextModeStyle {
+    pub fg: Option<LinearRgba>,
+    pub bg: Option<LinearRgba>,
+    pub flip_x: bool,
+    pub flip_y: bool,
+    /// Rotation in quarter turns
+    pub rotation: u8,
+}
+
+/// Text with per character styles, parsed from inline markup tags.
+///
+/// Tags are enclosed in brackets, `[[` being a literal `[`:
+/// - `[fg=color]` and `[bg=color]` set the colors, `color` being a palette color name or a `#RGB`,
+///   `#RRGGBB` or `#RRGGBBAA` hex color
+/// - `[flip=x]`, `[flip=y]` and `[flip=xy]` flip the glyphs
+/// - `[rotate=n]` rotates the glyphs by `n` quarter turns
+/// - `[/fg]`, `[/bg]`, `[/flip]` and `[/rotate]` reset a single attribute, `[/]` resets them all
+///
+/// ```text
+/// You found [fg=yellow]a key[/]
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TextModeMarkup {
+    text: String,
+    styles: Vec<TextModeStyle>,
+}
+
+impl TextModeMarkup {
+    /// Parses `source`, color names being looked up in `palette`.
+    ///
+    /// Errors report the byte position of the faulty tag in `source`.
+    pub fn parse(source: &str, palette: Option<&TextModePalette>) -> Result<Self, TextModeMarkupError> {
+        let mut markup = Self::default();
+        let mut style = TextModeStyle::default();
+        let mut chars = source.char_indices().peekable();
+        while let Some((position, c)) = chars.next() {
+            if c != '[' {
+                markup.text.push(c);
+                markup.styles.push(style);
+                continue;
+            }
+            if chars.next_if(|&(_, c)| c == '[').is_some() {
+                markup.text.push('[');
+                markup.styles.push(style);
+                continue;
+            }
+
+            let start = position + 1;
+            let end = source[start..]
+                .find(']')
+                .map(|len| start + len)
+                .ok_or(TextModeMarkupError::UnclosedTag { position })?;
+            apply_tag(&mut style, &source[start..end], position, palette)?;
+            while chars.next_if(|&(i, _)| i <= end).is_some() {}
+        }
+        Ok(markup)
+    }
+
+    /// Text without the tags
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Styles of the characters of [`TextModeMarkup::text`]
+    pub fn styles(&self) -> &[TextModeStyle] {
+        &self.styles
+    }
+
+    /// Iterates over the `(x, y, index, style)` positions, atlas indices and styles of the characters.
+    ///
+    /// `'\n'` moves to the start of the next line, characters missing from `charset` are skipped.
+    pub(crate) fn cells<'a>(
+        &'a self,
+        charset: &'a impl Charset,
+    ) -> impl Iterator<Item = (usize, usize, usize, TextModeStyle)> + 'a {
+        let (mut x, mut y) = (0, 0);
+        self.text.chars().zip(&self.styles).filter_map(move |(c, &style)| {
+            match c {
+                '\n' => {
+                    (x, y) = (0, y + 1);
+                    return None;
+                }
+                '\r' => return None,
+                _ => {}
+            }
+            x += 1;
+            charset.index(c).map(|index| (x - 1, y, index, style))
+        })
+    }
+}
+
+fn apply_tag(
+    style: &mut TextModeStyle,
+    tag: &str,
+    position: usize,
+    palette: Option<&TextModePalette>,
+) -> Result<(), TextModeMarkupError> {
+    let (name, value) = match tag.split_once('=') {
+        Some((name, value)) => (name.trim(), Some(value.trim())),
+        None => (tag.trim(), None),
+    };
+    let invalid_value = |value: &str| TextModeMarkupError::InvalidValue {
+        position,
+        tag: name.to_string(),
+        value: value.to_string(),
+    };
+
+    match (name, value) {
+        ("/", None) => *style = TextModeStyle::default(),
+        ("/fg", None) => style.fg = None,
+        ("/bg", None) => style.bg = None,
+        ("/flip", None) => (style.flip_x, style.flip_y) = (false, false),
+        ("/rotate", None) => style.rotation = 0,
+        ("fg", Some(value)) => style.fg = Some(parse_color(value, position, palette)?),
+        ("bg", Some(value)) => style.bg = Some(parse_color(value, position, palette)?),
+        ("flip", Some(value)) => {
+            (style.flip_x, style.flip_y) = match value {
+                "x" => (true, false),
+                "y" => (false, true),
+                "xy" | "yx" => (true, true),
+                _ => return Err(invalid_value(value)),
+            };
+        }
+        ("rotate", Some(value)) => {
+            style.rotation = value.parse::<u8>().map_err(|_| invalid_value(value))? % 4;
+        }
+        _ => {
+            return Err(TextModeMarkupError::UnknownTag {
+                position,
+                tag: tag.to_string(),
+            })
+        }
+    }
+    Ok(())
+}
+
+fn parse_color(
+    value: &str,
+    position: usize,
+    palette: Option<&TextModePalette>,
+) -> Result<LinearRgba, TextModeMarkupError> {
+    let color = if value.starts_with('#') {
+        Srgba::hex(value).ok().map(LinearRgba::from)
+    } else {
+        palette.and_then(|palette| palette.get_named(value))
+    };
+    color.ok_or_else(|| TextModeMarkupError::UnknownColor {
+        position,
+        color: value.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TextModeCharset;
+
+    fn palette() -> TextModePalette {
+        let mut palette = TextModePalette::default();
+        palette.push_named("red", LinearRgba::RED);
+        palette.push_named("blue", LinearRgba::BLUE);
+        palette
+    }
+
+    #[test]
+    fn nested_tags() {
+        let palette = palette();
+        let markup = TextModeMarkup::parse("a[fg=red]b[bg=blue][flip=x]c[/fg]d[/]e", Some(&palette)).unwrap();
+        assert_eq!(markup.text(), "abcde");
+        let [a, b, c, d, e] = markup.styles().try_into().unwrap();
+        assert_eq!(a, TextModeStyle::default());
+        assert_eq!(b.fg, Some(LinearRgba::RED));
+        assert_eq!(b.bg, None);
+        assert_eq!((c.fg, c.bg, c.flip_x, c.flip_y), (Some(LinearRgba::RED), Some(LinearRgba::BLUE), true, false));
+        // Closing a tag only resets its attribute
+        assert_eq!((d.fg, d.bg, d.flip_x), (None, Some(LinearRgba::BLUE), true));
+        assert_eq!(e, TextModeStyle::default());
+
+        // Inner tags override outer ones
+        let markup = TextModeMarkup::parse("[fg=red][fg=#00F]a[rotate=5][flip=xy]b[/rotate][/flip]c", Some(&palette)).unwrap();
+        let [a, b, c] = markup.styles().try_into().unwrap();
+        assert_eq!(a.fg, Some(LinearRgba::BLUE));
+        assert_eq!((b.rotation, b.flip_x, b.flip_y), (1, true, true));
+        assert_eq!((c.fg, c.rotation, c.flip_x, c.flip_y), (Some(LinearRgba::BLUE), 0, false, false));
+    }
+
+    #[test]
+    fn escapes() {
+        let markup = TextModeMarkup::parse("[[a] [fg=#FF0000][[[/]]", None).unwrap();
+        assert_eq!(markup.text(), "[a] []");
+        assert_eq!(markup.styles()[4].fg, Some(LinearRgba::RED));
+        assert_eq!(markup.styles()[5].fg, None);
+        // Tags are not parsed inside tag values
+        assert_eq!(
+            TextModeMarkup::parse("[fg=[[]x", None),
+            Err(TextModeMarkupError::UnknownColor {
+                position: 0,
+                color: "[[".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            TextModeMarkup::parse("ab[fg=red", None),
+            Err(TextModeMarkupError::UnclosedTag { position: 2 })
+        );
+        assert_eq!(TextModeMarkup::parse("é[", None), Err(TextModeMarkupError::UnclosedTag { position: 2 }));
+        assert_eq!(
+            TextModeMarkup::parse("a[bold]b", None),
+            Err(TextModeMarkupError::UnknownTag {
+                position: 1,
+                tag: "bold".to_string()
+            })
+        );
+        // Closing tags do not take values and opening tags need one
+        for tag in ["/fg=red", "fg", "/x", ""] {
+            assert!(matches!(
+                TextModeMarkup::parse(&format!("[{tag}]"), None),
+                Err(TextModeMarkupError::UnknownTag { .. })
+            ));
+        }
+        assert_eq!(
+            TextModeMarkup::parse("[fg=red]", None),
+            Err(TextModeMarkupError::UnknownColor {
+                position: 0,
+                color: "red".to_string()
+            })
+        );
+        assert_eq!(
+            TextModeMarkup::parse("[bg=#GG0000]", Some(&palette())),
+            Err(TextModeMarkupError::UnknownColor {
+                position: 0,
+                color: "#GG0000".to_string()
+            })
+        );
+        assert_eq!(
+            TextModeMarkup::parse("x[flip=z]", None),
+            Err(TextModeMarkupError::InvalidValue {
+                position: 1,
+                tag: "flip".to_string(),
+                value: "z".to_string()
+            })
+        );
+        assert!(matches!(
+            TextModeMarkup::parse("[rotate=-1]", None),
+            Err(TextModeMarkupError::InvalidValue { .. })
+        ));
+    }
+
+    #[test]
+    fn cells() {
+        let markup = TextModeMarkup::parse("a[fg=#F00]b\r\nc\u{2603}d", None).unwrap();
+        let charset = TextModeCharset::ascii(b' ', 0);
+        let cells: Vec<_> = markup.cells(&charset).map(|(x, y, index, style)| (x, y, index, style.fg)).collect();
+        // Missing characters are skipped but take their cell
+        assert_eq!(
+            cells,
+            [
+                (0, 0, b'a' as usize - 32, None),
+                (1, 0, b'b' as usize - 32, Some(LinearRgba::RED)),
+                (0, 1, b'c' as usize - 32, Some(LinearRgba::RED)),
+                (2, 1, b'd' as usize - 32, Some(LinearRgba::RED)),
+            ]
+        );
+    }
+}
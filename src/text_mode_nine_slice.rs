@@ -0,0 +1,116 @@
+use bevy::prelude::*;
+
+use crate::{TextModeCell, TextModeGrid, TextModeGridBundle};
+
+/// A window made of atlas cells, drawn on the [`TextModeGrid`] of its entity.
+///
+/// Corner cells are drawn once, edge cells are repeated along the edges and the center cell fills
+/// the interior. The grid is resized and redrawn whenever the nine-slice changes.
+/// A window 1 cell wide only uses its left column of parts, and a window 1 cell high only its top
+/// row of parts, as there is no room for the opposite edge.
+#[derive(Component, Debug, Clone, Default, Reflect)]
+pub struct TextModeNineSlice {
+    /// Size of the window in cells
+    pub size: UVec2,
+    /// Cells of the 3 × 3 parts, row by row from the top left corner
+    pub parts: [TextModeCell; 9],
+}
+
+impl TextModeNineSlice {
+    /// Creates a window whose parts use the atlas `indices`, row by row from the top left corner,
+    /// and the same colors
+    pub fn new(size: UVec2, indices: [usize; 9], bg: LinearRgba, fg: LinearRgba) -> Self {
+        Self {
+            size,
+            parts: indices.map(|index| TextModeCell {
+                index,
+                bg,
+                fg,
+                ..default()
+            }),
+        }
+    }
+
+    /// Creates a window whose parts are a 3 × 3 block of the atlas, `columns` being the number of
+    /// cells per atlas row
+    pub fn from_atlas_block(
+        size: UVec2,
+        top_left: usize,
+        columns: usize,
+        bg: LinearRgba,
+        fg: LinearRgba,
+    ) -> Self {
+        let indices = std::array::from_fn(|i| top_left + i / 3 * columns + i % 3);
+        Self::new(size, indices, bg, fg)
+    }
+
+    /// Resizes `grid` to the window size and draws the window
+    pub fn draw(&self, grid: &mut TextModeGrid) {
+        let (width, height) = (self.size.x as usize, self.size.y as usize);
+        if (grid.width(), grid.height()) != (width, height) {
+            grid.resize(width, height, TextModeCell::default());
+        }
+        let part = |i: usize, len: usize| match i {
+            0 => 0,
+            _ if i + 1 == len => 2,
+            _ => 1,
+        };
+        for (i, cell) in grid.cells_mut().iter_mut().enumerate() {
+            let (x, y) = (i % width, i / width);
+            *cell = self.parts[part(y, height) * 3 + part(x, width)];
+        }
+    }
+}
+
+/// Redraws the grids of the changed nine-slices
+pub(crate) fn draw_text_mode_nine_slices(
+    mut nine_slices: Query<(&TextModeNineSlice, &mut TextModeGrid), Changed<TextModeNineSlice>>,
+) {
+    for (nine_slice, mut grid) in &mut nine_slices {
+        nine_slice.draw(&mut grid);
+    }
+}
+
+#[derive(Bundle, Clone, Default)]
+pub struct TextModeNineSliceBundle {
+    pub nine_slice: TextModeNineSlice,
+    pub grid: TextModeGridBundle,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn draw(size: UVec2) -> Vec<Vec<usize>> {
+        let nine_slice = TextModeNineSlice::new(size, [0, 1, 2, 3, 4, 5, 6, 7, 8], LinearRgba::WHITE, LinearRgba::BLACK);
+        let mut grid = TextModeGrid::new(1, 1, Handle::default());
+        nine_slice.draw(&mut grid);
+        assert_eq!((grid.width(), grid.height()), (size.x as usize, size.y as usize));
+        grid.cells().chunks(size.x as usize).map(|row| row.iter().map(|cell| cell.index).collect()).collect()
+    }
+
+    #[test]
+    fn draw_window() {
+        assert_eq!(draw(UVec2::new(4, 3)), [[0, 1, 1, 2], [3, 4, 4, 5], [6, 7, 7, 8]]);
+        assert_eq!(draw(UVec2::new(2, 2)), [[0, 2], [6, 8]]);
+    }
+
+    #[test]
+    fn draw_thin_window() {
+        assert_eq!(draw(UVec2::new(1, 3)), [[0], [3], [6]]);
+        assert_eq!(draw(UVec2::new(3, 1)), [[0, 1, 2]]);
+        assert_eq!(draw(UVec2::new(1, 1)), [[0]]);
+    }
+
+    #[test]
+    fn redraw_resized() {
+        let mut nine_slice = TextModeNineSlice::from_atlas_block(UVec2::new(3, 3), 0, 16, LinearRgba::WHITE, LinearRgba::BLACK);
+        let mut grid = TextModeGrid::new(3, 3, Handle::default());
+        nine_slice.draw(&mut grid);
+        nine_slice.size = UVec2::new(5, 2);
+        nine_slice.draw(&mut grid);
+        assert_eq!((grid.width(), grid.height()), (5, 2));
+        let indices: Vec<_> = grid.cells().iter().map(|cell| cell.index).collect();
+        assert_eq!(indices, [0, 1, 1, 1, 2, 32, 33, 33, 33, 34]);
+    }
+}
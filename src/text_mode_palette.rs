@@ -0,0 +1,103 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use crate::plugin::TextModeExtractedSprite;
+
+/// A list of colors, optionally named
+#[derive(Asset, TypePath, Debug, Clone, Default)]
+pub struct TextModePalette {
+    colors: Vec<LinearRgba>,
+    names: HashMap<String, usize>,
+}
+
+impl TextModePalette {
+    pub fn new(colors: impl IntoIterator<Item = LinearRgba>) -> Self {
+        Self {
+            colors: colors.into_iter().collect(),
+            names: HashMap::default(),
+        }
+    }
+
+    /// Adds a color at the end of the palette and returns its index
+    pub fn push(&mut self, color: LinearRgba) -> usize {
+        self.colors.push(color);
+        self.colors.len() - 1
+    }
+
+    /// Adds a named color at the end of the palette and returns its index
+    pub fn push_named(&mut self, name: impl Into<String>, color: LinearRgba) -> usize {
+        let index = self.push(color);
+        self.names.insert(name.into(), index);
+        index
+    }
+
+    /// Names the color at `index`
+    pub fn set_name(&mut self, index: usize, name: impl Into<String>) {
+        self.names.insert(name.into(), index);
+    }
+
+    /// Replaces the color at `index`, out of bounds indices are ignored
+    pub fn set(&mut self, index: usize, color: LinearRgba) {
+        if let Some(c) = self.colors.get_mut(index) {
+            *c = color;
+        }
+    }
+
+    pub fn get(&self, index: usize) -> Option<LinearRgba> {
+        self.colors.get(index).copied()
+    }
+
+    /// Returns the index of the color named `name`
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.names.get(name).copied()
+    }
+
+    /// Returns the color named `name`
+    pub fn get_named(&self, name: &str) -> Option<LinearRgba> {
+        self.index_of(name).and_then(|i| self.get(i))
+    }
+
+    pub fn colors(&self) -> &[LinearRgba] {
+        &self.colors
+    }
+
+    pub fn len(&self) -> usize {
+        self.colors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.colors.is_empty()
+    }
+}
+
+/// Makes a [`TextModeSprite`](crate::TextModeSprite) use the colors of a [`TextModePalette`]
+/// instead of its own colors.
+///
+/// Colors are looked up when the sprite is extracted, so editing or hot-reloading the palette
+/// recolors the sprite without touching its components.
+#[derive(Component, Debug, Clone, Default, Reflect)]
+pub struct TextModePaletteIndices {
+    pub palette: Handle<TextModePalette>,
+    pub bg: usize,
+    pub fg: usize,
+    /// Indices of the indexed palette colors, see [`TextModeSprite::palette`](crate::TextModeSprite::palette)
+    pub shades: Vec<usize>,
+}
+
+impl TextModePaletteIndices {
+    /// Overrides the colors of an extracted sprite, missing palette colors are left untouched
+    pub(crate) fn apply(&self, palette: &TextModePalette, sprite: &mut TextModeExtractedSprite) {
+        if let Some(bg) = palette.get(self.bg) {
+            sprite.bg = bg;
+        }
+        if let Some(fg) = palette.get(self.fg) {
+            sprite.fg = fg;
+        }
+        if !self.shades.is_empty() {
+            for (color, &i) in sprite.palette.iter_mut().zip(&self.shades) {
+                *color = palette.get(i).unwrap_or(LinearRgba::NONE);
+            }
+            sprite.palette_len = self.shades.len().min(sprite.palette.len()) as u32;
+        }
+    }
+}
@@ -0,0 +1,423 @@
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::asset::io::Reader;
+use bevy::prelude::*;
+use thiserror::Error;
+
+use crate::TextModePalette;
+
+#[derive(Debug, Error)]
+pub enum TextModePaletteLoaderError {
+    #[error("could not read palette: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("palette is not valid UTF-8")]
+    Utf8(#[from] std::str::Utf8Error),
+    #[error("missing `{0}` header")]
+    MissingHeader(&'static str),
+    #[error("invalid color on line {line}: `{content}`")]
+    InvalidColor { line: usize, content: String },
+    #[error("expected {expected} colors, found {found}")]
+    ColorCount { expected: usize, found: usize },
+    #[error("could not decode palette image: {0}")]
+    Image(#[from] image::ImageError),
+}
+
+fn rgb(r: u8, g: u8, b: u8) -> LinearRgba {
+    Srgba::rgb_u8(r, g, b).into()
+}
+
+fn invalid_color(line: usize, content: &str) -> TextModePaletteLoaderError {
+    TextModePaletteLoaderError::InvalidColor {
+        line: line + 1,
+        content: content.to_string(),
+    }
+}
+
+/// Parses `count` whitespace separated decimal color components
+fn parse_components(content: &str, count: usize) -> Option<Vec<u8>> {
+    let components: Vec<u8> = content
+        .split_whitespace()
+        .take(count)
+        .map(|c| c.parse().ok())
+        .collect::<Option<_>>()?;
+    (components.len() == count).then_some(components)
+}
+
+/// Parses a `RRGGBB` or `AARRGGBB` hex color, with an optional leading `#`
+fn parse_hex(content: &str) -> Option<LinearRgba> {
+    let hex = content.strip_prefix('#').unwrap_or(content);
+    // `from_str_radix` also accepts a sign
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    let [a, r, g, b] = value.to_be_bytes();
+    match hex.len() {
+        6 => Some(rgb(r, g, b)),
+        8 => Some(Srgba::rgba_u8(r, g, b, a).into()),
+        _ => None,
+    }
+}
+
+async fn read_text(reader: &mut Reader<'_>) -> Result<String, TextModePaletteLoaderError> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes).await?;
+    Ok(std::str::from_utf8(&bytes)?.to_string())
+}
+
+fn parse_gpl(text: &str) -> Result<TextModePalette, TextModePaletteLoaderError> {
+    let mut lines = text.lines().enumerate();
+    if lines.next().map(|(_, l)| l.trim()) != Some("GIMP Palette") {
+        return Err(TextModePaletteLoaderError::MissingHeader("GIMP Palette"));
+    }
+
+    let mut palette = TextModePalette::default();
+    for (i, line) in lines {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with("Name:") || line.starts_with("Columns:") {
+            continue;
+        }
+        let [r, g, b] = parse_components(line, 3)
+            .map(|c| [c[0], c[1], c[2]])
+            .ok_or_else(|| invalid_color(i, line))?;
+        let index = palette.push(rgb(r, g, b));
+        let name = line.split_whitespace().skip(3).collect::<Vec<_>>().join(" ");
+        if !name.is_empty() {
+            palette.set_name(index, name);
+        }
+    }
+    Ok(palette)
+}
+
+fn parse_paint_net(text: &str) -> Result<TextModePalette, TextModePaletteLoaderError> {
+    let mut palette = TextModePalette::default();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') {
+            continue;
+        }
+        let color = parse_hex(line)
+            .filter(|_| line.len() == 8)
+            .ok_or_else(|| invalid_color(i, line))?;
+        palette.push(color);
+    }
+    Ok(palette)
+}
+
+fn parse_jasc(text: &str) -> Result<TextModePalette, TextModePaletteLoaderError> {
+    let mut lines = text.lines().map(str::trim).enumerate();
+    if lines.next().map(|(_, l)| l) != Some("JASC-PAL") {
+        return Err(TextModePaletteLoaderError::MissingHeader("JASC-PAL"));
+    }
+    if lines.next().map(|(_, l)| l) != Some("0100") {
+        return Err(TextModePaletteLoaderError::MissingHeader("0100"));
+    }
+    let expected: usize = lines
+        .next()
+        .and_then(|(_, l)| l.parse().ok())
+        .ok_or(TextModePaletteLoaderError::MissingHeader("color count"))?;
+
+    let mut palette = TextModePalette::default();
+    for (i, line) in lines.filter(|(_, l)| !l.is_empty()) {
+        let [r, g, b] = parse_components(line, 3)
+            .map(|c| [c[0], c[1], c[2]])
+            .ok_or_else(|| invalid_color(i, line))?;
+        palette.push(rgb(r, g, b));
+    }
+    if palette.len() != expected {
+        return Err(TextModePaletteLoaderError::ColorCount {
+            expected,
+            found: palette.len(),
+        });
+    }
+    Ok(palette)
+}
+
+fn parse_lospec(bytes: &[u8]) -> Result<TextModePalette, TextModePaletteLoaderError> {
+    let image = image::load_from_memory_with_format(bytes, image::ImageFormat::Png)?.to_rgba8();
+
+    let mut colors: Vec<[u8; 4]> = Vec::new();
+    for pixel in image.pixels() {
+        if pixel.0[3] != 0 && !colors.contains(&pixel.0) {
+            colors.push(pixel.0);
+        }
+    }
+    Ok(TextModePalette::new(
+        colors.into_iter().map(|[r, g, b, a]| Srgba::rgba_u8(r, g, b, a).into()),
+    ))
+}
+
+fn parse_hex_list(text: &str) -> Result<TextModePalette, TextModePaletteLoaderError> {
+    let mut palette = TextModePalette::default();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let color = parse_hex(line)
+            .filter(|_| line.trim_start_matches('#').len() == 6)
+            .ok_or_else(|| invalid_color(i, line))?;
+        palette.push(color);
+    }
+    Ok(palette)
+}
+
+/// Loads GIMP `.gpl` palettes, color names are kept as palette slot names
+#[derive(Default)]
+pub struct GplPaletteLoader;
+
+impl AssetLoader for GplPaletteLoader {
+    type Asset = TextModePalette;
+    type Settings = ();
+    type Error = TextModePaletteLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<TextModePalette, Self::Error> {
+        parse_gpl(&read_text(reader).await?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["gpl"]
+    }
+}
+
+/// Loads Paint.NET palettes (one `AARRGGBB` color per line).
+///
+/// Paint.NET saves palettes as `.txt` files, they have to be renamed to `.paintnet.txt`
+/// to avoid taking over the other `.txt` assets of the app.
+#[derive(Default)]
+pub struct PaintNetPaletteLoader;
+
+impl AssetLoader for PaintNetPaletteLoader {
+    type Asset = TextModePalette;
+    type Settings = ();
+    type Error = TextModePaletteLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<TextModePalette, Self::Error> {
+        parse_paint_net(&read_text(reader).await?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["paintnet.txt"]
+    }
+}
+
+/// Loads JASC `.pal` palettes
+#[derive(Default)]
+pub struct JascPaletteLoader;
+
+impl AssetLoader for JascPaletteLoader {
+    type Asset = TextModePalette;
+    type Settings = ();
+    type Error = TextModePaletteLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<TextModePalette, Self::Error> {
+        parse_jasc(&read_text(reader).await?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["pal"]
+    }
+}
+
+/// Loads `.hex` palettes (one `RRGGBB` color per line)
+#[derive(Default)]
+pub struct HexPaletteLoader;
+
+impl AssetLoader for HexPaletteLoader {
+    type Asset = TextModePalette;
+    type Settings = ();
+    type Error = TextModePaletteLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<TextModePalette, Self::Error> {
+        parse_hex_list(&read_text(reader).await?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["hex"]
+    }
+}
+
+/// Loads Lospec PNG palette strips, at any scale.
+///
+/// Colors are read left to right, top to bottom, and each distinct opaque color is added once.
+/// The `.palette.png` extension is used to avoid taking over regular `.png` images.
+#[derive(Default)]
+pub struct LospecPaletteLoader;
+
+impl AssetLoader for LospecPaletteLoader {
+    type Asset = TextModePalette;
+    type Settings = ();
+    type Error = TextModePaletteLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<TextModePalette, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        parse_lospec(&bytes)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["palette.png"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_colors(palette: &TextModePalette, colors: &[LinearRgba]) {
+        assert_eq!(palette.colors(), colors);
+    }
+
+    #[test]
+    fn hex_colors() {
+        assert_eq!(parse_hex("ff8000"), Some(rgb(255, 128, 0)));
+        assert_eq!(parse_hex("#ff8000"), Some(rgb(255, 128, 0)));
+        assert_eq!(parse_hex("80ff8000"), Some(Srgba::rgba_u8(255, 128, 0, 128).into()));
+        assert_eq!(parse_hex("+ff800"), None);
+        assert_eq!(parse_hex("-ff800"), None);
+        assert_eq!(parse_hex(" ff800"), None);
+        assert_eq!(parse_hex("ff80"), None);
+        assert_eq!(parse_hex("gg8000"), None);
+        assert_eq!(parse_hex(""), None);
+    }
+
+    #[test]
+    fn gpl() {
+        let palette = parse_gpl(
+            "GIMP Palette\nName: Test\nColumns: 2\n# comment\n\n  0   0   0 Black\n255 128   0\n 34  32  52 Dark Blue\n",
+        )
+        .unwrap();
+        assert_colors(&palette, &[rgb(0, 0, 0), rgb(255, 128, 0), rgb(34, 32, 52)]);
+        assert_eq!(palette.index_of("Black"), Some(0));
+        assert_eq!(palette.index_of("Dark Blue"), Some(2));
+    }
+
+    #[test]
+    fn gpl_errors() {
+        assert!(matches!(
+            parse_gpl("Palette\n0 0 0\n"),
+            Err(TextModePaletteLoaderError::MissingHeader("GIMP Palette"))
+        ));
+        assert!(matches!(
+            parse_gpl("GIMP Palette\n0 0\n"),
+            Err(TextModePaletteLoaderError::InvalidColor { line: 2, .. })
+        ));
+        assert!(matches!(
+            parse_gpl("GIMP Palette\n0 0 256\n"),
+            Err(TextModePaletteLoaderError::InvalidColor { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn paint_net() {
+        let palette = parse_paint_net("; paint.net Palette File\n;Colors: 2\nFFFF8000\n80000000\n").unwrap();
+        assert_colors(&palette, &[rgb(255, 128, 0), Srgba::rgba_u8(0, 0, 0, 128).into()]);
+    }
+
+    #[test]
+    fn paint_net_errors() {
+        // Paint.NET colors always have an alpha channel
+        assert!(matches!(
+            parse_paint_net("FF8000\n"),
+            Err(TextModePaletteLoaderError::InvalidColor { line: 1, .. })
+        ));
+        assert!(matches!(
+            parse_paint_net(";\n+FF80000\n"),
+            Err(TextModePaletteLoaderError::InvalidColor { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn jasc() {
+        let palette = parse_jasc("JASC-PAL\r\n0100\r\n2\r\n0 0 0\r\n255 128 0\r\n").unwrap();
+        assert_colors(&palette, &[rgb(0, 0, 0), rgb(255, 128, 0)]);
+    }
+
+    #[test]
+    fn jasc_errors() {
+        assert!(matches!(
+            parse_jasc("0100\n1\n0 0 0\n"),
+            Err(TextModePaletteLoaderError::MissingHeader("JASC-PAL"))
+        ));
+        assert!(matches!(
+            parse_jasc("JASC-PAL\n0200\n1\n0 0 0\n"),
+            Err(TextModePaletteLoaderError::MissingHeader("0100"))
+        ));
+        assert!(matches!(
+            parse_jasc("JASC-PAL\n0100\nmany\n0 0 0\n"),
+            Err(TextModePaletteLoaderError::MissingHeader("color count"))
+        ));
+        assert!(matches!(
+            parse_jasc("JASC-PAL\n0100\n2\n0 0 0\n"),
+            Err(TextModePaletteLoaderError::ColorCount { expected: 2, found: 1 })
+        ));
+        assert!(matches!(
+            parse_jasc("JASC-PAL\n0100\n1\n0 x 0\n"),
+            Err(TextModePaletteLoaderError::InvalidColor { line: 4, .. })
+        ));
+    }
+
+    #[test]
+    fn hex_list() {
+        let palette = parse_hex_list("000000\n\n#FF8000\n").unwrap();
+        assert_colors(&palette, &[rgb(0, 0, 0), rgb(255, 128, 0)]);
+    }
+
+    #[test]
+    fn hex_list_errors() {
+        // Alpha is not part of the format
+        assert!(matches!(
+            parse_hex_list("000000\nFFFF8000\n"),
+            Err(TextModePaletteLoaderError::InvalidColor { line: 2, .. })
+        ));
+        assert!(matches!(
+            parse_hex_list("+00000\n"),
+            Err(TextModePaletteLoaderError::InvalidColor { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn lospec() {
+        // A 2x scaled strip of 3 colors, with a repeated color and a transparent pixel
+        let mut strip = image::RgbaImage::new(8, 2);
+        for (x, _, pixel) in strip.enumerate_pixels_mut() {
+            pixel.0 = match x / 2 {
+                0 => [0, 0, 0, 255],
+                1 => [255, 128, 0, 255],
+                2 => [0, 0, 0, 255],
+                _ => [255, 255, 255, 0],
+            };
+        }
+        let mut bytes = Vec::new();
+        strip
+            .write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageFormat::Png)
+            .unwrap();
+        assert_colors(&parse_lospec(&bytes).unwrap(), &[rgb(0, 0, 0), rgb(255, 128, 0)]);
+
+        assert!(matches!(parse_lospec(b"not a png"), Err(TextModePaletteLoaderError::Image(_))));
+    }
+}
@@ -0,0 +1,165 @@
+use bevy::prelude::*;
+
+use crate::CP437;
+
+const SAUCE_LEN: usize = 128;
+const COMMENT_LINE_LEN: usize = 64;
+
+/// SAUCE metadata record found at the end of text mode art files
+#[derive(Debug, Clone, Default, PartialEq, Reflect)]
+pub struct Sauce {
+    pub title: String,
+    pub author: String,
+    pub group: String,
+    /// Creation date, `CCYYMMDD`
+    pub date: String,
+    pub data_type: u8,
+    pub file_type: u8,
+    /// Type dependent numeric information, e.g. width and height in characters for character files
+    pub t_info: [u16; 4],
+    /// Type dependent flags, e.g. iCE colors for character files
+    pub t_flags: u8,
+    /// Type dependent string, e.g. font name for character files
+    pub t_info_s: String,
+    pub comments: Vec<String>,
+}
+
+impl Sauce {
+    /// Splits `bytes` into the file contents and its SAUCE record, if any.
+    /// The comment block and the EOF character preceding the metadata are removed from the contents.
+    pub fn split(bytes: &[u8]) -> (&[u8], Option<Sauce>) {
+        let Some(record_start) = bytes.len().checked_sub(SAUCE_LEN) else {
+            return (bytes, None);
+        };
+        let record = &bytes[record_start..];
+        if &record[0..5] != b"SAUCE" {
+            return (bytes, None);
+        }
+
+        let u16_at = |i: usize| u16::from_le_bytes([record[i], record[i + 1]]);
+        let mut sauce = Sauce {
+            title: cp437_string(&record[7..42]),
+            author: cp437_string(&record[42..62]),
+            group: cp437_string(&record[62..82]),
+            date: cp437_string(&record[82..90]),
+            data_type: record[94],
+            file_type: record[95],
+            t_info: [u16_at(96), u16_at(98), u16_at(100), u16_at(102)],
+            t_flags: record[105],
+            t_info_s: cp437_string(&record[106..128]),
+            comments: Vec::new(),
+        };
+
+        // The optional comment block precedes the record
+        let mut end = record_start;
+        let comment_count = record[104] as usize;
+        let comment_start = record_start.checked_sub(5 + comment_count * COMMENT_LINE_LEN);
+        if let Some(start) = comment_start.filter(|&s| comment_count > 0 && &bytes[s..s + 5] == b"COMNT") {
+            sauce.comments = bytes[start + 5..record_start]
+                .chunks(COMMENT_LINE_LEN)
+                .map(cp437_string)
+                .collect();
+            end = start;
+        }
+
+        // Only the EOF character right before the metadata is removed, binary contents may contain others
+        let contents = &bytes[..end];
+        let contents = contents.strip_suffix(&[0x1A]).unwrap_or(contents);
+        (contents, Some(sauce))
+    }
+
+    /// Whether high intensity backgrounds replace blinking (character and binary files)
+    pub fn ice_colors(&self) -> bool {
+        self.t_flags & 1 != 0
+    }
+
+    /// Font name of character and binary files, e.g. `IBM VGA`
+    pub fn font(&self) -> Option<&str> {
+        (!self.t_info_s.is_empty()).then_some(self.t_info_s.as_str())
+    }
+}
+
+/// Decodes a space or NUL padded code page 437 string
+fn cp437_string(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&b| CP437[b as usize])
+        .collect::<String>()
+        .trim_end_matches([' ', '\0'])
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// SAUCE record of a 80 columns character file titled `Title`, with `comments` comment lines
+    fn record(comments: u8) -> Vec<u8> {
+        let mut record = vec![0; SAUCE_LEN];
+        record[0..7].copy_from_slice(b"SAUCE00");
+        // Strings are space padded
+        record[7..90].fill(b' ');
+        record[7..12].copy_from_slice(b"Title");
+        record[94] = 1;
+        record[95] = 1;
+        record[96..98].copy_from_slice(&80u16.to_le_bytes());
+        record[98..100].copy_from_slice(&25u16.to_le_bytes());
+        record[104] = comments;
+        record[105] = 1;
+        record
+    }
+
+    #[test]
+    fn no_sauce() {
+        let bytes = b"hello\x1Aworld";
+        assert_eq!(Sauce::split(bytes), (&bytes[..], None));
+        assert_eq!(Sauce::split(b"short"), (&b"short"[..], None));
+    }
+
+    #[test]
+    fn with_eof() {
+        let bytes = [b"hello\x1A".as_slice(), &record(0)].concat();
+        let (contents, sauce) = Sauce::split(&bytes);
+        let sauce = sauce.unwrap();
+        assert_eq!(contents, b"hello");
+        assert_eq!(sauce.title, "Title");
+        assert_eq!(sauce.author, "");
+        assert_eq!(sauce.t_info, [80, 25, 0, 0]);
+        assert!(sauce.ice_colors());
+        assert_eq!(sauce.font(), None);
+        assert!(sauce.comments.is_empty());
+    }
+
+    #[test]
+    fn without_eof() {
+        let bytes = [b"hello".as_slice(), &record(0)].concat();
+        let (contents, sauce) = Sauce::split(&bytes);
+        assert_eq!(contents, b"hello");
+        assert!(sauce.is_some());
+    }
+
+    #[test]
+    fn with_comments() {
+        let mut comments = b"COMNT".to_vec();
+        let mut line = [b' '; COMMENT_LINE_LEN];
+        line[..5].copy_from_slice(b"first");
+        comments.extend_from_slice(&line);
+        line[..6].copy_from_slice(b"second");
+        comments.extend_from_slice(&line);
+        let bytes = [b"hello\x1A".as_slice(), &comments, &record(2)].concat();
+        let (contents, sauce) = Sauce::split(&bytes);
+        assert_eq!(contents, b"hello");
+        assert_eq!(sauce.unwrap().comments, ["first", "second"]);
+    }
+
+    #[test]
+    fn binary_contents_with_eof_bytes() {
+        // `0x1A` is an ordinary glyph or attribute byte in binary files
+        let data = [0x41, 0x1A, 0x1A, 0x07, 0x1A, 0x0F];
+        let bytes = [data.as_slice(), &record(0)].concat();
+        assert_eq!(Sauce::split(&bytes).0, data);
+
+        let bytes = [data.as_slice(), &[0x1A], &record(0)].concat();
+        assert_eq!(Sauce::split(&bytes).0, data);
+    }
+}
@@ -11,8 +11,113 @@
 
 @group(0) @binding(1) var dt_lut_texture: texture_3d<f32>;
 @group(0) @binding(2) var dt_lut_sampler: sampler;
+// Blink and cursor blink phases, the off phase starting at `0.5`
+@group(0) @binding(3) var<uniform> blink_phases: vec2<f32>;
 
+// Instance flags, see `TextModeSpriteInstanceFlags`
+const PALETTE_LEN_BITS: u32 = 7u;
+const MASK_SHIFT_BITS: u32 = 3u;
+const MASK_BITS: u32 = 3u;
+const MASK_LUMINANCE: u32 = 1u;
+const MASK_ALPHA: u32 = 2u;
+const MASK_KEY: u32 = 3u;
+const INVERT_MASK_BIT: u32 = 32u;
+const SKIP_BG_BIT: u32 = 64u;
+const SKIP_FG_BIT: u32 = 128u;
+const COVERAGE_BIT: u32 = 256u;
+const BLINK_SHIFT_BITS: u32 = 9u;
+const BLINK_BITS: u32 = 3u;
+const BLINK_FG: u32 = 1u;
+const BLINK_SWAP: u32 = 2u;
+const BLINK_HIDE: u32 = 3u;
+const CURSOR_SHIFT_BITS: u32 = 11u;
+const CURSOR_BITS: u32 = 3u;
+const CURSOR_BLOCK: u32 = 1u;
+const CURSOR_UNDERLINE: u32 = 2u;
+const CURSOR_BAR: u32 = 3u;
+const CURSOR_BLINK_BIT: u32 = 8192u;
+const FLIP_X_BIT: u32 = 16384u;
+const FLIP_Y_BIT: u32 = 32768u;
+const ROTATION_SHIFT_BITS: u32 = 16u;
+const ROTATION_BITS: u32 = 3u;
+// Thickness of the underline and bar cursors, relative to the cell size
+const CURSOR_THICKNESS: f32 = 0.125;
+// Billboard modes, see `TextModeBillboard`
+const BILLBOARD_SPHERICAL: u32 = 1u;
+const BILLBOARD_CYLINDRICAL: u32 = 2u;
+// Glyphs per row of the glyph tables, see `TextModeGpuAtlasLayouts`
+const GLYPH_TABLE_WIDTH: u32 = 256u;
 
+#ifdef GPU_DRIVEN_GRID
+// See `TextModeGridUniform`
+struct Grid {
+    world_from_local: mat4x4<f32>,
+    // Top left corner of the first cell
+    origin: vec2<f32>,
+    cell_size: vec2<f32>,
+    mask: vec4<f32>,
+    width: u32,
+    first_cell: u32,
+    alpha: f32,
+    // Billboard mode, `world_from_local` having no rotation
+    billboard: u32,
+}
+
+// See `TextModePackedCell`, colors are sRGB encoded
+struct Cell {
+    glyph: u32,
+    fg: u32,
+    bg: u32,
+    flags: u32,
+}
+
+@group(2) @binding(0) var<uniform> grid: Grid;
+@group(2) @binding(1) var<storage> cells: array<Cell>;
+
+// Cells are read by instance index, row by row from the top left cell
+struct VertexInput {
+    @builtin(vertex_index) index: u32,
+    @builtin(instance_index) instance: u32,
+}
+#else
+// See `TextModeBatchUniform`, shared by the instances of a batch
+struct Batch {
+    palette_2: vec4<f32>,
+    palette_3: vec4<f32>,
+    mask: vec4<f32>,
+    // Billboards turn around the pivot, their model transforms having no rotation
+    pivot: vec3<f32>,
+    billboard: u32,
+}
+
+@group(2) @binding(0) var<uniform> batch: Batch;
+
+#ifdef COMPACT_INSTANCES
+// See `TextModeCompactSpriteInstance`, colors are sRGB encoded with the sprite alpha folded in
+struct VertexInput {
+    @builtin(vertex_index) index: u32,
+    // Columns of the 2D linear part of the model transform
+    @location(0) i_model: vec4<f32>,
+    @location(1) i_translation: vec3<f32>,
+    @location(2) i_glyph: u32,
+    @location(3) i_bg: vec4<f32>,
+    @location(4) i_fg: vec4<f32>,
+    @location(5) i_flags: u32,
+}
+#else ifdef CUSTOM_RECTS
+// See `TextModeRectSpriteInstance`, colors have the sprite alpha folded in
+struct VertexInput {
+    @builtin(vertex_index) index: u32,
+    @location(0) i_model_transpose_col0: vec4<f32>,
+    @location(1) i_model_transpose_col1: vec4<f32>,
+    @location(2) i_model_transpose_col2: vec4<f32>,
+    @location(3) i_bg: vec4<f32>,
+    @location(4) i_fg: vec4<f32>,
+    @location(5) i_uv_offset_scale: vec4<f32>,
+    @location(6) i_flags: u32,
+}
+#else
+// See `TextModeSpriteInstance`, colors have the sprite alpha folded in
 struct VertexInput {
     @builtin(vertex_index) index: u32,
     @location(0) i_model_transpose_col0: vec4<f32>,
@@ -20,21 +125,84 @@ struct VertexInput {
     @location(2) i_model_transpose_col2: vec4<f32>,
     @location(3) i_bg: vec4<f32>,
     @location(4) i_fg: vec4<f32>,
-    @location(5) i_alpha: f32,
-    @location(6) i_uv_offset_scale: vec4<f32>,
+    @location(5) i_glyph: u32,
+    @location(6) i_flags: u32,
+}
+#endif
+#endif
+
+#ifndef CUSTOM_RECTS
+// UV offset and scale of the glyphs of the atlas layout, `GLYPH_TABLE_WIDTH` per row
+@group(3) @binding(0) var glyph_table: texture_2d<f32>;
+
+// UV offset and scale of a glyph, flipped by the instance flags, zero for glyphs missing from the atlas layout
+fn glyph_uv_offset_scale(glyph: u32, flags: u32) -> vec4<f32> {
+    let texel = vec2<u32>(glyph % GLYPH_TABLE_WIDTH, glyph / GLYPH_TABLE_WIDTH);
+    if (texel.y >= textureDimensions(glyph_table).y) {
+        return vec4<f32>(0.0);
+    }
+    var uv_offset_scale = textureLoad(glyph_table, texel, 0);
+    if ((flags & FLIP_X_BIT) != 0u) {
+        uv_offset_scale.x += uv_offset_scale.z;
+        uv_offset_scale.z *= -1.0;
+    }
+    if ((flags & FLIP_Y_BIT) != 0u) {
+        uv_offset_scale.y += uv_offset_scale.w;
+        uv_offset_scale.w *= -1.0;
+    }
+    return uv_offset_scale;
 }
+#endif
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) uv: vec2<f32>,
     @location(1) @interpolate(flat) bg: vec4<f32>,
     @location(2) @interpolate(flat) fg: vec4<f32>,
-    @location(3) alpha: f32,
+    @location(3) @interpolate(flat) flags: u32,
+    @location(4) @interpolate(flat) mask: vec4<f32>,
+    // Position in the quad, `(0, 0)` being the bottom left corner
+    @location(5) local: vec2<f32>,
 };
 
+fn srgb_to_linear(color: vec4<f32>) -> vec4<f32> {
+    let low = color.rgb / 12.92;
+    let high = pow((color.rgb + 0.055) / 1.055, vec3<f32>(2.4));
+    return vec4<f32>(select(high, low, color.rgb <= vec3<f32>(0.04045)), color.a);
+}
+
+// Turns a world position of a billboard around `pivot` to face the view
+fn billboard(position: vec3<f32>, pivot: vec3<f32>, mode: u32) -> vec3<f32> {
+    var rotation: mat3x3<f32>;
+    switch (mode) {
+        case BILLBOARD_SPHERICAL: {
+            rotation = mat3x3<f32>(
+                normalize(view.world_from_view[0].xyz),
+                normalize(view.world_from_view[1].xyz),
+                normalize(view.world_from_view[2].xyz),
+            );
+        }
+        case BILLBOARD_CYLINDRICAL: {
+            // Yaw of the view, staying upright
+            let back = view.world_from_view[2].xyz;
+            let yaw = atan2(back.x, back.z);
+            rotation = mat3x3<f32>(
+                vec3<f32>(cos(yaw), 0.0, -sin(yaw)),
+                vec3<f32>(0.0, 1.0, 0.0),
+                vec3<f32>(sin(yaw), 0.0, cos(yaw)),
+            );
+        }
+        default: {
+            return position;
+        }
+    }
+    return pivot + rotation * (position - pivot);
+}
+
 @vertex
 fn vertex(in: VertexInput) -> VertexOutput {
     var out: VertexOutput;
+    var uv_offset_scale: vec4<f32>;
 
     let vertex_position = vec3<f32>(
         f32(in.index & 0x1u),
@@ -42,15 +210,68 @@ fn vertex(in: VertexInput) -> VertexOutput {
         0.0
     );
 
-    out.clip_position = view.clip_from_world * affine3_to_square(mat3x4<f32>(
+#ifdef GPU_DRIVEN_GRID
+    let cell = cells[in.instance];
+    let i = in.instance - grid.first_cell;
+    let cell_position = vec2<f32>(f32(i % grid.width), -f32(i / grid.width + 1u)) * grid.cell_size;
+
+    // Rotated by quarter turns around the cell center
+    let center = grid.cell_size * 0.5;
+    var corner = vertex_position.xy * grid.cell_size - center;
+    switch ((cell.flags >> ROTATION_SHIFT_BITS) & ROTATION_BITS) {
+        case 1u: {
+            corner = vec2<f32>(-corner.y, corner.x);
+        }
+        case 2u: {
+            corner = -corner;
+        }
+        case 3u: {
+            corner = vec2<f32>(corner.y, -corner.x);
+        }
+        default: {}
+    }
+    let local_position = grid.origin + cell_position + center + corner;
+    let world_position = (grid.world_from_local * vec4<f32>(local_position, 0.0, 1.0)).xyz;
+    let pivot = grid.world_from_local[3].xyz;
+    out.clip_position = view.clip_from_world * vec4<f32>(billboard(world_position, pivot, grid.billboard), 1.0);
+    uv_offset_scale = glyph_uv_offset_scale(cell.glyph, cell.flags);
+    let alpha = vec4<f32>(1.0, 1.0, 1.0, grid.alpha);
+    out.bg = srgb_to_linear(unpack4x8unorm(cell.bg)) * alpha;
+    out.fg = srgb_to_linear(unpack4x8unorm(cell.fg)) * alpha;
+    out.mask = grid.mask;
+    out.flags = cell.flags;
+#else ifdef COMPACT_INSTANCES
+    let world_position = in.i_model.xy * vertex_position.x + in.i_model.zw * vertex_position.y + in.i_translation.xy;
+    out.clip_position = view.clip_from_world * vec4<f32>(world_position, in.i_translation.z, 1.0);
+    out.bg = srgb_to_linear(in.i_bg);
+    out.fg = srgb_to_linear(in.i_fg);
+#else
+    let world_position = (affine3_to_square(mat3x4<f32>(
         in.i_model_transpose_col0,
         in.i_model_transpose_col1,
         in.i_model_transpose_col2,
-    )) * vec4<f32>(vertex_position, 1.0);
-    out.uv = vec2<f32>(vertex_position.xy) * in.i_uv_offset_scale.zw + in.i_uv_offset_scale.xy;
+    )) * vec4<f32>(vertex_position, 1.0)).xyz;
+    out.clip_position = view.clip_from_world * vec4<f32>(billboard(world_position, batch.pivot, batch.billboard), 1.0);
     out.bg = in.i_bg;
     out.fg = in.i_fg;
-    out.alpha = in.i_alpha;
+#endif
+#ifndef GPU_DRIVEN_GRID
+    out.mask = batch.mask;
+    out.flags = in.i_flags;
+#ifdef CUSTOM_RECTS
+    uv_offset_scale = in.i_uv_offset_scale;
+#else
+    uv_offset_scale = glyph_uv_offset_scale(in.i_glyph, in.i_flags);
+#endif
+#endif
+#ifndef CUSTOM_RECTS
+    if (uv_offset_scale.z == 0.0) {
+        // Glyphs missing from the atlas are not drawn, the quad collapses to a point
+        out.clip_position = vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+#endif
+    out.uv = vertex_position.xy * uv_offset_scale.zw + uv_offset_scale.xy;
+    out.local = vertex_position.xy;
 
     return out;
 }
@@ -58,16 +279,141 @@ fn vertex(in: VertexInput) -> VertexOutput {
 @group(1) @binding(0) var sprite_texture: texture_2d<f32>;
 @group(1) @binding(1) var sprite_sampler: sampler;
 
+// Value of the texture pixel for the mask rule, `1.0` being the foreground
+fn mask_value(color: vec4<f32>, flags: u32, mask: vec4<f32>) -> f32 {
+    switch ((flags >> MASK_SHIFT_BITS) & MASK_BITS) {
+        case MASK_LUMINANCE: {
+            return dot(color.rgb, vec3<f32>(0.2126, 0.7152, 0.0722));
+        }
+        case MASK_ALPHA: {
+            return color.a;
+        }
+        case MASK_KEY: {
+            return select(1.0, 0.0, all(abs(color - mask) < vec4<f32>(0.002)));
+        }
+        default: {
+            return color.r;
+        }
+    }
+}
+
+fn is_foreground(color: vec4<f32>, flags: u32, mask: vec4<f32>) -> bool {
+    var threshold = mask.x;
+    switch ((flags >> MASK_SHIFT_BITS) & MASK_BITS) {
+        case MASK_LUMINANCE, MASK_ALPHA: {}
+        case MASK_KEY: {
+            threshold = 0.5;
+        }
+        default: {
+            threshold = 0.0;
+        }
+    }
+    return (mask_value(color, flags, mask) > threshold) != ((flags & INVERT_MASK_BIT) != 0u);
+}
+
 @fragment
 fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
     var color = textureSample(sprite_texture, sprite_sampler, in.uv);
 
-    if (color[0] == 0.0) {
-        color = in.bg;
-        color[3] = in.alpha * in.bg[3];
+    var flags = in.flags;
+    var bg = in.bg;
+    var fg = in.fg;
+#ifdef INDEXED_PALETTE
+    var palette_2 = batch.palette_2;
+    var palette_3 = batch.palette_3;
+#endif
+    if (blink_phases.x >= 0.5) {
+        switch ((flags >> BLINK_SHIFT_BITS) & BLINK_BITS) {
+            case BLINK_FG: {
+                // Foreground pixels look like background pixels
+                fg = bg;
+#ifdef INDEXED_PALETTE
+                palette_2 = bg;
+                palette_3 = bg;
+#endif
+                if ((flags & SKIP_BG_BIT) != 0u) {
+                    flags |= SKIP_FG_BIT;
+                }
+            }
+            case BLINK_SWAP: {
+                bg = in.fg;
+                fg = in.bg;
+            }
+            case BLINK_HIDE: {
+                discard;
+            }
+            default: {}
+        }
+    }
+
+    let cursor_shape = (flags >> CURSOR_SHIFT_BITS) & CURSOR_BITS;
+    let cursor_on = cursor_shape != 0u && ((flags & CURSOR_BLINK_BIT) == 0u || blink_phases.y < 0.5);
+    var cursor_line = false;
+    if (cursor_on) {
+        switch (cursor_shape) {
+            case CURSOR_BLOCK: {
+                let cursor_bg = bg;
+                bg = fg;
+                fg = cursor_bg;
+            }
+            case CURSOR_UNDERLINE: {
+                cursor_line = in.local.y < CURSOR_THICKNESS;
+            }
+            default: {
+                cursor_line = in.local.x < CURSOR_THICKNESS;
+            }
+        }
+    }
+
+    if (cursor_line) {
+        color = fg;
     } else {
-        color = in.fg;
-        color[3] = in.alpha * in.fg[3];
+#ifdef INDEXED_PALETTE
+        // The mask value is quantized to one level per palette color
+        var value = mask_value(color, flags, in.mask);
+        if ((flags & INVERT_MASK_BIT) != 0u) {
+            value = 1.0 - value;
+        }
+        var palette = array<vec4<f32>, 4>(bg, fg, palette_2, palette_3);
+        let palette_len = max(flags & PALETTE_LEN_BITS, 1u);
+        let level = u32(round(clamp(value, 0.0, 1.0) * f32(palette_len - 1u)));
+        // The first palette color is the background
+        let skip_bit = select(SKIP_FG_BIT, SKIP_BG_BIT, level == 0u);
+        if ((flags & skip_bit) != 0u) {
+            discard;
+        }
+        color = palette[min(level, 3u)];
+#else
+        if ((flags & COVERAGE_BIT) != 0u) {
+            // bg and fg are mixed by the mask value
+            var value = clamp(mask_value(color, flags, in.mask), 0.0, 1.0);
+            if ((flags & INVERT_MASK_BIT) != 0u) {
+                value = 1.0 - value;
+            }
+            var coverage_bg = bg;
+            var coverage_fg = fg;
+            if ((flags & SKIP_BG_BIT) != 0u) {
+                coverage_bg = vec4<f32>(fg.rgb, 0.0);
+            }
+            if ((flags & SKIP_FG_BIT) != 0u) {
+                coverage_fg = vec4<f32>(bg.rgb, 0.0);
+            }
+            color = mix(coverage_bg, coverage_fg, value);
+            if (color[3] == 0.0) {
+                discard;
+            }
+        } else if (!is_foreground(color, flags, in.mask)) {
+            if ((flags & SKIP_BG_BIT) != 0u) {
+                discard;
+            }
+            color = bg;
+        } else {
+            if ((flags & SKIP_FG_BIT) != 0u) {
+                discard;
+            }
+            color = fg;
+        }
+#endif
     }
 
     #ifdef TONEMAP_IN_SHADER
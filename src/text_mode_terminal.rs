@@ -0,0 +1,613 @@
+use std::fmt;
+
+use bevy::prelude::*;
+
+use crate::text_mode_ansi::{ansi_color, ansi_param, AnsiAction, AnsiAttributes, AnsiParser};
+use crate::{Charset, TextModeCell, TextModeCharset, TextModeCursor, TextModeCursorShape, TextModeGrid, TextModeGridBundle};
+
+const TAB_WIDTH: usize = 8;
+
+/// A terminal interpreting ANSI / VT100 byte streams into the [`TextModeGrid`] of its entity.
+///
+/// Bytes queued with [`TextModeTerminal::write`], or with the `write!` macro, are applied to the grid
+/// by [`TextModeTerminal::flush`] during [`PostUpdate`]. The grid is cleared by the first flush.
+///
+/// Supported sequences are cursor movement, SGR colors, erasing, insertion and deletion of lines
+/// and characters, and scroll regions. `'\n'` also returns to the first column.
+/// Text is decoded as UTF-8 and mapped to glyphs with `charset`. The grid cursor follows the terminal cursor.
+#[derive(Component, Debug, Clone)]
+pub struct TextModeTerminal {
+    input: Vec<u8>,
+    parser: AnsiParser,
+    utf8: Vec<u8>,
+    cleared: bool,
+    cursor: (usize, usize),
+    wrap_pending: bool,
+    attributes: AnsiAttributes,
+    saved: ((usize, usize), AnsiAttributes),
+    /// Inclusive top and bottom rows, `None` for the whole grid
+    scroll_region: Option<(usize, usize)>,
+    cursor_visible: bool,
+    /// Maps characters to glyph indices, defaults to code page 437
+    pub charset: TextModeCharset,
+    pub default_bg: LinearRgba,
+    pub default_fg: LinearRgba,
+    /// Uses high intensity backgrounds instead of blinking
+    pub ice_colors: bool,
+    /// Shape of the grid cursor, set by `ESC [ n SP q`
+    pub cursor_shape: TextModeCursorShape,
+    pub cursor_blink: bool,
+}
+
+impl Default for TextModeTerminal {
+    fn default() -> Self {
+        Self {
+            input: Vec::new(),
+            parser: AnsiParser::default(),
+            utf8: Vec::new(),
+            cleared: false,
+            cursor: (0, 0),
+            wrap_pending: false,
+            attributes: AnsiAttributes::default(),
+            saved: ((0, 0), AnsiAttributes::default()),
+            scroll_region: None,
+            cursor_visible: true,
+            charset: TextModeCharset::cp437(),
+            default_bg: ansi_color(0),
+            default_fg: ansi_color(7),
+            ice_colors: false,
+            cursor_shape: TextModeCursorShape::Block,
+            cursor_blink: true,
+        }
+    }
+}
+
+impl fmt::Write for TextModeTerminal {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write(s);
+        Ok(())
+    }
+}
+
+impl TextModeTerminal {
+    /// Queues bytes to be applied to the grid by the next flush
+    pub fn write(&mut self, bytes: impl AsRef<[u8]>) {
+        self.input.extend_from_slice(bytes.as_ref());
+    }
+
+    /// Whether bytes are waiting for the next flush
+    pub fn has_pending_input(&self) -> bool {
+        !self.input.is_empty()
+    }
+
+    /// Cursor position, `(0, 0)` being the top left cell
+    pub fn cursor(&self) -> (usize, usize) {
+        self.cursor
+    }
+
+    /// Whether the cursor is shown, toggled by `ESC [ ? 25 h` and `ESC [ ? 25 l`
+    pub fn cursor_visible(&self) -> bool {
+        self.cursor_visible
+    }
+
+    /// Current SGR attributes
+    pub fn attributes(&self) -> &AnsiAttributes {
+        &self.attributes
+    }
+
+    /// Applies the queued bytes to `grid`
+    pub fn flush(&mut self, grid: &mut TextModeGrid) {
+        if grid.width() == 0 || grid.height() == 0 {
+            return;
+        }
+        if !self.cleared {
+            grid.fill(self.blank());
+            self.cleared = true;
+        }
+        self.clamp_cursor(grid);
+
+        let input = std::mem::take(&mut self.input);
+        for byte in input {
+            match self.parser.advance(byte) {
+                Some(AnsiAction::Print(b)) => self.decode(grid, b),
+                Some(AnsiAction::Control(b)) => self.control(grid, b),
+                Some(AnsiAction::Csi {
+                    params,
+                    private: None,
+                    intermediate: None,
+                    action,
+                }) => self.csi(grid, &params, action),
+                Some(AnsiAction::Csi {
+                    params,
+                    private: Some(b'?'),
+                    action: action @ (b'h' | b'l'),
+                    ..
+                }) if params.contains(&25) => self.cursor_visible = action == b'h',
+                Some(AnsiAction::Csi {
+                    params,
+                    private: None,
+                    intermediate: Some(b' '),
+                    action: b'q',
+                }) => {
+                    let style = params.first().copied().unwrap_or(0);
+                    self.cursor_shape = match style {
+                        3 | 4 => TextModeCursorShape::Underline,
+                        5 | 6 => TextModeCursorShape::Bar,
+                        _ => TextModeCursorShape::Block,
+                    };
+                    self.cursor_blink = style == 0 || style % 2 == 1;
+                }
+                Some(AnsiAction::Esc {
+                    intermediate: None,
+                    action,
+                }) => self.esc(grid, action),
+                _ => {}
+            }
+        }
+
+        grid.cursor = self.cursor_visible.then_some(TextModeCursor {
+            x: self.cursor.0,
+            y: self.cursor.1,
+            shape: self.cursor_shape,
+            blink: self.cursor_blink,
+        });
+    }
+
+    /// A space with the current background color
+    fn blank(&self) -> TextModeCell {
+        let (bg, fg) = self.attributes.colors(self.default_bg, self.default_fg, self.ice_colors);
+        TextModeCell {
+            index: self.charset.index(' ').unwrap_or_default(),
+            bg,
+            fg,
+            blink: self.attributes.blink(self.ice_colors),
+            ..default()
+        }
+    }
+
+    fn region(&self, grid: &TextModeGrid) -> (usize, usize) {
+        // The region is dropped if the grid was resized below it
+        self.scroll_region
+            .filter(|&(_, bottom)| bottom < grid.height())
+            .unwrap_or((0, grid.height() - 1))
+    }
+
+    fn clamp_cursor(&mut self, grid: &TextModeGrid) {
+        self.cursor.0 = self.cursor.0.min(grid.width() - 1);
+        self.cursor.1 = self.cursor.1.min(grid.height() - 1);
+        self.wrap_pending = false;
+    }
+
+    fn decode(&mut self, grid: &mut TextModeGrid, byte: u8) {
+        if byte < 0x80 {
+            self.utf8.clear();
+            self.print(grid, byte as char);
+            return;
+        }
+        self.utf8.push(byte);
+        match std::str::from_utf8(&self.utf8) {
+            Ok(s) => {
+                let c = s.chars().next().unwrap_or_default();
+                self.utf8.clear();
+                self.print(grid, c);
+            }
+            Err(e) if e.error_len().is_some() => {
+                self.utf8.clear();
+                self.print(grid, char::REPLACEMENT_CHARACTER);
+            }
+            Err(_) => {}
+        }
+    }
+
+    fn print(&mut self, grid: &mut TextModeGrid, c: char) {
+        if self.wrap_pending {
+            self.cursor.0 = 0;
+            self.line_feed(grid);
+        }
+        let blank = self.blank();
+        let index = self.charset.index(c).unwrap_or(blank.index);
+        let (x, y) = self.cursor;
+        grid.set(x, y, TextModeCell { index, ..blank });
+        // Wrapping is deferred so that filling the last column does not scroll
+        if x + 1 < grid.width() {
+            self.cursor.0 += 1;
+        } else {
+            self.wrap_pending = true;
+        }
+    }
+
+    fn line_feed(&mut self, grid: &mut TextModeGrid) {
+        let (_, bottom) = self.region(grid);
+        if self.cursor.1 == bottom {
+            self.scroll_up(grid, 1);
+        } else if self.cursor.1 + 1 < grid.height() {
+            self.cursor.1 += 1;
+        }
+        self.wrap_pending = false;
+    }
+
+    fn reverse_line_feed(&mut self, grid: &mut TextModeGrid) {
+        let (top, _) = self.region(grid);
+        if self.cursor.1 == top {
+            self.scroll_down(grid, 1);
+        } else {
+            self.cursor.1 = self.cursor.1.saturating_sub(1);
+        }
+        self.wrap_pending = false;
+    }
+
+    /// Scrolls the scroll region up by `n` lines
+    fn scroll_up(&self, grid: &mut TextModeGrid, n: usize) {
+        let (top, bottom) = self.region(grid);
+        grid.scroll_region(top, bottom, n as isize, self.blank());
+    }
+
+    /// Scrolls the scroll region down by `n` lines
+    fn scroll_down(&self, grid: &mut TextModeGrid, n: usize) {
+        let (top, bottom) = self.region(grid);
+        grid.scroll_region(top, bottom, -(n as isize), self.blank());
+    }
+
+    /// Blanks the cells from `from` to `to` excluded, row by row
+    fn erase(&self, grid: &mut TextModeGrid, from: (usize, usize), to: (usize, usize)) {
+        let width = grid.width();
+        let blank = self.blank();
+        let cells = grid.cells_mut();
+        let end = (to.1 * width + to.0).min(cells.len());
+        cells[(from.1 * width + from.0).min(end)..end].fill(blank);
+    }
+
+    fn control(&mut self, grid: &mut TextModeGrid, byte: u8) {
+        match byte {
+            b'\r' => {
+                self.cursor.0 = 0;
+                self.wrap_pending = false;
+            }
+            b'\n' | 0x0B | 0x0C => {
+                self.cursor.0 = 0;
+                self.line_feed(grid);
+            }
+            b'\t' => {
+                self.cursor.0 = ((self.cursor.0 / TAB_WIDTH + 1) * TAB_WIDTH).min(grid.width() - 1);
+                self.wrap_pending = false;
+            }
+            0x08 => {
+                self.cursor.0 = self.cursor.0.saturating_sub(1);
+                self.wrap_pending = false;
+            }
+            _ => {}
+        }
+    }
+
+    fn esc(&mut self, grid: &mut TextModeGrid, action: u8) {
+        match action {
+            b'7' => self.saved = (self.cursor, self.attributes),
+            b'8' => {
+                (self.cursor, self.attributes) = self.saved;
+                self.clamp_cursor(grid);
+            }
+            b'D' => self.line_feed(grid),
+            b'E' => {
+                self.cursor.0 = 0;
+                self.line_feed(grid);
+            }
+            b'M' => self.reverse_line_feed(grid),
+            b'c' => {
+                *self = Self {
+                    charset: std::mem::take(&mut self.charset),
+                    default_bg: self.default_bg,
+                    default_fg: self.default_fg,
+                    ice_colors: self.ice_colors,
+                    cursor_shape: self.cursor_shape,
+                    cursor_blink: self.cursor_blink,
+                    cleared: true,
+                    ..default()
+                };
+                grid.fill(self.blank());
+            }
+            _ => {}
+        }
+    }
+
+    fn csi(&mut self, grid: &mut TextModeGrid, params: &[u16], action: u8) {
+        let n = ansi_param(params, 0, 1) as usize;
+        let (x, y) = self.cursor;
+        let (width, height) = (grid.width(), grid.height());
+        match action {
+            b'A' => self.cursor.1 = y.saturating_sub(n),
+            b'B' => self.cursor.1 = y + n,
+            b'C' => self.cursor.0 = x + n,
+            b'D' => self.cursor.0 = x.saturating_sub(n),
+            b'E' => self.cursor = (0, y + n),
+            b'F' => self.cursor = (0, y.saturating_sub(n)),
+            b'G' | b'`' => self.cursor.0 = n - 1,
+            b'd' => self.cursor.1 = n - 1,
+            b'H' | b'f' => {
+                self.cursor = (
+                    ansi_param(params, 1, 1) as usize - 1,
+                    ansi_param(params, 0, 1) as usize - 1,
+                );
+            }
+            b'J' => match params.first().copied().unwrap_or(0) {
+                0 => self.erase(grid, (x, y), (0, height)),
+                1 => self.erase(grid, (0, 0), (x + 1, y)),
+                2 => self.erase(grid, (0, 0), (0, height)),
+                _ => grid.clear_scrollback(),
+            },
+            b'K' => match params.first().copied().unwrap_or(0) {
+                0 => self.erase(grid, (x, y), (0, y + 1)),
+                1 => self.erase(grid, (0, y), (x + 1, y)),
+                _ => self.erase(grid, (0, y), (0, y + 1)),
+            },
+            b'X' => self.erase(grid, (x, y), ((x + n).min(width), y)),
+            b'@' | b'P' => {
+                let row = &mut grid.cells_mut()[y * width + x..(y + 1) * width];
+                let n = n.min(row.len());
+                let blank = self.blank();
+                if action == b'@' {
+                    row.rotate_right(n);
+                    row[..n].fill(blank);
+                } else {
+                    row.rotate_left(n);
+                    let len = row.len();
+                    row[len - n..].fill(blank);
+                }
+            }
+            b'L' | b'M' => {
+                let (top, bottom) = self.region(grid);
+                if (top..=bottom).contains(&y) {
+                    let n = if action == b'L' { -(n as isize) } else { n as isize };
+                    grid.shift_rows(y, bottom, n, self.blank());
+                    self.cursor.0 = 0;
+                }
+            }
+            b'S' => self.scroll_up(grid, n),
+            b'T' => self.scroll_down(grid, n),
+            b'm' => {
+                // Keeps a pending wrap, colors often change right after the last column
+                self.attributes.apply_sgr(params);
+                return;
+            }
+            b'r' => {
+                let top = ansi_param(params, 0, 1) as usize - 1;
+                let bottom = (ansi_param(params, 1, height as u16) as usize).min(height) - 1;
+                if top < bottom {
+                    self.scroll_region = (top > 0 || bottom < height - 1).then_some((top, bottom));
+                    self.cursor = (0, 0);
+                }
+            }
+            b's' => self.saved.0 = self.cursor,
+            b'u' => self.cursor = self.saved.0,
+            _ => return,
+        }
+        self.clamp_cursor(grid);
+    }
+}
+
+/// Applies the queued bytes of every terminal to its grid
+pub(crate) fn flush_text_mode_terminals(mut terminals: Query<(&mut TextModeTerminal, &mut TextModeGrid)>) {
+    for (mut terminal, mut grid) in &mut terminals {
+        if terminal.has_pending_input() || !terminal.cleared {
+            terminal.flush(&mut grid);
+        }
+    }
+}
+
+#[derive(Bundle, Clone, Default)]
+pub struct TextModeTerminalBundle {
+    pub terminal: TextModeTerminal,
+    pub grid: TextModeGridBundle,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TextModeBlink;
+
+    /// A terminal and its `width` × `height` grid, with `bytes` applied
+    fn setup(width: usize, height: usize, bytes: &str) -> (TextModeTerminal, TextModeGrid) {
+        let mut terminal = TextModeTerminal::default();
+        let mut grid = TextModeGrid::new(width, height, Handle::default());
+        terminal.write(bytes);
+        terminal.flush(&mut grid);
+        (terminal, grid)
+    }
+
+    fn run(terminal: &mut TextModeTerminal, grid: &mut TextModeGrid, bytes: &str) {
+        terminal.write(bytes);
+        terminal.flush(grid);
+    }
+
+    /// Row `y` of the grid as text
+    fn row(grid: &TextModeGrid, y: usize) -> String {
+        (0..grid.width()).map(|x| grid.get(x, y).unwrap().index as u8 as char).collect()
+    }
+
+    #[test]
+    fn cursor_movement() {
+        let (mut terminal, mut grid) = setup(10, 5, "\x1b[3;5H");
+        assert_eq!(terminal.cursor(), (4, 2));
+        assert_eq!(grid.cursor.map(|c| (c.x, c.y)), Some((4, 2)));
+
+        // Missing and zero parameters are 1
+        for (bytes, cursor) in [
+            ("\x1b[H", (0, 0)),
+            ("\x1b[0;0H", (0, 0)),
+            ("\x1b[;3H", (2, 0)),
+            ("\x1b[2B\x1b[3C", (5, 2)),
+            ("\x1b[A", (5, 1)),
+            ("\x1b[0A", (5, 0)),
+            ("\x1b[A", (5, 0)),
+            ("\x1b[D", (4, 0)),
+            ("\x1b[0D", (3, 0)),
+            ("\x1b[8G", (7, 0)),
+            ("\x1b[G", (0, 0)),
+            ("\x1b[0G", (0, 0)),
+            ("\x1b[2E", (0, 2)),
+            ("\x1b[4G\x1b[F", (0, 1)),
+            // Out of bounds positions are clamped
+            ("\x1b[99;99H", (9, 4)),
+            ("\x1b[99A", (9, 0)),
+            ("\x1b[99G", (9, 0)),
+        ] {
+            run(&mut terminal, &mut grid, bytes);
+            assert_eq!(terminal.cursor(), cursor, "{bytes:?}");
+        }
+
+        run(&mut terminal, &mut grid, "\x1b[?25l");
+        assert!(!terminal.cursor_visible());
+        assert_eq!(grid.cursor, None);
+    }
+
+    #[test]
+    fn deferred_wrap() {
+        let (terminal, grid) = setup(3, 2, "abc");
+        assert_eq!(terminal.cursor(), (2, 0));
+        assert_eq!(row(&grid, 1), "   ");
+
+        let (terminal, grid) = setup(3, 3, "abcd\r\nef");
+        assert_eq!(terminal.cursor(), (2, 2));
+        assert_eq!([0, 1, 2].map(|y| row(&grid, y)), ["abc", "d  ", "ef "]);
+    }
+
+    #[test]
+    fn sgr_colors() {
+        let (_, grid) = setup(
+            16,
+            1,
+            "\x1b[31;44mA\x1b[1mB\x1b[38;5;196;48;5;21mC\x1b[38;2;1;2;3;48;2;4;5;6mD\x1b[0mE\x1b[32m\x1b[mF\x1b[7;33mG\x1b[27;39;5mH\x1b[92;103mI",
+        );
+        let colors = |x| {
+            let cell = grid.get(x, 0).unwrap();
+            (cell.fg, cell.bg)
+        };
+        assert_eq!(colors(0), (ansi_color(1), ansi_color(4)));
+        // Bold selects the high intensity foreground
+        assert_eq!(colors(1), (ansi_color(9), ansi_color(4)));
+        assert_eq!(colors(2), (ansi_color(196), ansi_color(21)));
+        assert_eq!(colors(3), (Srgba::rgb_u8(1, 2, 3).into(), Srgba::rgb_u8(4, 5, 6).into()));
+        assert_eq!(colors(4), (ansi_color(7), ansi_color(0)));
+        assert_eq!(colors(5), (ansi_color(7), ansi_color(0)));
+        assert_eq!(colors(6), (ansi_color(0), ansi_color(3)));
+        assert_eq!(colors(7), (ansi_color(7), ansi_color(0)));
+        assert_eq!(grid.get(7, 0).unwrap().blink, TextModeBlink::Fg);
+        assert_eq!(colors(8), (ansi_color(10), ansi_color(11)));
+    }
+
+    #[test]
+    fn erase_line() {
+        for (bytes, expected) in [
+            ("\x1b[K", "ABC     "),
+            ("\x1b[0K", "ABC     "),
+            ("\x1b[1K", "    EFGH"),
+            ("\x1b[2K", "        "),
+            ("\x1b[2X", "ABC  FGH"),
+            ("\x1b[9X", "ABC     "),
+        ] {
+            let (_, grid) = setup(8, 2, &format!("ABCDEFGH\x1b[2;1HABCDEFGH\x1b[1;4H{bytes}"));
+            assert_eq!(row(&grid, 0), expected, "{bytes:?}");
+            assert_eq!(row(&grid, 1), "ABCDEFGH", "{bytes:?}");
+        }
+    }
+
+    #[test]
+    fn erase_display() {
+        let text = "ABCD\x1b[2;1HEFGH\x1b[3;1HIJKL\x1b[2;2H";
+        for (bytes, expected) in [
+            ("\x1b[J", ["ABCD", "E   ", "    "]),
+            ("\x1b[1J", ["    ", "  GH", "IJKL"]),
+            ("\x1b[2J", ["    ", "    ", "    "]),
+            ("\x1b[3J", ["ABCD", "EFGH", "IJKL"]),
+        ] {
+            let (terminal, grid) = setup(4, 3, &format!("{text}{bytes}"));
+            assert_eq!([0, 1, 2].map(|y| row(&grid, y)), expected, "{bytes:?}");
+            assert_eq!(terminal.cursor(), (1, 1), "{bytes:?}");
+        }
+
+        // Erased cells take the current background color
+        let (_, grid) = setup(4, 3, "\x1b[44m\x1b[2J");
+        assert!(grid.cells().iter().all(|c| c.bg == ansi_color(4)));
+
+        let mut terminal = TextModeTerminal::default();
+        let mut grid = TextModeGrid::new(4, 2, Handle::default());
+        grid.scrollback_limit = 10;
+        run(&mut terminal, &mut grid, "A\nB\nC\nD");
+        assert_eq!(grid.scrollback().len(), 2);
+        run(&mut terminal, &mut grid, "\x1b[3J");
+        assert!(grid.scrollback().is_empty());
+        assert_eq!([row(&grid, 0), row(&grid, 1)], ["C   ", "D   "]);
+    }
+
+    #[test]
+    fn scroll_region() {
+        let (mut terminal, mut grid) = setup(3, 5, "top\x1b[5;1Hbot\x1b[2;4r");
+        // Setting the region homes the cursor
+        assert_eq!(terminal.cursor(), (0, 0));
+
+        run(&mut terminal, &mut grid, "\x1b[2;1Ha\nb\nc");
+        assert_eq!(terminal.cursor(), (1, 3));
+        // A line feed at the bottom margin only scrolls the region
+        run(&mut terminal, &mut grid, "\nd");
+        assert_eq!(terminal.cursor(), (1, 3));
+        assert_eq!((0..5).map(|y| row(&grid, y)).collect::<Vec<_>>(), ["top", "b  ", "c  ", "d  ", "bot"]);
+
+        // Below the region the cursor stops at the last row without scrolling
+        run(&mut terminal, &mut grid, "\x1b[5;1H\n\n");
+        assert_eq!(terminal.cursor(), (0, 4));
+        assert_eq!(row(&grid, 0), "top");
+
+        // A reverse line feed at the top margin scrolls the region down
+        run(&mut terminal, &mut grid, "\x1b[2;1H\x1bM");
+        assert_eq!((0..5).map(|y| row(&grid, y)).collect::<Vec<_>>(), ["top", "   ", "b  ", "c  ", "bot"]);
+
+        // Resetting the region scrolls the whole grid
+        run(&mut terminal, &mut grid, "\x1b[r\x1b[5;1H\n");
+        assert_eq!((0..5).map(|y| row(&grid, y)).collect::<Vec<_>>(), ["   ", "b  ", "c  ", "bot", "   "]);
+    }
+
+    #[test]
+    fn insert_and_delete_characters() {
+        for (bytes, expected) in [
+            ("\x1b[1;5H\x1b[@", "ABCD "),
+            ("\x1b[1;5H\x1b[P", "ABCD "),
+            ("\x1b[1;5H\x1b[9@", "ABCD "),
+            ("\x1b[1;5H\x1b[9P", "ABCD "),
+            ("\x1b[1;4H\x1b[9@", "ABC  "),
+            ("\x1b[1;2H\x1b[2@", "A  BC"),
+            ("\x1b[1;2H\x1b[2P", "ADE  "),
+            ("\x1b[1;2H\x1b[0P", "ACDE "),
+        ] {
+            let (_, grid) = setup(5, 2, &format!("ABCDE\x1b[2;1HABCDE{bytes}"));
+            assert_eq!(row(&grid, 0), expected, "{bytes:?}");
+            assert_eq!(row(&grid, 1), "ABCDE", "{bytes:?}");
+        }
+    }
+
+    #[test]
+    fn split_sequences() {
+        let (mut terminal, mut grid) = setup(8, 3, "\x1b[3");
+        run(&mut terminal, &mut grid, "1mA\x1b");
+        run(&mut terminal, &mut grid, "[2;");
+        run(&mut terminal, &mut grid, "3HB");
+        assert_eq!(grid.get(0, 0).unwrap().fg, ansi_color(1));
+        assert_eq!(grid.get(0, 0).unwrap().index, b'A' as usize);
+        assert_eq!(grid.get(2, 1).unwrap().index, b'B' as usize);
+
+        // UTF-8 characters split across writes
+        let bytes = "é".as_bytes();
+        terminal.write(&bytes[..1]);
+        terminal.flush(&mut grid);
+        terminal.write(&bytes[1..]);
+        terminal.flush(&mut grid);
+        assert_eq!(grid.get(3, 1).unwrap().index, terminal.charset.index('é').unwrap());
+
+        // Several writes before a flush
+        terminal.write("\x1b[");
+        terminal.write("3;1");
+        terminal.write("HC");
+        assert!(terminal.has_pending_input());
+        terminal.flush(&mut grid);
+        assert!(!terminal.has_pending_input());
+        assert_eq!(grid.get(0, 2).unwrap().index, b'C' as usize);
+    }
+}
@@ -0,0 +1,300 @@
+use bevy::prelude::*;
+
+use crate::Charset;
+
+/// Horizontal alignment of the lines of a [`TextModeTextLayout`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+pub enum TextModeAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+    /// Spreads the words over the whole width, except on the last line of a paragraph
+    Justify,
+}
+
+/// How lines longer than a [`TextModeTextLayout`] are broken
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+pub enum TextModeWrap {
+    /// Breaks lines between words, words longer than a line are broken between characters.
+    ///
+    /// Words are separated by single spaces: leading, trailing and repeated spaces are dropped.
+    /// Use [`TextModeWrap::Char`] or [`TextModeWrap::None`] to keep them, e.g. for indentation.
+    #[default]
+    Word,
+    /// Breaks lines between any characters
+    Char,
+    /// Cuts lines at the right of the rect
+    None,
+}
+
+/// Lays out text into a rect of cells.
+///
+/// `'\n'` starts a new paragraph. Characters missing from the charset are skipped,
+/// spaces take a cell even if the charset has no glyph for them.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Reflect)]
+pub struct TextModeTextLayout {
+    /// Cells the text is laid out in, `max` being excluded
+    pub rect: URect,
+    pub align: TextModeAlign,
+    pub wrap: TextModeWrap,
+}
+
+/// Cells written by a [`TextModeTextLayout`]
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TextModeLaidOutText {
+    /// `(x, y, index)` positions and atlas indices of the written cells
+    pub cells: Vec<(usize, usize, usize)>,
+    /// Number of lines of the laid out text, including the lines below the rect
+    pub lines: usize,
+    /// Whether some characters did not fit in the rect
+    pub overflow: bool,
+}
+
+/// A laid out line: its words, and whether it ends a paragraph
+struct Line {
+    words: Vec<Vec<char>>,
+    last: bool,
+}
+
+impl Line {
+    fn len(&self) -> usize {
+        self.words.iter().map(Vec::len).sum::<usize>() + self.words.len().saturating_sub(1)
+    }
+}
+
+impl TextModeTextLayout {
+    pub fn new(rect: URect) -> Self {
+        Self {
+            rect,
+            ..default()
+        }
+    }
+
+    /// Breaks `text` into lines and positions its characters in the rect
+    pub fn layout(&self, text: &str, charset: &impl Charset) -> TextModeLaidOutText {
+        let width = self.rect.width() as usize;
+        let height = self.rect.height() as usize;
+        let mut overflow = false;
+
+        let mut lines = Vec::new();
+        for paragraph in text.lines() {
+            let chars: Vec<char> = paragraph
+                .chars()
+                .filter(|&c| c == ' ' || charset.index(c).is_some())
+                .collect();
+            let start = lines.len();
+            match self.wrap {
+                TextModeWrap::Word => wrap_words(&chars, width, &mut lines),
+                TextModeWrap::Char => {
+                    lines.extend(chars.chunks(width.max(1)).map(|chunk| Line {
+                        words: vec![chunk.to_vec()],
+                        last: false,
+                    }));
+                }
+                TextModeWrap::None => {
+                    overflow |= chars.len() > width;
+                    lines.push(Line {
+                        words: vec![chars[..chars.len().min(width)].to_vec()],
+                        last: false,
+                    });
+                }
+            }
+            if lines.len() == start {
+                lines.push(Line {
+                    words: Vec::new(),
+                    last: false,
+                });
+            }
+            if let Some(line) = lines.last_mut() {
+                line.last = true;
+            }
+        }
+
+        // Nothing fits in an empty rect
+        let visible = if width == 0 { 0 } else { height };
+        overflow |= lines.iter().skip(visible).any(|line| !line.words.iter().all(Vec::is_empty));
+
+        let mut cells = Vec::new();
+        for (y, line) in lines.iter().take(visible).enumerate() {
+            let len = line.len();
+            let free = width.saturating_sub(len);
+            let gaps = line.words.len().saturating_sub(1);
+            let x = match self.align {
+                TextModeAlign::Left | TextModeAlign::Justify => 0,
+                TextModeAlign::Center => free / 2,
+                TextModeAlign::Right => free,
+            };
+
+            let mut x = self.rect.min.x as usize + x;
+            let y = self.rect.min.y as usize + y;
+            for (i, word) in line.words.iter().enumerate() {
+                for &c in word {
+                    if let Some(index) = charset.index(c) {
+                        cells.push((x, y, index));
+                    }
+                    x += 1;
+                }
+                if i == gaps {
+                    break;
+                }
+                let gap = match self.align {
+                    TextModeAlign::Justify if !line.last => 1 + free / gaps + usize::from(i < free % gaps),
+                    _ => 1,
+                };
+                if let Some(index) = charset.index(' ') {
+                    cells.extend((x..x + gap).map(|x| (x, y, index)));
+                }
+                x += gap;
+            }
+        }
+
+        TextModeLaidOutText {
+            cells,
+            lines: lines.len(),
+            overflow,
+        }
+    }
+}
+
+/// Greedily fills lines of `width` cells with the words of `chars`
+fn wrap_words(chars: &[char], width: usize, lines: &mut Vec<Line>) {
+    let width = width.max(1);
+    let mut line: Vec<Vec<char>> = Vec::new();
+    let mut len = 0;
+    for word in chars.split(|&c| c == ' ').filter(|word| !word.is_empty()) {
+        if !line.is_empty() && len + 1 + word.len() > width {
+            lines.push(Line {
+                words: std::mem::take(&mut line),
+                last: false,
+            });
+        }
+        if line.is_empty() {
+            // Words longer than a line are broken between characters
+            let mut chunks = word.chunks(width).peekable();
+            while let Some(chunk) = chunks.next() {
+                if chunks.peek().is_none() {
+                    len = chunk.len();
+                    line.push(chunk.to_vec());
+                } else {
+                    lines.push(Line {
+                        words: vec![chunk.to_vec()],
+                        last: false,
+                    });
+                }
+            }
+        } else {
+            len += 1 + word.len();
+            line.push(word.to_vec());
+        }
+    }
+    if !line.is_empty() {
+        lines.push(Line {
+            words: line,
+            last: false,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Cp437Charset;
+
+    /// Lays out `text` in a `width` × `height` rect at the origin and draws the written cells, `.` being unwritten
+    fn layout(layout: TextModeTextLayout, text: &str) -> (Vec<String>, TextModeLaidOutText) {
+        let laid_out = layout.layout(text, &Cp437Charset);
+        let size = layout.rect.max;
+        let mut rows = vec![vec!['.'; size.x as usize]; size.y as usize];
+        for &(x, y, index) in &laid_out.cells {
+            rows[y][x] = index as u8 as char;
+        }
+        (rows.into_iter().map(String::from_iter).collect(), laid_out)
+    }
+
+    fn rect(width: u32, height: u32) -> TextModeTextLayout {
+        TextModeTextLayout::new(URect::new(0, 0, width, height))
+    }
+
+    #[test]
+    fn word_wrap() {
+        let (rows, laid_out) = layout(rect(10, 3), "the quick brown fox");
+        assert_eq!(rows, ["the quick.", "brown fox.", ".........."]);
+        assert_eq!(laid_out.lines, 2);
+        assert!(!laid_out.overflow);
+
+        // Extra spaces between words are collapsed, leading and trailing spaces are dropped
+        let (rows, _) = layout(rect(10, 1), "  a    b  ");
+        assert_eq!(rows, ["a b......."]);
+        let (rows, laid_out) = layout(rect(4, 3), "ab\n  cd\n   ");
+        assert_eq!(rows, ["ab..", "cd..", "...."]);
+        assert_eq!(laid_out.lines, 3);
+
+        // Words longer than a line are broken between characters
+        let (rows, laid_out) = layout(rect(4, 3), "ab abcdefghij");
+        assert_eq!(rows, ["ab..", "abcd", "efgh"]);
+        assert_eq!(laid_out.lines, 4);
+        assert!(laid_out.overflow);
+    }
+
+    #[test]
+    fn hard_breaks() {
+        let (rows, laid_out) = layout(rect(5, 4), "ab\n\ncd ef\r\ngh");
+        assert_eq!(rows, ["ab...", ".....", "cd ef", "gh..."]);
+        assert_eq!(laid_out.lines, 4);
+        assert!(!laid_out.overflow);
+    }
+
+    #[test]
+    fn char_and_no_wrap() {
+        let mut layout_char = rect(4, 2);
+        layout_char.wrap = TextModeWrap::Char;
+        let (rows, laid_out) = layout(layout_char, "ab cdefg");
+        assert_eq!(rows, ["ab c", "defg"]);
+        assert!(!laid_out.overflow);
+
+        let mut layout_none = rect(4, 2);
+        layout_none.wrap = TextModeWrap::None;
+        let (rows, laid_out) = layout(layout_none, "abcdef\ngh");
+        assert_eq!(rows, ["abcd", "gh.."]);
+        assert!(laid_out.overflow);
+
+        // Leading and repeated spaces are kept
+        let (rows, _) = layout(layout_none, "  a  b");
+        assert_eq!(rows, ["  a ", "...."]);
+        let (rows, _) = layout(layout_char, " a  b");
+        assert_eq!(rows, [" a  ", "b..."]);
+    }
+
+    #[test]
+    fn alignment() {
+        let mut aligned = rect(9, 2);
+        aligned.align = TextModeAlign::Center;
+        assert_eq!(layout(aligned, "ab cd ef\nabc").0, ["ab cd ef.", "...abc..."]);
+        aligned.align = TextModeAlign::Right;
+        assert_eq!(layout(aligned, "ab cd ef\nabc").0, [".ab cd ef", "......abc"]);
+
+        // The last line of a paragraph is not justified
+        aligned.align = TextModeAlign::Justify;
+        assert_eq!(layout(aligned, "ab c d efg hi").0, ["ab   c  d", "efg hi..."]);
+    }
+
+    #[test]
+    fn overflow() {
+        // Lines fitting exactly do not overflow, neither do trailing empty lines
+        for text in ["ab\ncd", "ab\ncd\n", "ab\ncd\n\n\n"] {
+            let (rows, laid_out) = layout(rect(2, 2), text);
+            assert_eq!(rows, ["ab", "cd"]);
+            assert!(!laid_out.overflow, "{text:?}");
+        }
+
+        let (_, laid_out) = layout(rect(2, 2), "ab\ncd\n\ne");
+        assert_eq!(laid_out.lines, 4);
+        assert!(laid_out.overflow);
+
+        let (_, laid_out) = layout(rect(0, 2), "a");
+        assert!(laid_out.cells.is_empty());
+        assert!(laid_out.overflow);
+        assert!(!layout(rect(0, 2), "").1.overflow);
+    }
+}
@@ -1,6 +1,72 @@
 use bevy::prelude::*;
 use bevy::sprite::Anchor;
 
+use crate::TextModeBlink;
+
+/// Rule deciding which pixels of a glyph are drawn with the foreground color
+#[derive(Debug, Clone, Copy, PartialEq, Default, Reflect)]
+pub enum TextModeMask {
+    /// Pixels with a non-zero red channel
+    #[default]
+    Red,
+    /// Pixels with a luminance above the threshold
+    Luminance(f32),
+    /// Pixels with an alpha above the threshold
+    Alpha(f32),
+    /// Pixels of any color but the key color
+    Key(LinearRgba),
+}
+
+/// Maximum number of colors of a [`TextModeSpritePalette`]
+pub const MAX_SPRITE_PALETTE_LEN: usize = 4;
+
+/// Indexed palette of up to [`MAX_SPRITE_PALETTE_LEN`] colors, stored inline
+#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
+pub struct TextModeSpritePalette {
+    colors: [LinearRgba; MAX_SPRITE_PALETTE_LEN],
+    len: u8,
+}
+
+impl TextModeSpritePalette {
+    /// Palette of the given colors, from the darkest to the lightest shade.
+    /// Colors past [`MAX_SPRITE_PALETTE_LEN`] are a bug: they panic in debug builds and are dropped otherwise.
+    pub fn new(colors: &[LinearRgba]) -> Self {
+        debug_assert!(
+            colors.len() <= MAX_SPRITE_PALETTE_LEN,
+            "sprite palette has {} colors, the maximum is {MAX_SPRITE_PALETTE_LEN}",
+            colors.len()
+        );
+        let len = colors.len().min(MAX_SPRITE_PALETTE_LEN);
+        let mut palette = Self::default();
+        palette.colors[..len].copy_from_slice(&colors[..len]);
+        palette.len = len as u8;
+        palette
+    }
+
+    pub fn colors(&self) -> &[LinearRgba] {
+        &self.colors[..self.len as usize]
+    }
+
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Colors padded with transparent black, and their count
+    pub(crate) fn padded(&self) -> ([LinearRgba; MAX_SPRITE_PALETTE_LEN], u32) {
+        (self.colors, self.len as u32)
+    }
+}
+
+impl Default for TextModeSpritePalette {
+    fn default() -> Self {
+        Self { colors: [LinearRgba::NONE; MAX_SPRITE_PALETTE_LEN], len: 0 }
+    }
+}
+
 #[derive(Component, Debug, Clone, Reflect)]
 pub struct TextModeSprite {
     pub bg: LinearRgba,
@@ -12,6 +78,20 @@ pub struct TextModeSprite {
     pub custom_size: Option<Vec2>,
     pub rect: Option<Rect>,
     pub anchor: Anchor,
+    /// Indexed palette of up to 4 colors, from the darkest to the lightest shade.
+    /// When not empty, the red channel of the glyph selects a palette color instead of `bg` / `fg`.
+    pub palette: TextModeSpritePalette,
+    pub mask: TextModeMask,
+    /// Swaps the foreground and background pixels of the mask
+    pub invert_mask: bool,
+    /// Discards the background pixels instead of drawing them with `bg`
+    pub skip_bg: bool,
+    /// Discards the foreground pixels instead of drawing them with `fg`
+    pub skip_fg: bool,
+    /// Mixes `bg` and `fg` by the mask value instead of picking one of them,
+    /// for anti-aliased or filtered glyphs
+    pub coverage: bool,
+    pub blink: TextModeBlink,
 }
 
 impl Default for TextModeSprite {
@@ -26,6 +106,13 @@ impl Default for TextModeSprite {
             custom_size: None,
             rect: None,
             anchor: Anchor::default(),
+            palette: TextModeSpritePalette::default(),
+            mask: TextModeMask::default(),
+            invert_mask: false,
+            skip_bg: false,
+            skip_fg: false,
+            coverage: false,
+            blink: TextModeBlink::None,
         }
     }
 }
@@ -40,4 +127,23 @@ pub struct TextModeSpriteBundle {
     pub visibility: Visibility,
     pub inherited_visibility: InheritedVisibility,
     pub view_visibility: ViewVisibility,
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sprite_palette() {
+        let palette = TextModeSpritePalette::new(&[LinearRgba::BLACK, LinearRgba::WHITE]);
+        assert_eq!(palette.colors(), &[LinearRgba::BLACK, LinearRgba::WHITE]);
+        assert_eq!(palette.padded(), ([LinearRgba::BLACK, LinearRgba::WHITE, LinearRgba::NONE, LinearRgba::NONE], 2));
+        assert!(TextModeSpritePalette::default().is_empty());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "the maximum is 4")]
+    fn sprite_palette_too_long() {
+        TextModeSpritePalette::new(&[LinearRgba::BLACK; 5]);
+    }
+}